@@ -1,50 +1,63 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use clearinghouse::ARData;
+use intake::{Clock, Money};
 
-use chrono;
 
-pub fn calculate_patient_statistics(data: &Vec<ARData>) -> (f64, f64, f64, usize) {
+/// Average copay, coinsurance and deductible per patient (each patient's average per claim,
+/// averaged over patients, rounded to the cent at each step) and the number of patients.
+pub fn calculate_patient_statistics(data: &[ARData]) -> (Money, Money, Money, usize) {
     if data.is_empty() {
-        return (0.0, 0.0, 0.0, 0);
+        return (Money::ZERO, Money::ZERO, Money::ZERO, 0);
     }
     
-    let mut patient_totals: HashMap<String, (f64, f64, f64, usize)> = HashMap::new();
+    let mut patient_totals: HashMap<String, (Money, Money, Money, usize)> = HashMap::new();
     
-    for ar in data.iter() {
-        let entry = patient_totals.entry(ar.patient_id.clone()).or_insert((0.0, 0.0, 0.0, 0));
+    // a crossed-over entry's patient amounts were passed on; the next payer's entry has what's left
+    for ar in data.iter().filter(|ar| ar.crossover_payer_id.is_none()) {
+        let entry = patient_totals.entry(ar.patient_id.clone()).or_insert((Money::ZERO, Money::ZERO, Money::ZERO, 0));
         entry.0 += ar.total_copay_amount;
         entry.1 += ar.total_coinsurance_amount;
         entry.2 += ar.total_deductible_amount;
-        entry.3 += 1;
+        // a reversal nets its original's amounts out and takes back its claim count
+        if ar.reversal {
+            entry.3 = entry.3.saturating_sub(1);
+        } else {
+            entry.3 += 1;
+        }
     }
+    // patients whose only claims were voided have nothing left to average
+    patient_totals.retain(|_, totals| totals.3 > 0);
     
     let num_patients = patient_totals.len();
     if num_patients == 0 {
-        return (0.0, 0.0, 0.0, 0);
+        return (Money::ZERO, Money::ZERO, Money::ZERO, 0);
     }
     
-    let (total_copay, total_coinsurance, total_deductible): (f64, f64, f64) = patient_totals
+    let (total_copay, total_coinsurance, total_deductible) = patient_totals
         .values()
-        .fold((0.0, 0.0, 0.0), |acc, &(copay, coinsurance, deductible, claims)| {
-            let avg_copay = copay / claims as f64;
-            let avg_coinsurance = coinsurance / claims as f64;
-            let avg_deductible = deductible / claims as f64;
+        .fold((Money::ZERO, Money::ZERO, Money::ZERO), |acc, &(copay, coinsurance, deductible, claims)| {
+            let avg_copay = copay.divided_by(claims);
+            let avg_coinsurance = coinsurance.divided_by(claims);
+            let avg_deductible = deductible.divided_by(claims);
             (acc.0 + avg_copay, acc.1 + avg_coinsurance, acc.2 + avg_deductible)
         });
     
     (
-        total_copay / num_patients as f64,
-        total_coinsurance / num_patients as f64,
-        total_deductible / num_patients as f64,
+        total_copay.divided_by(num_patients),
+        total_coinsurance.divided_by(num_patients),
+        total_deductible.divided_by(num_patients),
         num_patients
     )
 }
 
-pub fn calculate_aging_buckets(data: &Vec<ARData>) -> [u32; 4] {
-    let now = chrono::Utc::now().timestamp_millis();
+/// Ages claims against `clock`, which should be the clock that stamped their `initial_claim_ts`.
+/// Reversals, and the entries they reverse, are no longer outstanding and aren't aged.
+pub fn calculate_aging_buckets(data: &[ARData], clock: &dyn Clock) -> [u32; 4] {
+    let now = clock.now_millis();
     let mut buckets = [0u32; 4];
+    let reversed: HashSet<&str> = data.iter().filter(|ar| ar.reversal).map(|ar| ar.remittance_id.as_str()).collect();
     
-    for ar in data.iter() {
+    for ar in data.iter().filter(|ar| !reversed.contains(ar.remittance_id.as_str())) {
         let minutes_old = (now - ar.initial_claim_ts) as f64 / (1000.0 * 60.0);
         let bucket_idx = match minutes_old {
             x if x <= 1.0 => 0,
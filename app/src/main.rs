@@ -1,8 +1,12 @@
-use intake::{Config, parse_line, read_file, TokenBucket, PayerClaim};
-use clearinghouse::{validate_claim, submit_claim_to_payer, submit_remittance_to_submitter, ARData};
+use intake::{Clock, Config, InputFormat, IntakeError, LinePosition, parse_line, read_file, read_x12_file, ResumePoint, TokenBucket, PayerClaim, PayerId};
+use clearinghouse::{check_eligibility, crossover_claim, submit_claim_to_payer, submit_remittance_to_submitter, ARData, ClaimValidator, ClearinghouseError, DuplicateCheck, DuplicateIndex, ExchangeRateTable, ModifierTable, NpiRegistry, PayerRegistry, PayerSettings, ProcedureCodeSet};
 use app::{calculate_aging_buckets, calculate_patient_statistics};
+use insurance::{AccumulatorStore, MemberRoster};
 
+use std::collections::hash_map::DefaultHasher;
 use std::env;
+use std::path::Path;
+use std::hash::{Hash, Hasher};
 use std::thread;
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -21,59 +25,106 @@ fn log_header(component: &str) -> String {
 
 struct WorkItem {
     claim: PayerClaim,
+    /// Offset of the line the claim was read from, handed back with its result
+    offset: Option<u64>,
 }
 
 enum WorkerMessage {
-    Process(WorkItem),
+    Process(Box<WorkItem>),
     Shutdown,
 }
 
+/// A claim as read from the input, or why it couldn't be. JSON lines carry where they were read
+/// from; claims from an 837P interchange don't.
+type Record = (Option<LinePosition>, Result<PayerClaim, IntakeError>);
+
 enum TaskMessage {
-    Claim(PayerClaim),
-    ParseError(String),
+    Claim { claim: Box<PayerClaim>, position: Option<LinePosition> },
+    /// Logged by the parser; the position lets the resume point move past the line
+    ParseError { position: Option<LinePosition> },
+    /// Reading stopped partway through the file; nothing after this was read
+    ReadError(IntakeError),
     EndOfFile,
 }
 
+// a retryable failure is tried this many times in total before the claim is dead-lettered
+const MAX_ATTEMPTS: u32 = 3;
+
+// claims handed to workers but not yet finished; past this the main thread stops taking claims
+// from the parser, whose bounded channel in turn stops it reading
+const MAX_IN_FLIGHT: usize = 1000;
+
 enum ResultMessage {
-    Completed { claim_id: String },
-    Error { claim_id: String, error: String },
+    /// `suspected_duplicate_of` names an earlier claim this one looks like a resubmission of
+    Completed { claim_id: String, suspected_duplicate_of: Option<String> },
+    /// Already submitted; not adjudicated again
+    Duplicate { claim_id: String, error: ClearinghouseError },
+    /// Permanent failure: the claim is returned to the submitter and never retried
+    Rejected { claim_id: String, error: ClearinghouseError },
+    /// Retryable failure that kept failing; parked for manual follow-up
+    DeadLettered { claim_id: String, error: ClearinghouseError, attempts: u32 },
+    /// Paid by its earlier payers, but crossing it over to `payer_id` failed; what they paid stands
+    CrossoverFailed { claim_id: String, payer_id: PayerId, error: ClearinghouseError, attempts: u32 },
     // Status(String),
 }
 
+/// Everything a worker needs to take a claim from validation to AR data, shared by all workers.
+struct ClaimPipeline {
+    validator: ClaimValidator,
+    duplicates: DuplicateIndex,
+    payers: Arc<PayerRegistry>,
+    /// Paces retries on the same clock the payers use
+    clock: Arc<dyn Clock>,
+}
+
+/// Each worker has its own queue and every claim for a given member goes to the same worker, so a
+/// member's claims are adjudicated in the order they were read. That keeps deductible and
+/// out-of-pocket accumulation (and therefore a seeded run) independent of thread scheduling.
 struct ThreadPool {
     workers: Vec<thread::JoinHandle<()>>,
-    work_sender: mpsc::Sender<WorkerMessage>,
+    work_senders: Vec<mpsc::Sender<WorkerMessage>>,
 }
 
 impl ThreadPool {
     fn new(
         num_threads: usize,
-        result_sender: mpsc::Sender<ResultMessage>,
-        ar_data: Arc<Mutex<Vec<ARData>>>
+        result_sender: mpsc::Sender<(Option<u64>, ResultMessage)>,
+        ar_data: Arc<Mutex<Vec<ARData>>>,
+        pipeline: Arc<ClaimPipeline>,
     ) -> ThreadPool {
-        let (work_sender, work_receiver) = mpsc::channel::<WorkerMessage>();
-        let work_receiver = Arc::new(Mutex::new(work_receiver));
-        
         let mut workers = Vec::with_capacity(num_threads);
+        let mut work_senders = Vec::with_capacity(num_threads);
         
         for id in 0..num_threads {
-            let receiver = Arc::clone(&work_receiver);
+            let (work_sender, work_receiver) = mpsc::channel::<WorkerMessage>();
             let result_sender = result_sender.clone();
             let ar_data = Arc::clone(&ar_data);
+            let pipeline = Arc::clone(&pipeline);
             
             let worker = thread::spawn(move || {
-                worker_thread(id, receiver, result_sender, ar_data);
+                worker_thread(id, work_receiver, result_sender, ar_data, pipeline);
             });
             
             workers.push(worker);
+            work_senders.push(work_sender);
         }
         
-        ThreadPool { workers, work_sender }
+        ThreadPool { workers, work_senders }
+    }
+
+    fn dispatch(&self, item: Box<WorkItem>) -> Result<(), String> {
+        let mut hasher = DefaultHasher::new();
+        item.claim.insurance.patient_member_id.hash(&mut hasher);
+        let worker = (hasher.finish() % self.work_senders.len() as u64) as usize;
+
+        self.work_senders[worker]
+            .send(WorkerMessage::Process(item))
+            .map_err(|_| "Thread pool shutdown unexpectedly".to_string())
     }
     
     fn shutdown(self) {
-        for _ in 0..self.workers.len() {
-            let _ = self.work_sender.send(WorkerMessage::Shutdown);
+        for sender in &self.work_senders {
+            let _ = sender.send(WorkerMessage::Shutdown);
         }
         
         for worker in self.workers {
@@ -84,38 +135,95 @@ impl ThreadPool {
 
 fn worker_thread(
     worker_id: usize,
-    work_receiver: Arc<Mutex<mpsc::Receiver<WorkerMessage>>>,
-    result_sender: mpsc::Sender<ResultMessage>,
+    work_receiver: mpsc::Receiver<WorkerMessage>,
+    result_sender: mpsc::Sender<(Option<u64>, ResultMessage)>,
     ar_data: Arc<Mutex<Vec<ARData>>>,
+    pipeline: Arc<ClaimPipeline>,
 ) {
     eprintln!("{} Worker {} started", log_header("worker"), worker_id);
     
     loop {
-        let work_item = {
-            let receiver = work_receiver.lock().unwrap();
-            receiver.recv()
-        };
+        let work_item = work_receiver.recv();
         
         match work_item {
             Ok(WorkerMessage::Process(item)) => {
                 eprintln!("{} Worker {} received claim {}", log_header("worker"), worker_id, item.claim.claim_id);
-                
-                match process_claim_direct(&item.claim) {
-                    Ok(ar_data_item) => {
-                        ar_data.lock().unwrap().push(ar_data_item);
-                        eprintln!("{} Worker {} completed claim {}", log_header("worker"), worker_id, item.claim.claim_id);
-                        let _ = result_sender.send(ResultMessage::Completed {
-                            claim_id: item.claim.claim_id.clone(),
-                        });
+                let claim_id = item.claim.claim_id.clone();
+
+                let suspected_duplicate_of = match pipeline.duplicates.reserve(&item.claim) {
+                    Ok(DuplicateCheck::New) => None,
+                    Ok(DuplicateCheck::Suspected { original_claim_id, reason }) => {
+                        eprintln!("{} Worker {} flagged claim {} as a suspected duplicate: {}", log_header("worker"), worker_id, claim_id, reason);
+                        Some(original_claim_id)
                     }
                     Err(e) => {
-                        eprintln!("{} Worker {} failed claim {}: {}", log_header("worker"), worker_id, item.claim.claim_id, e);
-                        let _ = result_sender.send(ResultMessage::Error {
-                            claim_id: item.claim.claim_id.clone(),
-                            error: e,
-                        });
+                        eprintln!("{} Worker {} skipped claim {}: {}", log_header("worker"), worker_id, claim_id, e);
+                        let _ = result_sender.send((item.offset, ResultMessage::Duplicate { claim_id, error: e }));
+                        continue;
                     }
+                };
+
+                // a 270/271 up front, so an uncovered member shows up before the payer denies the claim
+                let date_of_service = item.claim.date_of_service();
+                let insurance = &item.claim.insurance;
+                if let Ok(eligibility) = check_eligibility(&insurance.payer_id, &insurance.patient_member_id, date_of_service, &pipeline.payers)
+                    && !eligibility.status.is_active()
+                {
+                    eprintln!("{} Worker {} found member {} not covered on {} ({:?}); claim {} will be denied", log_header("worker"), worker_id, insurance.patient_member_id, date_of_service, eligibility.status, claim_id);
                 }
+
+                let mut claim = item.claim.clone();
+                let mut attempt = 1;
+                // once the primary payer has paid, the claim counts as submitted whatever happens to its
+                // crossovers, so a resubmission is caught as a duplicate instead of being paid again
+                let mut adjudicated = false;
+                let message = loop {
+                    match process_claim_direct(&claim, &pipeline.validator, &pipeline.payers) {
+                        Ok((ar_data_items, crossover)) => {
+                            ar_data.lock().unwrap().extend(ar_data_items);
+                            if !adjudicated {
+                                adjudicated = true;
+                                if let Err(e) = pipeline.duplicates.commit(&item.claim) {
+                                    eprintln!("{} Worker {} couldn't record claim {}: {}", log_header("worker"), worker_id, item.claim.claim_id, e);
+                                }
+                            }
+                            // the patient's share goes to their next coverage as a claim of its own, so
+                            // retrying it never resubmits to the payers that already paid
+                            if let Some(crossover) = crossover {
+                                eprintln!("{} Worker {} crossing claim {} over to {}", log_header("worker"), worker_id, claim_id, crossover.insurance.payer_id);
+                                claim = crossover;
+                                attempt = 1;
+                                continue;
+                            }
+                            eprintln!("{} Worker {} completed claim {}", log_header("worker"), worker_id, claim_id);
+                            break ResultMessage::Completed { claim_id, suspected_duplicate_of };
+                        }
+                        Err(e) if e.is_retryable() && attempt < MAX_ATTEMPTS => {
+                            // back off 1s, 2s, ... on the pipeline clock before trying again
+                            let backoff = Duration::from_secs(1 << (attempt - 1));
+                            eprintln!("{} Worker {} retrying claim {} in {:?} (attempt {} failed: {})", log_header("worker"), worker_id, claim_id, backoff, attempt, e);
+                            pipeline.clock.sleep(backoff);
+                            attempt += 1;
+                        }
+                        Err(e) if adjudicated => {
+                            eprintln!("{} Worker {} couldn't cross claim {} over to {} after {} attempts: {}", log_header("worker"), worker_id, claim_id, claim.insurance.payer_id, attempt, e);
+                            break ResultMessage::CrossoverFailed { claim_id, payer_id: claim.insurance.payer_id.clone(), error: e, attempts: attempt };
+                        }
+                        Err(e) if e.is_retryable() => {
+                            eprintln!("{} Worker {} dead-lettered claim {} after {} attempts: {}", log_header("worker"), worker_id, claim_id, attempt, e);
+                            break ResultMessage::DeadLettered { claim_id, error: e, attempts: attempt };
+                        }
+                        Err(e) => {
+                            eprintln!("{} Worker {} rejected claim {}: {}", log_header("worker"), worker_id, claim_id, e);
+                            break ResultMessage::Rejected { claim_id, error: e };
+                        }
+                    }
+                };
+                // only adjudicated claims count as submitted; a corrected resubmission of a failed one goes through
+                if !adjudicated {
+                    pipeline.duplicates.release(&item.claim);
+                }
+                let _ = result_sender.send((item.offset, message));
             }
             Ok(WorkerMessage::Shutdown) => {
                 eprintln!("{} Worker {} shutting down", log_header("worker"), worker_id);
@@ -129,9 +237,9 @@ fn worker_thread(
     }
 }
 
-fn display_ar_report(data: &Vec<ARData>, total_claims: usize) {
-    let buckets = calculate_aging_buckets(&data);
-    let (avg_copay, avg_coinsurance, avg_deductible, num_patients) = calculate_patient_statistics(&data);
+fn display_ar_report(data: &[ARData], total_claims: usize, clock: &dyn Clock) {
+    let buckets = calculate_aging_buckets(data, clock);
+    let (avg_copay, avg_coinsurance, avg_deductible, num_patients) = calculate_patient_statistics(data);
     
     println!("=== AR Aging Report ===");
     println!("Total Claims: {}", total_claims);
@@ -142,13 +250,14 @@ fn display_ar_report(data: &Vec<ARData>, total_claims: usize) {
     println!();
     println!("=== Patient Statistics ===");
     println!("Total Patients: {}", num_patients);
-    println!("Average Copay per Patient: ${:.2}", avg_copay);
-    println!("Average Coinsurance per Patient: ${:.2}", avg_coinsurance);
-    println!("Average Deductible per Patient: ${:.2}", avg_deductible);
+    println!("Average Copay per Patient: ${}", avg_copay);
+    println!("Average Coinsurance per Patient: ${}", avg_coinsurance);
+    println!("Average Deductible per Patient: ${}", avg_deductible);
     println!("========================");
 }
 
-fn ar_reporting_thread(ar_data: Arc<Mutex<Vec<ARData>>>) {
+fn ar_reporting_thread(ar_data: Arc<Mutex<Vec<ARData>>>, clock: Arc<dyn Clock>) {
+    // reports every 5 real seconds whatever the clock, so an instant clock doesn't spin
     loop {
         thread::sleep(Duration::from_secs(5));
         
@@ -160,37 +269,55 @@ fn ar_reporting_thread(ar_data: Arc<Mutex<Vec<ARData>>>) {
             continue;
         }
 
-        display_ar_report(&data, total_claims);
+        display_ar_report(&data, total_claims, clock.as_ref());
     }
 }
 
-fn parser_thread(lines: Vec<String>, config: &Config, task_sender: mpsc::SyncSender<TaskMessage>) {
+fn parser_thread(
+    records: impl Iterator<Item = Result<Record, IntakeError>>,
+    config: &Config,
+    clock: &dyn Clock,
+    task_sender: mpsc::SyncSender<TaskMessage>,
+) {
     eprintln!("{} Starting parser thread", log_header("parser"));
     let mut token_bucket = TokenBucket::new(config.rate_per_second, config.refill_rate);
     let mut parsed_count = 0;
     let mut error_count = 0;
     
-    for (line_num, line) in lines.into_iter().enumerate() {
+    for (record_num, record) in records.enumerate() {
         while !token_bucket.try_consume(1) {
             // eprintln!("{} Parser thread waiting for token bucket", log_header("parser"));
             thread::sleep(Duration::from_millis(config.rate_per_second as u64 * 1000));
         }
         
-        match parse_line(&line) {
-            Ok(claim) => {
+        let (position, claim) = match record {
+            Ok(record) => record,
+            Err(e @ IntakeError::Read { .. }) => {
+                eprintln!("{} {}", log_header("parser"), e);
+                let _ = task_sender.send(TaskMessage::ReadError(e));
+                break;
+            }
+            // a line the reader couldn't hand over; the error says which
+            Err(e) => (None, Err(e)),
+        };
+        match claim {
+            Ok(mut claim) => {
+                // received when it clears the rate limiter, on the same clock the payers and AR report use
+                claim.initial_claim_ts = clock.now_millis();
                 parsed_count += 1;
                 if parsed_count % 5 == 0 {
                     eprintln!("{} Parsed {} claims", log_header("parser"), parsed_count);
                 }
-                if task_sender.send(TaskMessage::Claim(claim)).is_err() {
+                if task_sender.send(TaskMessage::Claim { claim: Box::new(claim), position }).is_err() {
                     eprintln!("{} Task channel closed, stopping parser", log_header("parser"));
                     break;
                 }
             }
             Err(e) => {
                 error_count += 1;
-                eprintln!("{} Parse error on line {}: {}", log_header("parser"), line_num + 1, e);
-                if task_sender.send(TaskMessage::ParseError(format!("Failed to parse line: {}", e))).is_err() {
+                let record_num = position.map_or(record_num + 1, |position| position.line_number);
+                eprintln!("{} Parse error on record {}: {}", log_header("parser"), record_num, e);
+                if task_sender.send(TaskMessage::ParseError { position }).is_err() {
                     eprintln!("{} Task channel closed, stopping parser", log_header("parser"));
                     break;
                 }
@@ -210,56 +337,147 @@ fn main() -> Result<(), String> {
     eprintln!("{} Configuration loaded: file={}, threads={}, rate={}/sec", 
         log_header("config"), config.file_path, config.num_threads, config.rate_per_second);
     
-    eprintln!("{} Reading file: {}", log_header("file_io"), config.file_path);
-    let lines: Vec<String> = read_file(&config)
-        .map_err(|e| format!("Failed to read file: {}", e))?
-        .collect();
-    
-    let total_lines = lines.len();
-    eprintln!("{} File read complete: {} lines loaded", log_header("file_io"), total_lines);
+    eprintln!("{} Reading file: {} ({:?})", log_header("file_io"), config.file_path, config.input_format);
+    // JSON lines are read and parsed one at a time on the parser thread; an 837P interchange has to be parsed as a whole
+    let records: Box<dyn Iterator<Item = Result<Record, IntakeError>> + Send> = match config.input_format {
+        InputFormat::Json => {
+            let lines = read_file(&config).map_err(|e| format!("Failed to read file: {}", e))?;
+            if lines.offset() > 0 {
+                eprintln!("{} Resuming at byte {} (line {})", log_header("file_io"), lines.offset(), lines.line_number() + 1);
+            }
+            Box::new(lines.map(|line| line.map(|line| (Some(line.position), parse_line(&line.text)))))
+        }
+        InputFormat::X12 => {
+            let claims = read_x12_file(&config).map_err(|e| format!("Failed to read file: {}", e))?;
+            eprintln!("{} File read complete: {} records loaded", log_header("file_io"), claims.len());
+            Box::new(claims.into_iter().map(|claim| Ok((None, Ok(claim)))))
+        }
+    };
     
     let ar_data = Arc::new(Mutex::new(Vec::new()));
     let ar_data_clone = ar_data.clone();
     
     eprintln!("{} Creating worker thread pool with {} threads", log_header("thread_pool"), config.num_threads);
-    let (result_sender, result_receiver) = mpsc::channel::<ResultMessage>();
-    let thread_pool = ThreadPool::new(config.num_threads as usize, result_sender.clone(), ar_data.clone());
+    let (result_sender, result_receiver) = mpsc::channel::<(Option<u64>, ResultMessage)>();
+    // one accumulator store for the whole run so a member's deductible carries across their claims
+    let mut settings = match config.seed {
+        Some(seed) => {
+            eprintln!("{} Adjudicating with seed {}", log_header("thread_pool"), seed);
+            PayerSettings::with_seed(seed)
+        }
+        None => PayerSettings::new(),
+    };
+    let clock = config.clock.clock().map_err(|e| format!("Config error: {}", e))?;
+    eprintln!("{} Using {:?} clock", log_header("thread_pool"), config.clock);
+    settings.clock = Arc::clone(&clock);
+    settings.outage_rate = config.outage_rate;
+    if let Some((month, day)) = config.plan_year_start {
+        let accumulators = AccumulatorStore::with_plan_year_start(month, day).map_err(|e| format!("Config error: {}", e))?;
+        eprintln!("{} Plan years start on {:02}-{:02}", log_header("thread_pool"), month, day);
+        settings.accumulators = Arc::new(accumulators);
+    }
+    if let Some(ref path) = config.roster {
+        let roster = MemberRoster::load(Path::new(path)).map_err(|e| format!("Config error: {}", e))?;
+        eprintln!("{} Loaded {} members from roster {}", log_header("thread_pool"), roster.len(), path);
+        settings.roster = Arc::new(roster);
+    }
+    let mut validator = ClaimValidator::new();
+    if let Some(ref path) = config.npi_registry {
+        let registry = NpiRegistry::load(Path::new(path)).map_err(|e| format!("Config error: {}", e))?;
+        eprintln!("{} Loaded {} NPIs from registry {}", log_header("thread_pool"), registry.len(), path);
+        validator = validator.with_npi_registry(registry);
+    }
+    if let Some(ref path) = config.modifier_table {
+        let modifiers = ModifierTable::load(Path::new(path)).map_err(|e| format!("Config error: {}", e))?;
+        eprintln!("{} Loaded modifier table {}", log_header("thread_pool"), path);
+        validator = validator.with_modifier_table(modifiers);
+    }
+    if let Some(ref path) = config.procedure_codes {
+        let codes = ProcedureCodeSet::load(Path::new(path)).map_err(|e| format!("Config error: {}", e))?;
+        eprintln!("{} Loaded {} procedure codes from {}", log_header("thread_pool"), codes.len(), path);
+        validator = validator.with_procedure_codes(codes);
+    }
+    let duplicates = match config.duplicate_store {
+        Some(ref path) => {
+            let index = DuplicateIndex::open(Path::new(path)).map_err(|e| format!("Config error: {}", e))?;
+            eprintln!("{} Loaded {} previously adjudicated claims from {}", log_header("thread_pool"), index.len(), path);
+            index
+        }
+        None => DuplicateIndex::new(),
+    };
+    let mut payers = match config.payers {
+        Some(ref path) => {
+            let registry = PayerRegistry::load(Path::new(path), &settings).map_err(|e| format!("Config error: {}", e))?;
+            eprintln!("{} Loaded {} payers from {}", log_header("thread_pool"), registry.len(), path);
+            registry
+        }
+        None => PayerRegistry::with_builtins(&settings),
+    };
+    if let Some(ref path) = config.exchange_rates {
+        let rates = ExchangeRateTable::load(Path::new(path)).map_err(|e| format!("Config error: {}", e))?;
+        eprintln!("{} Loaded {} exchange rates from {}", log_header("thread_pool"), rates.len(), path);
+        payers = payers.with_exchange_rates(rates);
+    }
+    let payers = Arc::new(payers);
+    let validator = validator.with_payer_registry(Arc::clone(&payers));
+    let pipeline = ClaimPipeline { validator, duplicates, payers, clock: Arc::clone(&clock) };
+    let thread_pool = ThreadPool::new(
+        config.num_threads as usize,
+        result_sender.clone(),
+        ar_data.clone(),
+        Arc::new(pipeline),
+    );
     
     eprintln!("{} Starting AR reporting thread", log_header("reporting"));
+    let reporting_clock = Arc::clone(&clock);
     let _reporting_handle = thread::spawn(move || {
-        ar_reporting_thread(ar_data_clone);
+        ar_reporting_thread(ar_data_clone, reporting_clock);
     });
     
     eprintln!("{} Starting parser thread", log_header("coordination"));
     let (task_sender, task_receiver) = mpsc::sync_channel::<TaskMessage>(1000);
     let config_clone = config.clone();
+    let parser_clock = Arc::clone(&clock);
     let _parser_handle = thread::spawn(move || {
-        parser_thread(lines, &config_clone, task_sender);
+        parser_thread(records, &config_clone, parser_clock.as_ref(), task_sender);
     });
     
     let mut active_claims = 0usize;
     let mut processed_claims = 0usize;
     let mut parse_errors = 0usize;
+    let mut rejected_claims = 0usize;
+    let mut duplicate_claims = 0usize;
+    let mut suspected_duplicates: Vec<(String, String)> = Vec::new();
+    let mut dead_letters: Vec<(String, ClearinghouseError)> = Vec::new();
+    let mut failed_crossovers: Vec<(String, PayerId, ClearinghouseError)> = Vec::new();
     let mut parsing_complete = false;
+    let mut read_error = None;
+    let mut resume_point = ResumePoint::new(config.resume_offset.unwrap_or(0));
     
-    eprintln!("{} Main event loop starting: {} lines to process", log_header("coordination"), total_lines);
+    eprintln!("{} Main event loop starting", log_header("coordination"));
     eprintln!("{} Configuration: {} threads, {} claims/sec limit", log_header("coordination"), config.num_threads, config.rate_per_second);
     
     loop {
         // Try to get parsed claims from parser thread
-        if !parsing_complete {
+        if !parsing_complete && active_claims < MAX_IN_FLIGHT {
             match task_receiver.try_recv() {
-                Ok(TaskMessage::Claim(claim)) => {
-                    let work_item = WorkItem { claim };
-                    
-                    if thread_pool.work_sender.send(WorkerMessage::Process(work_item)).is_err() {
-                        return Err("Thread pool shutdown unexpectedly".to_string());
+                Ok(TaskMessage::Claim { claim, position }) => {
+                    if let Some(ref position) = position {
+                        resume_point.started(position);
                     }
+                    let work_item = Box::new(WorkItem { claim: *claim, offset: position.map(|position| position.offset) });
+                    thread_pool.dispatch(work_item)?;
                     active_claims += 1;
                 }
-                Ok(TaskMessage::ParseError(_error)) => {
+                Ok(TaskMessage::ParseError { position }) => {
+                    if let Some(ref position) = position {
+                        resume_point.skipped(position);
+                    }
                     parse_errors += 1;
                 }
+                Ok(TaskMessage::ReadError(error)) => {
+                    read_error = Some(error);
+                }
                 Ok(TaskMessage::EndOfFile) => {
                     parsing_complete = true;
                     eprintln!("{} Parsing phase complete: {} errors", log_header("coordination"), parse_errors);
@@ -272,20 +490,48 @@ fn main() -> Result<(), String> {
         }
         
         // Process worker results
-        match result_receiver.try_recv() {
-            Ok(ResultMessage::Completed { claim_id }) => {
+        let result = result_receiver.try_recv().map(|(offset, message)| {
+            if let Some(offset) = offset {
+                resume_point.finished(offset);
+            }
+            message
+        });
+        match result {
+            Ok(ResultMessage::Completed { claim_id, suspected_duplicate_of }) => {
                 active_claims -= 1;
                 processed_claims += 1;
                 eprintln!("{} Claim {} processed", log_header("coordination"), claim_id);
-                if processed_claims % 50 == 0 {
-                    eprintln!("{} Progress: {}/{} processed, {} active", 
-                        log_header("coordination"), processed_claims, total_lines - parse_errors, active_claims);
+                if let Some(original_claim_id) = suspected_duplicate_of {
+                    suspected_duplicates.push((claim_id, original_claim_id));
+                }
+                if processed_claims.is_multiple_of(50) {
+                    eprintln!("{} Progress: {} processed, {} active, resumable from byte {}",
+                        log_header("coordination"), processed_claims, active_claims, resume_point.offset());
                 }
             }
-            Ok(ResultMessage::Error { claim_id, error }) => {
+            Ok(ResultMessage::Duplicate { claim_id, error }) => {
+                active_claims -= 1;
+                processed_claims += 1;
+                duplicate_claims += 1;
+                eprintln!("{} Claim {} not adjudicated: {}", log_header("coordination"), claim_id, error);
+            }
+            Ok(ResultMessage::Rejected { claim_id, error }) => {
+                active_claims -= 1;
+                processed_claims += 1;
+                rejected_claims += 1;
+                eprintln!("{} Claim {} rejected: {}", log_header("coordination"), claim_id, error);
+            }
+            Ok(ResultMessage::DeadLettered { claim_id, error, attempts }) => {
                 active_claims -= 1;
                 processed_claims += 1;
-                eprintln!("{} Claim {} failed: {}", log_header("coordination"), claim_id, error);
+                eprintln!("{} Claim {} dead-lettered after {} attempts: {}", log_header("coordination"), claim_id, attempts, error);
+                dead_letters.push((claim_id, error));
+            }
+            Ok(ResultMessage::CrossoverFailed { claim_id, payer_id, error, attempts }) => {
+                active_claims -= 1;
+                processed_claims += 1;
+                eprintln!("{} Claim {} paid, but its crossover to {} failed after {} attempts: {}", log_header("coordination"), claim_id, payer_id, attempts, error);
+                failed_crossovers.push((claim_id, payer_id, error));
             }
             // Ok(ResultMessage::Status(status)) => {
             //     // Status messages already have proper headers from worker threads
@@ -306,24 +552,53 @@ fn main() -> Result<(), String> {
     eprintln!("{} Shutting down thread pool", log_header("coordination"));
     thread_pool.shutdown();
     
-    eprintln!("{} Processing complete: {} claims processed, {} parse errors", log_header("coordination"), processed_claims, parse_errors);
-    display_ar_report(&ar_data.lock().unwrap(), processed_claims);
+    eprintln!("{} Processing complete: {} claims processed, {} parse errors, {} rejected, {} duplicates, {} suspected duplicates, {} dead-lettered, {} failed crossovers",
+        log_header("coordination"), processed_claims, parse_errors, rejected_claims, duplicate_claims, suspected_duplicates.len(), dead_letters.len(), failed_crossovers.len());
+    for (claim_id, original_claim_id) in &suspected_duplicates {
+        eprintln!("{} Suspected duplicate: claim {} matches claim {}", log_header("coordination"), claim_id, original_claim_id);
+    }
+    for (claim_id, error) in &dead_letters {
+        eprintln!("{} Dead letter: claim {}: {}", log_header("coordination"), claim_id, error);
+    }
+    for (claim_id, payer_id, error) in &failed_crossovers {
+        eprintln!("{} Failed crossover: claim {} to {}: {}", log_header("coordination"), claim_id, payer_id, error);
+    }
+    display_ar_report(&ar_data.lock().unwrap(), processed_claims, clock.as_ref());
+    // every claim read before the failure has been dealt with, so a rerun picks up at the line it failed on
+    if let Some(error) = read_error {
+        return Err(format!("{} (rerun with --resume-from={} to carry on)", error, resume_point.offset()));
+    }
     Ok(())
 }
 
-fn process_claim_direct(claim: &PayerClaim) -> Result<ARData, String> {
+/// Returns the AR entries for the claim's remittances, and the claim for the patient's next
+/// coverage when it crosses over.
+fn process_claim_direct(claim: &PayerClaim, validator: &ClaimValidator, payers: &PayerRegistry) -> Result<(Vec<ARData>, Option<PayerClaim>), ClearinghouseError> {
     eprintln!("{} Starting validation for claim {}", log_header("claim_processor"), claim.claim_id);
-    if let Err(e) = validate_claim(&claim) {
-        return Err(format!("Validation failed: {}", e));
+    let report = validator.validate(claim).inspect_err(|report| {
+        // the full report is the rejection file returned to the submitter
+        eprintln!("{} Claim {} rejected:\n{}", log_header("claim_processor"), claim.claim_id, report.to_json());
+    })?;
+    for warning in report.warnings() {
+        eprintln!("{} Claim {} warning {} at {}: {}", log_header("claim_processor"), claim.claim_id, warning.code, warning.path, warning.message);
     }
     
     eprintln!("{} Submitting claim {} to payer", log_header("claim_processor"), claim.claim_id);
-    let remittance = submit_claim_to_payer(&claim)?;
-    eprintln!("{} Remittance {} received for claim {}", log_header("claim_processor"), remittance.remittance_id, claim.claim_id);
+    let mut remittances = submit_claim_to_payer(claim, payers)?;
+    let crossover = crossover_claim(claim, &mut remittances);
+
+    let mut ar_data = Vec::new();
+    for remittance in &remittances {
+        if remittance.reversal {
+            eprintln!("{} Reversal of remittance {} (claim {}) received for claim {}", log_header("claim_processor"), remittance.remittance_id, remittance.claim_id, claim.claim_id);
+        } else {
+            eprintln!("{} Remittance {} received for claim {}", log_header("claim_processor"), remittance.remittance_id, claim.claim_id);
+        }
 
-    eprintln!("{} Submitting remittance {} to clearinghouse", log_header("claim_processor"), remittance.remittance_id);
-    let ar_data = submit_remittance_to_submitter(&remittance)?;
+        eprintln!("{} Submitting remittance {} to clearinghouse", log_header("claim_processor"), remittance.remittance_id);
+        ar_data.push(submit_remittance_to_submitter(remittance)?);
+    }
     eprintln!("{} AR data generated for claim {}", log_header("claim_processor"), claim.claim_id);
     
-    Ok(ar_data)
-}
\ No newline at end of file
+    Ok((ar_data, crossover))
+}
@@ -1,7 +1,12 @@
 use app::{calculate_patient_statistics, calculate_aging_buckets};
 use clearinghouse::ARData;
+use intake::{ManualClock, Money, SystemClock};
 use insurance::ServiceLine;
 
+fn usd(amount: f64) -> Money {
+    Money::from_dollars(amount)
+}
+
 // Helper function to create test ARData
 fn create_ar_data(
     claim_id: &str,
@@ -18,21 +23,28 @@ fn create_ar_data(
         payee_npi: "1234567890".to_string(),
         patient_id: patient_id.to_string(),
         initial_claim_ts,
-        total_billed_amount: 100.0,
-        total_payer_paid_amount: 80.0,
-        total_coinsurance_amount: total_coinsurance,
-        total_copay_amount: total_copay,
-        total_deductible_amount: total_deductible,
-        total_not_allowed_amount: 5.0,
+        reversal: false,
+        payer_sequence: 1,
+        crossover_payer_id: None,
+        currency: "USD".to_string(),
+        currency_conversion: None,
+        total_billed_amount: usd(100.0),
+        total_payer_paid_amount: usd(80.0),
+        total_coinsurance_amount: usd(total_coinsurance),
+        total_copay_amount: usd(total_copay),
+        total_deductible_amount: usd(total_deductible),
+        total_not_allowed_amount: usd(5.0),
+        adjustment_totals: vec![],
         service_lines: vec![ServiceLine {
             service_line_id: "SL001".to_string(),
             procedure_code: "99213".to_string(),
-            billed_amount: 100.0,
-            payer_paid_amount: 80.0,
-            coinsurance_amount: total_coinsurance,
-            copay_amount: total_copay,
-            deductible_amount: total_deductible,
-            not_allowed_amount: 5.0,
+            billed_amount: usd(100.0),
+            payer_paid_amount: usd(80.0),
+            coinsurance_amount: usd(total_coinsurance),
+            copay_amount: usd(total_copay),
+            deductible_amount: usd(total_deductible),
+            not_allowed_amount: usd(5.0),
+            adjustments: vec![],
             remark_codes: None,
         }],
     }
@@ -46,7 +58,7 @@ mod calculate_patient_statistics_tests {
     fn test_empty_data_returns_zeros() {
         let data = vec![];
         let result = calculate_patient_statistics(&data);
-        assert_eq!(result, (0.0, 0.0, 0.0, 0));
+        assert_eq!(result, (usd(0.0), usd(0.0), usd(0.0), 0));
     }
 
     #[test]
@@ -56,9 +68,9 @@ mod calculate_patient_statistics_tests {
         let result = calculate_patient_statistics(&data);
         
         // Single patient, so averages should equal the claim amounts
-        assert_eq!(result.0, 10.0); // copay
-        assert_eq!(result.1, 15.0); // coinsurance  
-        assert_eq!(result.2, 5.0);  // deductible
+        assert_eq!(result.0, usd(10.0)); // copay
+        assert_eq!(result.1, usd(15.0)); // coinsurance  
+        assert_eq!(result.2, usd(5.0));  // deductible
         assert_eq!(result.3, 1);    // number of patients
     }
 
@@ -72,9 +84,9 @@ mod calculate_patient_statistics_tests {
         let result = calculate_patient_statistics(&data);
         
         // Single patient with 2 claims: (10+20)/2=15, (15+25)/2=20, (5+15)/2=10
-        assert_eq!(result.0, 15.0); // avg copay per patient
-        assert_eq!(result.1, 20.0); // avg coinsurance per patient
-        assert_eq!(result.2, 10.0); // avg deductible per patient
+        assert_eq!(result.0, usd(15.0)); // avg copay per patient
+        assert_eq!(result.1, usd(20.0)); // avg coinsurance per patient
+        assert_eq!(result.2, usd(10.0)); // avg deductible per patient
         assert_eq!(result.3, 1);    // number of patients
     }
 
@@ -88,9 +100,9 @@ mod calculate_patient_statistics_tests {
         let result = calculate_patient_statistics(&data);
         
         // Two patients, average across patients: (10+20)/2=15, (15+25)/2=20, (5+15)/2=10
-        assert_eq!(result.0, 15.0);
-        assert_eq!(result.1, 20.0);
-        assert_eq!(result.2, 10.0);
+        assert_eq!(result.0, usd(15.0));
+        assert_eq!(result.1, usd(20.0));
+        assert_eq!(result.2, usd(10.0));
         assert_eq!(result.3, 2);
     }
 
@@ -107,12 +119,41 @@ mod calculate_patient_statistics_tests {
         let result = calculate_patient_statistics(&data);
         
         // Average across patients: copay (20+40)/2=30, coinsurance (25+50)/2=37.5, deductible (15+20)/2=17.5
-        assert_eq!(result.0, 30.0);
-        assert_eq!(result.1, 37.5);
-        assert_eq!(result.2, 17.5);
+        assert_eq!(result.0, usd(30.0));
+        assert_eq!(result.1, usd(37.5));
+        assert_eq!(result.2, usd(17.5));
         assert_eq!(result.3, 2);
     }
 
+    #[test]
+    fn test_reversals_net_out_original_claims() {
+        let mut reversal = create_ar_data("C001", "patient1", 1000, -10.0, -15.0, -5.0);
+        reversal.reversal = true;
+        let mut void = create_ar_data("C003", "patient2", 3000, -40.0, -50.0, -20.0);
+        void.reversal = true;
+        let data = vec![
+            // Patient 1: C001 replaced by C002, leaving only C002
+            create_ar_data("C001", "patient1", 1000, 10.0, 15.0, 5.0),
+            reversal,
+            create_ar_data("C002", "patient1", 2000, 30.0, 35.0, 25.0),
+            // Patient 2: C003 voided, leaving nothing
+            create_ar_data("C003", "patient2", 3000, 40.0, 50.0, 20.0),
+            void,
+        ];
+
+        assert_eq!(calculate_patient_statistics(&data), (usd(30.0), usd(35.0), usd(25.0), 1));
+    }
+
+    #[test]
+    fn test_crossed_over_claims_count_what_the_secondary_left() {
+        let mut primary = create_ar_data("C001", "Medicare-MED1", 1000, 0.0, 20.0, 10.0);
+        primary.crossover_payer_id = Some("aarp_medigap".to_string());
+        let mut secondary = create_ar_data("C001", "aarp_medigap-AARP1", 1000, 0.0, 5.0, 0.0);
+        secondary.payer_sequence = 2;
+
+        assert_eq!(calculate_patient_statistics(&[primary, secondary]), (usd(0.0), usd(5.0), usd(0.0), 1));
+    }
+
     #[test]
     fn test_zero_amounts() {
         let data = vec![
@@ -122,9 +163,9 @@ mod calculate_patient_statistics_tests {
         
         let result = calculate_patient_statistics(&data);
         
-        assert_eq!(result.0, 0.0);
-        assert_eq!(result.1, 0.0);
-        assert_eq!(result.2, 0.0);
+        assert_eq!(result.0, usd(0.0));
+        assert_eq!(result.1, usd(0.0));
+        assert_eq!(result.2, usd(0.0));
         assert_eq!(result.3, 2);
     }
 
@@ -138,9 +179,9 @@ mod calculate_patient_statistics_tests {
         let result = calculate_patient_statistics(&data);
         
         // Average: (0+20)/2=10, (0+30)/2=15, (0+10)/2=5
-        assert_eq!(result.0, 10.0);
-        assert_eq!(result.1, 15.0);
-        assert_eq!(result.2, 5.0);
+        assert_eq!(result.0, usd(10.0));
+        assert_eq!(result.1, usd(15.0));
+        assert_eq!(result.2, usd(5.0));
         assert_eq!(result.3, 2);
     }
 
@@ -153,12 +194,26 @@ mod calculate_patient_statistics_tests {
         
         let result = calculate_patient_statistics(&data);
         
-        // Test floating point precision
-        assert!((result.0 - 1000.0).abs() < 0.01);
-        assert!((result.1 - 1234.55).abs() < 0.01);
-        assert!((result.2 - 500.0).abs() < 0.01);
+        // averages are exact to the cent
+        assert_eq!(result.0, usd(1000.0));
+        assert_eq!(result.1, usd(1234.55));
+        assert_eq!(result.2, usd(500.0));
         assert_eq!(result.3, 2);
     }
+
+    #[test]
+    fn test_averages_round_half_cents_away_from_zero() {
+        let data = vec![
+            create_ar_data("C001", "patient1", 1000, 10.0, 0.01, -0.01),
+            create_ar_data("C002", "patient2", 2000, 10.01, 0.0, 0.0),
+        ];
+
+        let result = calculate_patient_statistics(&data);
+
+        assert_eq!(result.0, usd(10.01));
+        assert_eq!(result.1, usd(0.01));
+        assert_eq!(result.2, usd(-0.01));
+    }
 }
 
 #[cfg(test)]
@@ -169,7 +224,7 @@ mod calculate_aging_buckets_tests {
     #[test]
     fn test_empty_data_returns_zero_buckets() {
         let data = vec![];
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         assert_eq!(result, [0, 0, 0, 0]);
     }
 
@@ -178,7 +233,7 @@ mod calculate_aging_buckets_tests {
         let now = Utc::now().timestamp_millis();
         let data = vec![create_ar_data("C001", "patient1", now, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 0 (0-1 minutes old)
         assert_eq!(result[0], 1);
@@ -190,10 +245,10 @@ mod calculate_aging_buckets_tests {
     #[test]
     fn test_one_minute_old_bucket_0() {
         let now = Utc::now().timestamp_millis();
-        let one_minute_ago = now - (1 * 60 * 1000); // 1 minute ago
+        let one_minute_ago = now - (60 * 1000); // 1 minute ago
         let data = vec![create_ar_data("C001", "patient1", one_minute_ago, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should still be in bucket 0 (0-1 minutes old, inclusive)
         assert_eq!(result[0], 1);
@@ -208,7 +263,7 @@ mod calculate_aging_buckets_tests {
         let two_minutes_ago = now - (2 * 60 * 1000); // 2 minutes ago
         let data = vec![create_ar_data("C001", "patient1", two_minutes_ago, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 1 (1-2 minutes old)
         assert_eq!(result[0], 0);
@@ -223,7 +278,7 @@ mod calculate_aging_buckets_tests {
         let three_minutes_ago = now - (3 * 60 * 1000); // 3 minutes ago
         let data = vec![create_ar_data("C001", "patient1", three_minutes_ago, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 2 (2-3 minutes old)
         assert_eq!(result[0], 0);
@@ -238,7 +293,7 @@ mod calculate_aging_buckets_tests {
         let four_minutes_ago = now - (4 * 60 * 1000); // 4 minutes ago
         let data = vec![create_ar_data("C001", "patient1", four_minutes_ago, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 3 (3+ minutes old)
         assert_eq!(result[0], 0);
@@ -253,7 +308,7 @@ mod calculate_aging_buckets_tests {
         let very_old = now - (60 * 60 * 1000); // 1 hour ago
         let data = vec![create_ar_data("C001", "patient1", very_old, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 3 (3+ minutes old)
         assert_eq!(result[0], 0);
@@ -267,14 +322,14 @@ mod calculate_aging_buckets_tests {
         let now = Utc::now().timestamp_millis();
         let data = vec![
             create_ar_data("C001", "patient1", now, 10.0, 15.0, 5.0), // bucket 0
-            create_ar_data("C002", "patient2", now - (1 * 60 * 1000), 10.0, 15.0, 5.0), // bucket 0
+            create_ar_data("C002", "patient2", now - (60 * 1000), 10.0, 15.0, 5.0), // bucket 0
             create_ar_data("C003", "patient3", now - (2 * 60 * 1000), 10.0, 15.0, 5.0), // bucket 1
             create_ar_data("C004", "patient4", now - (3 * 60 * 1000), 10.0, 15.0, 5.0), // bucket 2
             create_ar_data("C005", "patient5", now - (4 * 60 * 1000), 10.0, 15.0, 5.0), // bucket 3
             create_ar_data("C006", "patient6", now - (10 * 60 * 1000), 10.0, 15.0, 5.0), // bucket 3
         ];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         assert_eq!(result[0], 2); // 0-1 minutes: 2 claims
         assert_eq!(result[1], 1); // 1-2 minutes: 1 claim
@@ -287,14 +342,14 @@ mod calculate_aging_buckets_tests {
         let now = Utc::now().timestamp_millis();
         let data = vec![
             // Exactly 1 minute old (should be bucket 0)
-            create_ar_data("C001", "patient1", now - (1 * 60 * 1000), 10.0, 15.0, 5.0),
+            create_ar_data("C001", "patient1", now - (60 * 1000), 10.0, 15.0, 5.0),
             // Exactly 2 minutes old (should be bucket 1)
             create_ar_data("C002", "patient2", now - (2 * 60 * 1000), 10.0, 15.0, 5.0),
             // Exactly 3 minutes old (should be bucket 2)
             create_ar_data("C003", "patient3", now - (3 * 60 * 1000), 10.0, 15.0, 5.0),
         ];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         assert_eq!(result[0], 1); // 0-1 minutes
         assert_eq!(result[1], 1); // 1-2 minutes  
@@ -310,7 +365,7 @@ mod calculate_aging_buckets_tests {
             create_ar_data("C001", "patient1", -1000000, 10.0, 15.0, 5.0),
         ];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 3 (3+ minutes old)
         assert_eq!(result[0], 0);
@@ -326,7 +381,7 @@ mod calculate_aging_buckets_tests {
         let timestamp = now - (59 * 1000); // 59 seconds ago (< 1 minute)
         let data = vec![create_ar_data("C001", "patient1", timestamp, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 0 (< 1.0 minutes)
         assert_eq!(result[0], 1);
@@ -341,7 +396,7 @@ mod calculate_aging_buckets_tests {
         let timestamp = now - (61 * 1000); // 61 seconds ago (> 1 minute, < 2 minutes)
         let data = vec![create_ar_data("C001", "patient1", timestamp, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 1 (>= 1.0 and < 2.0 minutes)
         assert_eq!(result[0], 0);
@@ -356,7 +411,7 @@ mod calculate_aging_buckets_tests {
         let timestamp = now - (119 * 1000); // 119 seconds ago (< 2 minutes)
         let data = vec![create_ar_data("C001", "patient1", timestamp, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 1 (>= 1.0 and < 2.0 minutes)
         assert_eq!(result[0], 0);
@@ -371,7 +426,7 @@ mod calculate_aging_buckets_tests {
         let timestamp = now - (121 * 1000); // 121 seconds ago (> 2 minutes, < 3 minutes)
         let data = vec![create_ar_data("C001", "patient1", timestamp, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 2 (>= 2.0 and < 3.0 minutes)
         assert_eq!(result[0], 0);
@@ -386,7 +441,7 @@ mod calculate_aging_buckets_tests {
         let timestamp = now - (179 * 1000); // 179 seconds ago (< 3 minutes)
         let data = vec![create_ar_data("C001", "patient1", timestamp, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 2 (>= 2.0 and < 3.0 minutes)
         assert_eq!(result[0], 0);
@@ -401,7 +456,7 @@ mod calculate_aging_buckets_tests {
         let timestamp = now - (181 * 1000); // 181 seconds ago (> 3 minutes)
         let data = vec![create_ar_data("C001", "patient1", timestamp, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Should be in bucket 3 (>= 3.0 minutes)
         assert_eq!(result[0], 0);
@@ -436,7 +491,7 @@ mod calculate_aging_buckets_tests {
     //         create_ar_data("C009", "patient9", now - 180001, 10.0, 15.0, 5.0),
     //     ];
         
-    //     let result = calculate_aging_buckets(&data);
+    //     let result = calculate_aging_buckets(&data, &SystemClock);
         
     //     // Bucket 0 (0 <= t < 1.0): 59999ms claim should be here
     //     assert_eq!(result[0], 2);
@@ -465,7 +520,7 @@ mod calculate_aging_buckets_tests {
             create_ar_data("C004", "patient4", now - (210 * 1000), 10.0, 15.0, 5.0),
         ];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         assert_eq!(result[0], 1); // 0.5 minutes -> bucket 0
         assert_eq!(result[1], 1); // 1.5 minutes -> bucket 1
@@ -473,13 +528,29 @@ mod calculate_aging_buckets_tests {
         assert_eq!(result[3], 1); // 3.5 minutes -> bucket 3
     }
 
+    #[test]
+    fn test_buckets_follow_injected_clock() {
+        let clock = ManualClock::new(1_700_000_000_000);
+        let data = vec![
+            create_ar_data("C001", "patient1", 1_700_000_000_000, 10.0, 15.0, 5.0),
+            create_ar_data("C002", "patient2", 1_700_000_000_000 - 60 * 1000, 10.0, 15.0, 5.0),
+            create_ar_data("C003", "patient3", 1_700_000_000_000 - 60 * 1000 - 1, 10.0, 15.0, 5.0),
+        ];
+
+        // exact boundaries are stable because the clock doesn't move between stamping and aging
+        assert_eq!(calculate_aging_buckets(&data, &clock), [2, 1, 0, 0]);
+
+        clock.advance(std::time::Duration::from_secs(150));
+        assert_eq!(calculate_aging_buckets(&data, &clock), [0, 0, 1, 2]);
+    }
+
     #[test]
     fn test_future_timestamps_bucket_0() {
         let now = Utc::now().timestamp_millis();
         let future_timestamp = now + (30 * 1000); // 30 seconds in the future
         let data = vec![create_ar_data("C001", "patient1", future_timestamp, 10.0, 15.0, 5.0)];
         
-        let result = calculate_aging_buckets(&data);
+        let result = calculate_aging_buckets(&data, &SystemClock);
         
         // Future timestamps result in negative age, which should go to bucket 0
         assert_eq!(result[0], 1);
@@ -487,4 +558,18 @@ mod calculate_aging_buckets_tests {
         assert_eq!(result[2], 0);
         assert_eq!(result[3], 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_reversed_claims_are_not_aged() {
+        let clock = ManualClock::new(1_700_000_000_000);
+        let mut reversal = create_ar_data("C001", "patient1", 1_700_000_000_000, -10.0, -15.0, -5.0);
+        reversal.reversal = true;
+        let data = vec![
+            create_ar_data("C001", "patient1", 1_700_000_000_000, 10.0, 15.0, 5.0),
+            reversal,
+            create_ar_data("C002", "patient1", 1_700_000_000_000, 10.0, 15.0, 5.0),
+        ];
+
+        assert_eq!(calculate_aging_buckets(&data, &clock), [1, 0, 0, 0]);
+    }
+}
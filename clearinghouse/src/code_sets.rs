@@ -0,0 +1,198 @@
+use crate::ClearinghouseError;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Letters that start a HCPCS Level II code on a professional claim (D codes are dental and
+/// billed on a different claim type).
+const HCPCS_LEVEL_II_PREFIXES: &str = "ABCEGHJKLMPQRSTUV";
+
+/// The kind of a procedure code, judged by its shape alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcedureCodeKind {
+    /// Five digits, e.g. `99213`
+    CptCategoryI,
+    /// Four digits and `F`, performance measurement tracking codes, e.g. `3074F`
+    CptCategoryII,
+    /// Four digits and `T`, emerging technology codes, e.g. `0042T`
+    CptCategoryIII,
+    /// Four digits and `U`, proprietary laboratory analyses, e.g. `0001U`
+    ProprietaryLab,
+    /// A letter and four digits, e.g. `J1100`
+    HcpcsLevelII,
+}
+
+impl ProcedureCodeKind {
+    /// Classifies `code`, or returns `None` if it isn't shaped like a CPT or HCPCS code.
+    pub fn of(code: &str) -> Option<ProcedureCodeKind> {
+        let bytes = code.as_bytes();
+        if bytes.len() != 5 {
+            return None;
+        }
+        let digits = |range: std::ops::Range<usize>| bytes[range].iter().all(u8::is_ascii_digit);
+
+        match bytes[4] {
+            b'0'..=b'9' if digits(0..4) => Some(ProcedureCodeKind::CptCategoryI),
+            b'F' if digits(0..4) => Some(ProcedureCodeKind::CptCategoryII),
+            b'T' if digits(0..4) => Some(ProcedureCodeKind::CptCategoryIII),
+            b'U' if digits(0..4) => Some(ProcedureCodeKind::ProprietaryLab),
+            b'0'..=b'9' if HCPCS_LEVEL_II_PREFIXES.as_bytes().contains(&bytes[0]) && digits(1..5) => {
+                Some(ProcedureCodeKind::HcpcsLevelII)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The procedure codes in effect for the year, loaded from the annual CPT/HCPCS release. When
+/// configured, codes that are shaped correctly but aren't listed are rejected.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcedureCodeSet {
+    codes: HashMap<String, String>,
+}
+
+impl ProcedureCodeSet {
+    pub fn new() -> ProcedureCodeSet {
+        ProcedureCodeSet::default()
+    }
+
+    pub fn insert(&mut self, code: &str, description: &str) {
+        self.codes.insert(code.to_string(), description.to_string());
+    }
+
+    pub fn contains(&self, code: &str) -> bool {
+        self.codes.contains_key(code)
+    }
+
+    pub fn description(&self, code: &str) -> Option<&str> {
+        self.codes.get(code).map(|d| d.as_str())
+    }
+
+    /// Parses `procedure_code,description` rows; the description may contain commas.
+    pub fn from_csv(contents: &str) -> Result<ProcedureCodeSet, ClearinghouseError> {
+        let mut set = ProcedureCodeSet::new();
+        for (line_number, fields) in csv_rows(contents, "procedure_code,description", 2)? {
+            if ProcedureCodeKind::of(fields[0]).is_none() {
+                return Err(ClearinghouseError::reference(format!(
+                    "Line {}: {} is not a CPT or HCPCS code",
+                    line_number, fields[0]
+                )));
+            }
+            set.insert(fields[0], fields[1]);
+        }
+        Ok(set)
+    }
+
+    pub fn load(path: &Path) -> Result<ProcedureCodeSet, ClearinghouseError> {
+        load_with(path, ProcedureCodeSet::from_csv)
+    }
+
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ModifierEntry {
+    description: String,
+    conflicts_with: Vec<String>,
+}
+
+/// Recognized modifiers and the pairs that contradict each other on one line (such as `LT`
+/// with `RT`, or `26` with `TC`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModifierTable {
+    modifiers: HashMap<String, ModifierEntry>,
+}
+
+impl ModifierTable {
+    pub fn new() -> ModifierTable {
+        ModifierTable::default()
+    }
+
+    pub fn insert(&mut self, modifier: &str, conflicts_with: &[&str], description: &str) {
+        self.modifiers.insert(
+            modifier.to_string(),
+            ModifierEntry {
+                description: description.to_string(),
+                conflicts_with: conflicts_with.iter().map(|m| m.to_string()).collect(),
+            },
+        );
+    }
+
+    pub fn contains(&self, modifier: &str) -> bool {
+        self.modifiers.contains_key(modifier)
+    }
+
+    pub fn description(&self, modifier: &str) -> Option<&str> {
+        self.modifiers.get(modifier).map(|entry| entry.description.as_str())
+    }
+
+    /// True when either modifier lists the other as a conflict.
+    pub fn conflicts(&self, a: &str, b: &str) -> bool {
+        let lists = |x: &str, y: &str| self.modifiers.get(x).is_some_and(|entry| entry.conflicts_with.iter().any(|m| m == y));
+        lists(a, b) || lists(b, a)
+    }
+
+    /// Parses `modifier,conflicts_with,description` rows, where `conflicts_with` is a space
+    /// separated list and the description may contain commas.
+    pub fn from_csv(contents: &str) -> Result<ModifierTable, ClearinghouseError> {
+        let mut table = ModifierTable::new();
+        for (line_number, fields) in csv_rows(contents, "modifier,conflicts_with,description", 3)? {
+            if fields[0].len() != 2 || !fields[0].chars().all(|c| c.is_ascii_digit() || c.is_ascii_uppercase()) {
+                return Err(ClearinghouseError::reference(format!(
+                    "Line {}: modifier {} must be two digits or uppercase letters",
+                    line_number, fields[0]
+                )));
+            }
+            let conflicts_with: Vec<&str> = fields[1].split_whitespace().collect();
+            table.insert(fields[0], &conflicts_with, fields[2]);
+        }
+        Ok(table)
+    }
+
+    pub fn load(path: &Path) -> Result<ModifierTable, ClearinghouseError> {
+        load_with(path, ModifierTable::from_csv)
+    }
+
+    /// The bundled table of common CPT and HCPCS Level II modifiers.
+    pub fn standard() -> ModifierTable {
+        ModifierTable::from_csv(include_str!("../code_sets/modifiers.csv")).unwrap()
+    }
+}
+
+/// Splits a code table into `columns` fields per row (the last one keeps any further commas),
+/// skipping blank lines and `#` comments. The header row must match `header` exactly.
+pub(crate) fn csv_rows<'a>(contents: &'a str, header: &str, columns: usize) -> Result<Vec<(usize, Vec<&'a str>)>, ClearinghouseError> {
+    let mut rows = contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    match rows.next() {
+        Some((_, first)) if first == header => {}
+        Some((line_number, _)) => {
+            return Err(ClearinghouseError::reference(format!("Line {}: expected header {}", line_number, header)));
+        }
+        None => return Err(ClearinghouseError::reference("Code table is empty")),
+    }
+
+    rows.map(|(line_number, row)| {
+        let fields: Vec<&str> = row.splitn(columns, ',').map(|f| f.trim()).collect();
+        if fields.len() != columns || fields[0].is_empty() {
+            return Err(ClearinghouseError::reference(format!("Line {}: expected {} fields", line_number, columns)));
+        }
+        Ok((line_number, fields))
+    })
+    .collect()
+}
+
+pub(crate) fn load_with<T>(path: &Path, parse: impl Fn(&str) -> Result<T, ClearinghouseError>) -> Result<T, ClearinghouseError> {
+    let with_path = |message: String| ClearinghouseError::Reference { path: Some(path.display().to_string()), message };
+    let contents = std::fs::read_to_string(path).map_err(|e| with_path(format!("failed to read: {}", e)))?;
+    parse(&contents).map_err(|e| with_path(e.to_string()))
+}
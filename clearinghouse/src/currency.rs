@@ -0,0 +1,183 @@
+use crate::code_sets::{csv_rows, load_with};
+use crate::ClearinghouseError;
+use chrono::NaiveDate;
+use insurance::CurrencyConversion;
+use intake::{PayerClaim, PriorAdjustment, PriorLinePayment, PriorPayment};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+struct CurrencyEntry {
+    minor_units: u8,
+    name: String,
+}
+
+/// ISO 4217 currency codes and the number of decimal places (minor units) amounts in each are
+/// written with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CurrencyTable {
+    currencies: HashMap<String, CurrencyEntry>,
+}
+
+impl CurrencyTable {
+    pub fn new() -> CurrencyTable {
+        CurrencyTable::default()
+    }
+
+    pub fn insert(&mut self, code: &str, minor_units: u8, name: &str) {
+        self.currencies.insert(code.to_string(), CurrencyEntry { minor_units, name: name.to_string() });
+    }
+
+    pub fn contains(&self, code: &str) -> bool {
+        self.currencies.contains_key(code)
+    }
+
+    /// Decimal places amounts in `code` are written with: 2 for US dollars, 0 for yen.
+    pub fn minor_units(&self, code: &str) -> Option<u8> {
+        self.currencies.get(code).map(|entry| entry.minor_units)
+    }
+
+    pub fn name(&self, code: &str) -> Option<&str> {
+        self.currencies.get(code).map(|entry| entry.name.as_str())
+    }
+
+    /// Parses `code,minor_units,name` rows; the name may contain commas.
+    pub fn from_csv(contents: &str) -> Result<CurrencyTable, ClearinghouseError> {
+        let mut table = CurrencyTable::new();
+        for (line_number, fields) in csv_rows(contents, "code,minor_units,name", 3)? {
+            if !is_currency_code(fields[0]) {
+                return Err(ClearinghouseError::reference(format!(
+                    "Line {}: currency code {} must be 3 uppercase letters",
+                    line_number, fields[0]
+                )));
+            }
+            let minor_units = fields[1].parse().map_err(|_| {
+                ClearinghouseError::reference(format!("Line {}: invalid minor_units {}", line_number, fields[1]))
+            })?;
+            table.insert(fields[0], minor_units, fields[2]);
+        }
+        Ok(table)
+    }
+
+    pub fn load(path: &Path) -> Result<CurrencyTable, ClearinghouseError> {
+        load_with(path, CurrencyTable::from_csv)
+    }
+
+    /// The bundled ISO 4217 list of active currencies.
+    pub fn standard() -> CurrencyTable {
+        CurrencyTable::from_csv(include_str!("../code_sets/currencies.csv")).unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.currencies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.currencies.is_empty()
+    }
+}
+
+/// Exchange rates from one currency into another, each in effect from its date until the pair's
+/// next rate. Rates only apply in the direction they're listed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExchangeRateTable {
+    // each pair's rates, oldest first
+    rates: HashMap<(String, String), Vec<(NaiveDate, f64)>>,
+}
+
+impl ExchangeRateTable {
+    pub fn new() -> ExchangeRateTable {
+        ExchangeRateTable::default()
+    }
+
+    /// Adds the number of `to_currency` units one `from_currency` unit buys from `effective_date`
+    /// on, replacing a rate for the pair that took effect the same day.
+    pub fn insert(&mut self, from_currency: &str, to_currency: &str, effective_date: NaiveDate, rate: f64) {
+        let rates = self.rates.entry((from_currency.to_string(), to_currency.to_string())).or_default();
+        match rates.binary_search_by_key(&effective_date, |&(date, _)| date) {
+            Ok(index) => rates[index].1 = rate,
+            Err(index) => rates.insert(index, (effective_date, rate)),
+        }
+    }
+
+    /// The rate converting `from_currency` into `to_currency` in effect on `date`, or `None` if the
+    /// pair has no rate that took effect by then.
+    pub fn rate(&self, from_currency: &str, to_currency: &str, date: NaiveDate) -> Option<CurrencyConversion> {
+        let rates = self.rates.get(&(from_currency.to_string(), to_currency.to_string()))?;
+        let &(effective_date, rate) = rates.iter().rev().find(|&&(effective_date, _)| effective_date <= date)?;
+        Some(CurrencyConversion {
+            from_currency: from_currency.to_string(),
+            to_currency: to_currency.to_string(),
+            rate,
+            effective_date,
+        })
+    }
+
+    /// Parses `from_currency,to_currency,effective_date,rate` rows, with dates as `YYYY-MM-DD`.
+    pub fn from_csv(contents: &str) -> Result<ExchangeRateTable, ClearinghouseError> {
+        let mut table = ExchangeRateTable::new();
+        for (line_number, fields) in csv_rows(contents, "from_currency,to_currency,effective_date,rate", 4)? {
+            let invalid = |message: String| ClearinghouseError::reference(format!("Line {}: {}", line_number, message));
+            if let Some(code) = fields[..2].iter().find(|code| !is_currency_code(code)) {
+                return Err(invalid(format!("currency code {} must be 3 uppercase letters", code)));
+            }
+            let effective_date = NaiveDate::parse_from_str(fields[2], "%Y-%m-%d")
+                .map_err(|_| invalid(format!("invalid effective_date {} (expected YYYY-MM-DD)", fields[2])))?;
+            let rate = fields[3]
+                .parse::<f64>()
+                .ok()
+                .filter(|rate| rate.is_finite() && *rate > 0.0)
+                .ok_or_else(|| invalid(format!("rate {} must be a positive number", fields[3])))?;
+            table.insert(fields[0], fields[1], effective_date, rate);
+        }
+        Ok(table)
+    }
+
+    pub fn load(path: &Path) -> Result<ExchangeRateTable, ClearinghouseError> {
+        load_with(path, ExchangeRateTable::from_csv)
+    }
+
+    /// Number of rates across all pairs.
+    pub fn len(&self) -> usize {
+        self.rates.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+}
+
+pub(crate) fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// The claim with its charges, and what earlier payers paid and adjusted, converted into the
+/// conversion's currency. Each unit charge is rounded to the cent before it's multiplied out.
+pub(crate) fn convert_claim(claim: &PayerClaim, conversion: &CurrencyConversion) -> PayerClaim {
+    let mut converted = claim.clone();
+    for line in &mut converted.service_lines {
+        line.unit_charge_amount = conversion.convert(line.unit_charge_amount);
+        line.unit_charge_currency = conversion.to_currency.clone();
+    }
+    converted.prior_payments = claim
+        .prior_payments
+        .iter()
+        .map(|prior| PriorPayment {
+            service_lines: prior
+                .service_lines
+                .iter()
+                .map(|line| PriorLinePayment {
+                    paid_amount: conversion.convert(line.paid_amount),
+                    adjustments: line
+                        .adjustments
+                        .iter()
+                        .map(|a| PriorAdjustment { amount: conversion.convert(a.amount), ..a.clone() })
+                        .collect(),
+                    ..line.clone()
+                })
+                .collect(),
+            ..prior.clone()
+        })
+        .collect();
+    converted
+}
@@ -0,0 +1,168 @@
+use crate::ClearinghouseError;
+use intake::{ClaimFrequency, PayerClaim};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Outcome of checking a claim that isn't an exact duplicate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DuplicateCheck {
+    New,
+    /// A different claim id with the same patient, provider and services. The claim is still
+    /// processed, but flagged for review.
+    Suspected { original_claim_id: String, reason: String },
+}
+
+/// One line of the on-disk store.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredClaim {
+    claim_id: String,
+    fingerprint: String,
+}
+
+#[derive(Debug, Default)]
+struct DuplicateState {
+    /// claim id -> fingerprint, for every claim reserved in this run or committed in earlier ones
+    claims: HashMap<String, String>,
+    /// fingerprint -> the first claim id seen with it
+    fingerprints: HashMap<String, String>,
+}
+
+/// Claims seen so far, so a resubmitted file isn't adjudicated twice.
+///
+/// A claim is `reserve`d before it's processed, then either `commit`ted once it's been
+/// adjudicated or `release`d if it failed, so that a corrected resubmission goes through. With a
+/// store file, committed claims are appended to it and loaded again by the next run.
+#[derive(Debug, Default)]
+pub struct DuplicateIndex {
+    state: Mutex<DuplicateState>,
+    store: Option<Mutex<File>>,
+}
+
+impl DuplicateIndex {
+    /// An index that only lasts for this run.
+    pub fn new() -> DuplicateIndex {
+        DuplicateIndex::default()
+    }
+
+    /// Loads the claims committed by earlier runs from `path` (a JSON line per claim; created if
+    /// missing) and appends this run's claims to it.
+    pub fn open(path: &Path) -> Result<DuplicateIndex, ClearinghouseError> {
+        let with_path = |message: String| ClearinghouseError::Reference { path: Some(path.display().to_string()), message };
+
+        let mut state = DuplicateState::default();
+        if path.exists() {
+            let contents = std::fs::read_to_string(path).map_err(|e| with_path(format!("failed to read duplicate store: {}", e)))?;
+            for (index, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+                let stored: StoredClaim =
+                    serde_json::from_str(line).map_err(|e| with_path(format!("Line {}: {}", index + 1, e)))?;
+                state.fingerprints.entry(stored.fingerprint.clone()).or_insert_with(|| stored.claim_id.clone());
+                state.claims.insert(stored.claim_id, stored.fingerprint);
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| with_path(format!("failed to open duplicate store: {}", e)))?;
+        Ok(DuplicateIndex { state: Mutex::new(state), store: Some(Mutex::new(file)) })
+    }
+
+    /// Rejects a claim id that was already seen and flags a claim that repeats another claim's
+    /// patient, provider and services under a new id. Either way the claim is reserved until
+    /// it's committed or released.
+    ///
+    /// Replacement and void claims are expected to repeat an earlier claim, often under the same
+    /// id, so they always pass; the payer rejects one that doesn't match anything it can reverse.
+    pub fn reserve(&self, claim: &PayerClaim) -> Result<DuplicateCheck, ClearinghouseError> {
+        if claim.frequency_code != ClaimFrequency::Original {
+            return Ok(DuplicateCheck::New);
+        }
+        let fingerprint = fingerprint(claim);
+        let mut state = self.state.lock().unwrap();
+
+        if state.claims.contains_key(&claim.claim_id) {
+            return Err(ClearinghouseError::Duplicate {
+                claim_id: claim.claim_id.clone(),
+                reason: "claim_id was already submitted".to_string(),
+            });
+        }
+
+        state.claims.insert(claim.claim_id.clone(), fingerprint.clone());
+        match state.fingerprints.get(&fingerprint) {
+            Some(original_claim_id) => Ok(DuplicateCheck::Suspected {
+                original_claim_id: original_claim_id.clone(),
+                reason: format!("same patient, rendering provider and service lines as claim {}", original_claim_id),
+            }),
+            None => {
+                state.fingerprints.insert(fingerprint, claim.claim_id.clone());
+                Ok(DuplicateCheck::New)
+            }
+        }
+    }
+
+    /// Records an adjudicated claim in the store file, if there is one.
+    pub fn commit(&self, claim: &PayerClaim) -> Result<(), ClearinghouseError> {
+        let Some(ref store) = self.store else { return Ok(()) };
+        if claim.frequency_code != ClaimFrequency::Original {
+            return Ok(());
+        }
+        let stored = StoredClaim { claim_id: claim.claim_id.clone(), fingerprint: fingerprint(claim) };
+        let line = serde_json::to_string(&stored).expect("stored claims always serialize");
+        writeln!(store.lock().unwrap(), "{}", line)
+            .map_err(|e| ClearinghouseError::reference(format!("failed to write duplicate store: {}", e)))
+    }
+
+    /// Forgets a reserved claim that wasn't adjudicated.
+    pub fn release(&self, claim: &PayerClaim) {
+        if claim.frequency_code != ClaimFrequency::Original {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if let Some(fingerprint) = state.claims.remove(&claim.claim_id)
+            && state.fingerprints.get(&fingerprint) == Some(&claim.claim_id)
+        {
+            state.fingerprints.remove(&fingerprint);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().claims.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// What makes two claims the same visit: the patient, the rendering provider and the billed
+/// services with their dates, regardless of claim id or line order.
+fn fingerprint(claim: &PayerClaim) -> String {
+    let mut lines: Vec<String> = claim
+        .service_lines
+        .iter()
+        .map(|line| {
+            let mut modifiers = line.modifiers.clone().unwrap_or_default();
+            modifiers.sort();
+            let mut key = format!("{}:{}:{}:{}", line.procedure_code, modifiers.join("+"), line.units, line.unit_charge_amount);
+            // lines without dates keep the fingerprints stores written before dates existed
+            if let Some(from) = line.service_date_from {
+                key.push_str(&format!(":{}", from));
+            }
+            key
+        })
+        .collect();
+    lines.sort();
+
+    format!(
+        "{}|{}|{}|{}",
+        claim.insurance.patient_member_id,
+        claim.patient.dob,
+        claim.rendering_provider.npi,
+        lines.join(",")
+    )
+}
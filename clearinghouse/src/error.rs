@@ -0,0 +1,102 @@
+use crate::ValidationReport;
+use insurance::InsuranceError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClearinghouseError {
+    /// The claim failed validation; the report lists every problem and is returned to the submitter
+    Rejected(ValidationReport),
+    /// The claim was already submitted; it's not adjudicated again
+    Duplicate { claim_id: String, reason: String },
+    /// The claim is billed to a payer the registry doesn't know
+    UnknownPayer { claim_id: String, payer_id: String },
+    /// The claim is in a currency its payer doesn't accept, or there's no rate to convert it with
+    Currency { claim_id: String, message: String },
+    /// The payer couldn't adjudicate the claim
+    Payer { claim_id: String, source: InsuranceError },
+    /// An eligibility inquiry couldn't be answered; without a `source` the payer is unknown
+    Eligibility { payer_id: String, member_id: String, source: Option<InsuranceError> },
+    /// A remittance couldn't be turned into AR data
+    Remittance { claim_id: String, field: String, message: String },
+    /// Reference data used for validation (such as the NPI registry) couldn't be loaded
+    Reference { path: Option<String>, message: String },
+}
+
+impl ClearinghouseError {
+    /// Whether resubmitting the same claim could succeed. Validation rejections and malformed
+    /// remittances never will; payer errors depend on the payer.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClearinghouseError::Payer { source, .. } => source.is_retryable(),
+            ClearinghouseError::Eligibility { source, .. } => source.as_ref().is_some_and(InsuranceError::is_retryable),
+            ClearinghouseError::Rejected(_)
+            | ClearinghouseError::Duplicate { .. }
+            | ClearinghouseError::UnknownPayer { .. }
+            | ClearinghouseError::Currency { .. }
+            | ClearinghouseError::Remittance { .. }
+            | ClearinghouseError::Reference { .. } => false,
+        }
+    }
+
+    pub fn claim_id(&self) -> Option<&str> {
+        match self {
+            ClearinghouseError::Rejected(report) => Some(&report.claim_id),
+            ClearinghouseError::Duplicate { claim_id, .. }
+            | ClearinghouseError::UnknownPayer { claim_id, .. }
+            | ClearinghouseError::Currency { claim_id, .. }
+            | ClearinghouseError::Payer { claim_id, .. }
+            | ClearinghouseError::Remittance { claim_id, .. } => Some(claim_id),
+            ClearinghouseError::Eligibility { .. } | ClearinghouseError::Reference { .. } => None,
+        }
+    }
+
+    pub(crate) fn reference(message: impl Into<String>) -> ClearinghouseError {
+        ClearinghouseError::Reference { path: None, message: message.into() }
+    }
+}
+
+impl fmt::Display for ClearinghouseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClearinghouseError::Rejected(report) => write!(f, "Validation failed: {}", report),
+            ClearinghouseError::Duplicate { claim_id, reason } => write!(f, "Duplicate claim {}: {}", claim_id, reason),
+            ClearinghouseError::UnknownPayer { claim_id, payer_id } => {
+                write!(f, "Claim {} is billed to unknown payer {}", claim_id, payer_id)
+            }
+            ClearinghouseError::Currency { claim_id, message } => write!(f, "Claim {} can't be paid: {}", claim_id, message),
+            ClearinghouseError::Payer { source, .. } => write!(f, "Payer error: {}", source),
+            ClearinghouseError::Eligibility { payer_id, member_id, source: Some(source) } => {
+                write!(f, "Eligibility inquiry for member {} with {} failed: {}", member_id, payer_id, source)
+            }
+            ClearinghouseError::Eligibility { payer_id, member_id, source: None } => {
+                write!(f, "Eligibility inquiry for member {} is addressed to unknown payer {}", member_id, payer_id)
+            }
+            ClearinghouseError::Remittance { claim_id, field, message } => {
+                write!(f, "Remittance for claim {} {}: {}", claim_id, field, message)
+            }
+            ClearinghouseError::Reference { path: Some(path), message } => write!(f, "{}: {}", path, message),
+            ClearinghouseError::Reference { path: None, message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ClearinghouseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClearinghouseError::Rejected(report) => Some(report),
+            ClearinghouseError::Payer { source, .. } => Some(source),
+            ClearinghouseError::Eligibility { source, .. } => source.as_ref().map(|e| e as &(dyn std::error::Error + 'static)),
+            ClearinghouseError::Duplicate { .. }
+            | ClearinghouseError::UnknownPayer { .. }
+            | ClearinghouseError::Currency { .. }
+            | ClearinghouseError::Remittance { .. }
+            | ClearinghouseError::Reference { .. } => None,
+        }
+    }
+}
+
+impl From<ValidationReport> for ClearinghouseError {
+    fn from(report: ValidationReport) -> Self {
+        ClearinghouseError::Rejected(report)
+    }
+}
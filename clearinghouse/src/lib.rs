@@ -1,166 +1,222 @@
 pub mod types;
+pub mod validation;
+pub mod error;
+pub mod npi;
+pub mod code_sets;
+pub mod duplicates;
+pub mod payers;
+pub mod currency;
 
 pub use types::*;
-
-use intake::{PayerClaim, PayerId};
-use insurance::{Medicare, UnitedHealthGroup, Anthem, Insurance, Remittance};
-use std::collections::HashSet;
-
-pub fn validate_claim(claim: &PayerClaim) -> Result<(), String> {
-    validate_non_empty_fields(claim)?;
-    validate_formats(claim)?;
-    validate_business_rules(claim)?;
-    Ok(())
+pub use validation::{is_icd10_code, validate_claim, ClaimValidator, Severity, ValidationIssue, ValidationReport};
+pub use npi::{is_valid_npi, NpiEntityType, NpiRegistry};
+pub use code_sets::{ModifierTable, ProcedureCodeKind, ProcedureCodeSet};
+pub use duplicates::{DuplicateCheck, DuplicateIndex};
+pub use payers::PayerRegistry;
+pub use currency::{CurrencyTable, ExchangeRateTable};
+pub use intake::{place_of_service, PlaceOfService};
+pub use error::ClearinghouseError;
+
+use chrono::NaiveDate;
+use intake::{ClaimFrequency, Clock, Money, PayerClaim, PayerId, PriorAdjustment, PriorLinePayment, PriorPayment, SystemClock};
+use insurance::{AccumulatorStore, Adjustment, ClaimHistory, CurrencyConversion, EligibilityResponse, Insurance, InsuranceError, MemberRoster, Remittance};
+use std::sync::Arc;
+
+/// Run-wide state shared by every payer in the `PayerRegistry`. One instance must be shared across
+/// every claim in a run so deductibles and out-of-pocket maximums carry over between a member's claims.
+pub struct PayerSettings {
+    pub accumulators: Arc<AccumulatorStore>,
+    /// Claims already adjudicated, so replacements and voids can reverse them
+    pub claim_history: Arc<ClaimHistory>,
+    /// Members each payer covers; payers without rows cover everyone
+    pub roster: Arc<MemberRoster>,
+    /// Makes adjudication reproducible: the same seed and claims always produce the same remittances.
+    pub seed: Option<u64>,
+    /// Drives simulated payer latency; the same clock should stamp claims and age the AR report.
+    pub clock: Arc<dyn Clock>,
+    /// Chance (0-1) that a payer is unavailable; such claims fail with a retryable error
+    pub outage_rate: f64,
 }
 
-fn validate_non_empty_fields(claim: &PayerClaim) -> Result<(), String> {
-    let empty_check = |field: &str, value: &str| {
-        if value.trim().is_empty() {
-            Err(format!("{} cannot be empty", field))
-        } else {
-            Ok(())
-        }
-    };
-
-    empty_check("claim_id", &claim.claim_id)?;
-    empty_check("patient.first_name", &claim.patient.first_name)?;
-    empty_check("patient.last_name", &claim.patient.last_name)?;
-    empty_check("patient.dob", &claim.patient.dob)?;
-    empty_check("organization.name", &claim.organization.name)?;
-    empty_check("rendering_provider.first_name", &claim.rendering_provider.first_name)?;
-    empty_check("rendering_provider.last_name", &claim.rendering_provider.last_name)?;
-    empty_check("rendering_provider.npi", &claim.rendering_provider.npi)?;
-    empty_check("insurance.patient_member_id", &claim.insurance.patient_member_id)?;
-
-    for (i, line) in claim.service_lines.iter().enumerate() {
-        empty_check(&format!("service_lines[{}].service_line_id", i), &line.service_line_id)?;
-        empty_check(&format!("service_lines[{}].procedure_code", i), &line.procedure_code)?;
-        empty_check(&format!("service_lines[{}].details", i), &line.details)?;
-        empty_check(&format!("service_lines[{}].unit_charge_currency", i), &line.unit_charge_currency)?;
+impl Default for PayerSettings {
+    fn default() -> Self {
+        PayerSettings::new()
     }
-
-    Ok(())
 }
 
-fn validate_formats(claim: &PayerClaim) -> Result<(), String> {
-    // NPI validation (10 digits)
-    let validate_npi = |npi: &str, field: &str| {
-        if npi.len() != 10 || !npi.chars().all(|c| c.is_ascii_digit()) {
-            Err(format!("{} must be exactly 10 digits", field))
-        } else {
-            Ok(())
-        }
-    };
-
-    validate_npi(&claim.rendering_provider.npi, "rendering_provider.npi")?;
-    
-    if let Some(ref npi) = claim.organization.billing_npi {
-        validate_npi(npi, "organization.billing_npi")?;
-    }
-
-    // EIN validation (XX-XXXXXXX)
-    if let Some(ref ein) = claim.organization.ein {
-        if ein.len() != 10 || ein.chars().nth(2) != Some('-') || 
-           !ein[..2].chars().all(|c| c.is_ascii_digit()) ||
-           !ein[3..].chars().all(|c| c.is_ascii_digit()) {
-            return Err("organization.ein must match format XX-XXXXXXX".to_string());
+impl PayerSettings {
+    pub fn new() -> PayerSettings {
+        PayerSettings {
+            accumulators: Arc::new(AccumulatorStore::new()),
+            claim_history: Arc::new(ClaimHistory::new()),
+            roster: Arc::new(MemberRoster::new()),
+            seed: None,
+            clock: Arc::new(SystemClock),
+            outage_rate: 0.0,
         }
     }
 
-    // ZIP code validation
-    if let Some(ref address) = claim.patient.address {
-        if let Some(ref zip) = address.zip {
-            let valid_zip = zip.len() == 5 && zip.chars().all(|c| c.is_ascii_digit()) ||
-                          (zip.len() == 10 && zip.chars().nth(5) == Some('-') &&
-                           zip[..5].chars().all(|c| c.is_ascii_digit()) &&
-                           zip[6..].chars().all(|c| c.is_ascii_digit()));
-            if !valid_zip {
-                return Err("patient.address.zip must be XXXXX or XXXXX-XXXX format".to_string());
-            }
-        }
+    pub fn with_seed(seed: u64) -> PayerSettings {
+        PayerSettings { seed: Some(seed), ..PayerSettings::new() }
     }
+}
 
-    // Currency code validation (3 uppercase letters)
-    for (i, line) in claim.service_lines.iter().enumerate() {
-        if line.unit_charge_currency.len() != 3 || !line.unit_charge_currency.chars().all(|c| c.is_ascii_uppercase()) {
-            return Err(format!("service_lines[{}].unit_charge_currency must be 3 uppercase letters", i));
-        }
+/// Adjudicates the claim with the payer it's billed to. An original claim gets one remittance; a
+/// replacement gets the reversal of the claim it replaces followed by its own adjudication, and a
+/// void just the reversal.
+///
+/// A claim in a currency the payer accepts but doesn't settle in is converted into its settlement
+/// currency first, at the rate in effect on the day the claim was received, and the rate is
+/// recorded on the remittance.
+pub fn submit_claim_to_payer(claim: &PayerClaim, payers: &PayerRegistry) -> Result<Vec<Remittance>, ClearinghouseError> {
+    let insurance = payers.resolve(&claim.insurance.payer_id).ok_or_else(|| ClearinghouseError::UnknownPayer {
+        claim_id: claim.claim_id.clone(),
+        payer_id: claim.insurance.payer_id.to_string(),
+    })?;
+    let conversion = settlement_conversion(claim, payers)?;
+    let converted = conversion.as_ref().map(|conversion| currency::convert_claim(claim, conversion));
+
+    let mut remittances = adjudicate(insurance, converted.as_ref().unwrap_or(claim))
+        .map_err(|source| ClearinghouseError::Payer { claim_id: claim.claim_id.clone(), source })?;
+    for remittance in remittances.iter_mut().filter(|r| !r.reversal) {
+        remittance.currency_conversion = conversion.clone();
     }
-
-    Ok(())
+    Ok(remittances)
 }
 
-fn validate_business_rules(claim: &PayerClaim) -> Result<(), String> {
-    // Place of service code range
-    if claim.place_of_service_code < 1 || claim.place_of_service_code > 99 {
-        return Err("place_of_service_code must be between 1-99".to_string());
+// The rate converting the claim into the settlement currency of the payer it's billed to, or `None`
+// when it's already in it
+fn settlement_conversion(claim: &PayerClaim, payers: &PayerRegistry) -> Result<Option<CurrencyConversion>, ClearinghouseError> {
+    let Some(currency) = claim.service_lines.first().map(|line| line.unit_charge_currency.as_str()) else {
+        return Ok(None);
+    };
+    let accepted = payers.accepted_currencies(&claim.insurance.payer_id).unwrap_or_default();
+    let currency_error = |message: String| ClearinghouseError::Currency { claim_id: claim.claim_id.clone(), message };
+    if !accepted.iter().any(|c| c == currency) {
+        return Err(currency_error(format!(
+            "payer {} doesn't accept {} (accepts {})",
+            claim.insurance.payer_id,
+            currency,
+            accepted.join(" ")
+        )));
     }
-
-    // Service lines must not be empty
-    if claim.service_lines.is_empty() {
-        return Err("service_lines must contain at least one item".to_string());
+    let settlement = &accepted[0];
+    if currency == settlement {
+        return Ok(None);
     }
 
-    // Service line business rules
-    let mut service_line_ids = HashSet::new();
-    let first_currency = &claim.service_lines[0].unit_charge_currency;
-
-    for (i, line) in claim.service_lines.iter().enumerate() {
-        // Unique service line IDs
-        if !service_line_ids.insert(&line.service_line_id) {
-            return Err(format!("Duplicate service_line_id: {}", line.service_line_id));
-        }
-
-        // Units must be >= 1
-        if line.units < 1 {
-            return Err(format!("service_lines[{}].units must be at least 1", i));
-        }
-
-        // Amount must be positive
-        if line.unit_charge_amount <= 0.0 {
-            return Err(format!("service_lines[{}].unit_charge_amount must be positive", i));
-        }
-
-        // Currency consistency
-        if line.unit_charge_currency != *first_currency {
-            return Err("All service lines must use the same currency".to_string());
-        }
+    let received = claim.received_date();
+    payers
+        .exchange_rates()
+        .rate(currency, settlement, received)
+        .map(Some)
+        .ok_or_else(|| currency_error(format!("no {} to {} exchange rate in effect on {}", currency, settlement, received)))
+}
 
-        // Line total must be positive
-        let total = line.unit_charge_amount * line.units as f64;
-        if total <= 0.0 {
-            return Err(format!("service_lines[{}] total must be positive", i));
+// A payer's remittance is only passed on if every line balances to the cent. The simulated payers
+// check before keeping anything; this catches any other `Insurance` that doesn't
+fn adjudicate(insurance: &dyn Insurance, claim: &PayerClaim) -> Result<Vec<Remittance>, InsuranceError> {
+    let remittances = match claim.frequency_code {
+        ClaimFrequency::Original => vec![insurance.submit_claim(claim)?],
+        ClaimFrequency::Replacement => {
+            let (reversal, remittance) = insurance.replace_claim(claim)?;
+            vec![reversal, remittance]
         }
+        ClaimFrequency::Void => vec![insurance.reverse_claim(claim)?],
+    };
+    for remittance in &remittances {
+        remittance.check_balance()?;
     }
+    Ok(remittances)
+}
 
-    // Provider NPI != Organization billing NPI
-    if let Some(ref billing_npi) = claim.organization.billing_npi {
-        if &claim.rendering_provider.npi == billing_npi {
-            return Err("rendering_provider.npi cannot equal organization.billing_npi".to_string());
-        }
+/// Builds the claim for the next payer in the patient's coordination of benefits from the
+/// remittances the billed payer returned, carrying what that payer paid and adjusted on each line,
+/// and marks its remittance as crossed over. Returns `None` when no coverage pays after it or the
+/// patient was left owing nothing.
+///
+/// A claim that was converted for the billed payer crosses over in that payer's settlement
+/// currency, the currency of what it paid. Only original claims cross over: a replacement or void
+/// goes to the payer it's billed to alone.
+pub fn crossover_claim(claim: &PayerClaim, remittances: &mut [Remittance]) -> Option<PayerClaim> {
+    let (next, later) = claim.other_insurance.split_first()?;
+    if claim.frequency_code != ClaimFrequency::Original {
+        return None;
+    }
+    let remittance = remittances.iter_mut().rev().find(|r| !r.reversal)?;
+    let patient_responsibility: Money = remittance
+        .service_lines
+        .iter()
+        .map(|line| line.deductible_amount + line.coinsurance_amount + line.copay_amount)
+        .sum();
+    if patient_responsibility <= Money::ZERO {
+        return None;
     }
 
-    Ok(())
+    remittance.crossover_payer_id = Some(next.payer_id.name().to_string());
+    let prior_payment = PriorPayment {
+        payer_id: claim.insurance.payer_id.clone(),
+        payer_claim_control_number: remittance.remittance_id.clone(),
+        service_lines: remittance
+            .service_lines
+            .iter()
+            .map(|line| PriorLinePayment {
+                service_line_id: line.service_line_id.clone(),
+                paid_amount: line.payer_paid_amount,
+                adjustments: line
+                    .adjustments
+                    .iter()
+                    .map(|a| PriorAdjustment { group_code: a.group_code.code().to_string(), reason_code: a.reason_code.clone(), amount: a.amount })
+                    .collect(),
+            })
+            .collect(),
+    };
+    let claim = match remittance.currency_conversion {
+        Some(ref conversion) => currency::convert_claim(claim, conversion),
+        None => claim.clone(),
+    };
+    let mut prior_payments = claim.prior_payments.clone();
+    prior_payments.push(prior_payment);
+
+    Some(PayerClaim {
+        insurance: next.clone(),
+        other_insurance: later.to_vec(),
+        prior_payments,
+        ..claim
+    })
 }
 
-pub fn submit_claim_to_payer(claim: &PayerClaim) -> Result<Remittance, String> {
-    match claim.insurance.payer_id {
-        PayerId::Medicare => {
-            let insurance = Medicare::new();
-            insurance.submit_claim(claim)
-        }
-        PayerId::UnitedHealthGroup => {
-            let insurance = UnitedHealthGroup::new();
-            insurance.submit_claim(claim)
-        }
-        PayerId::Anthem => {
-            let insurance = Anthem::new();
-            insurance.submit_claim(claim)
-        }
-    }
+/// Asks the payer billed as `payer_id` whether the member is covered on `date_of_service` (a 270
+/// eligibility inquiry), so coverage problems surface before a claim is sent.
+pub fn check_eligibility(
+    payer_id: &PayerId,
+    member_id: &str,
+    date_of_service: NaiveDate,
+    payers: &PayerRegistry,
+) -> Result<EligibilityResponse, ClearinghouseError> {
+    let eligibility_error = |source| ClearinghouseError::Eligibility {
+        payer_id: payer_id.to_string(),
+        member_id: member_id.to_string(),
+        source,
+    };
+    let insurance = payers.resolve(payer_id).ok_or_else(|| eligibility_error(None))?;
+    insurance.check_eligibility(member_id, date_of_service).map_err(|e| eligibility_error(Some(e)))
 }
 
-pub fn submit_remittance_to_submitter(remittance: &Remittance) -> Result<ARData, String> {
+pub fn submit_remittance_to_submitter(remittance: &Remittance) -> Result<ARData, ClearinghouseError> {
+    if remittance.service_lines.is_empty() {
+        return Err(ClearinghouseError::Remittance {
+            claim_id: remittance.claim_id.clone(),
+            field: "service_lines".to_string(),
+            message: "remittance has no service lines".to_string(),
+        });
+    }
+    remittance.check_balance().map_err(|e| ClearinghouseError::Remittance {
+        claim_id: remittance.claim_id.clone(),
+        field: "service_lines".to_string(),
+        message: e.to_string(),
+    })?;
+
     Ok(ARData {
         claim_id: remittance.claim_id.clone(),
         remittance_id: remittance.remittance_id.clone(),
@@ -168,12 +224,32 @@ pub fn submit_remittance_to_submitter(remittance: &Remittance) -> Result<ARData,
         payee_npi: remittance.payee_npi.clone(),
         patient_id: remittance.patient_id.clone(),
         initial_claim_ts: remittance.initial_claim_ts,
+        reversal: remittance.reversal,
+        payer_sequence: remittance.payer_sequence,
+        crossover_payer_id: remittance.crossover_payer_id.clone(),
+        currency: remittance.currency.clone(),
+        currency_conversion: remittance.currency_conversion.clone(),
         total_billed_amount: remittance.service_lines.iter().map(|line| line.billed_amount).sum(),
         total_payer_paid_amount: remittance.service_lines.iter().map(|line| line.payer_paid_amount).sum(),
         total_coinsurance_amount: remittance.service_lines.iter().map(|line| line.coinsurance_amount).sum(),
         total_copay_amount: remittance.service_lines.iter().map(|line| line.copay_amount).sum(),
         total_deductible_amount: remittance.service_lines.iter().map(|line| line.deductible_amount).sum(),
         total_not_allowed_amount: remittance.service_lines.iter().map(|line| line.not_allowed_amount).sum(),
+        adjustment_totals: total_adjustments(remittance),
         service_lines: remittance.service_lines.clone(),
     })
+}
+
+fn total_adjustments(remittance: &Remittance) -> Vec<Adjustment> {
+    let mut totals: Vec<Adjustment> = Vec::new();
+    for adjustment in remittance.service_lines.iter().flat_map(|line| &line.adjustments) {
+        match totals
+            .iter_mut()
+            .find(|t| t.group_code == adjustment.group_code && t.reason_code == adjustment.reason_code)
+        {
+            Some(total) => total.amount += adjustment.amount,
+            None => totals.push(adjustment.clone()),
+        }
+    }
+    totals
 }
\ No newline at end of file
@@ -0,0 +1,111 @@
+use crate::ClearinghouseError;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The ISO card issuer prefix for US health identifiers. NPI check digits are computed as if the
+/// NPI were prefixed with it.
+const NPI_PREFIX: &str = "80840";
+
+/// True when `npi` is ten digits and its last digit is the Luhn check digit of `80840` followed
+/// by the first nine digits.
+pub fn is_valid_npi(npi: &str) -> bool {
+    if npi.len() != 10 || !npi.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let sum: u32 = NPI_PREFIX
+        .chars()
+        .chain(npi.chars())
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, digit)| match (i % 2 == 1, digit * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => digit,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// NPPES "Entity Type Code": 1 for individual providers, 2 for organizations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NpiEntityType {
+    Individual,
+    Organization,
+}
+
+/// A local extract of the NPPES registry, used to confirm that rendering NPIs belong to people
+/// and billing NPIs to organizations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NpiRegistry {
+    entries: HashMap<String, NpiEntityType>,
+}
+
+impl NpiRegistry {
+    pub fn new() -> NpiRegistry {
+        NpiRegistry::default()
+    }
+
+    pub fn insert(&mut self, npi: &str, entity_type: NpiEntityType) {
+        self.entries.insert(npi.to_string(), entity_type);
+    }
+
+    pub fn entity_type(&self, npi: &str) -> Option<NpiEntityType> {
+        self.entries.get(npi).copied()
+    }
+
+    /// Parses an NPPES-style CSV. Only the `NPI` and `Entity Type Code` columns are read, so the
+    /// full NPPES download and a trimmed two-column file both work. Quotes around fields and
+    /// rows with an empty entity type (deactivated NPIs) are skipped over.
+    pub fn from_csv(contents: &str) -> Result<NpiRegistry, ClearinghouseError> {
+        let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let split = |line: &str| -> Vec<String> { line.split(',').map(|f| f.trim().trim_matches('"').to_string()).collect() };
+
+        let header = split(lines.next().map(|(_, line)| line).ok_or_else(|| ClearinghouseError::reference("NPI registry is empty"))?);
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(name))
+                .ok_or_else(|| ClearinghouseError::reference(format!("NPI registry header is missing the {} column", name)))
+        };
+        let npi_column = column("NPI")?;
+        let type_column = column("Entity Type Code")?;
+
+        let mut registry = NpiRegistry::new();
+        for (index, line) in lines {
+            let fields = split(line);
+            let (Some(npi), Some(code)) = (fields.get(npi_column), fields.get(type_column)) else {
+                return Err(ClearinghouseError::reference(format!(
+                    "Line {}: expected at least {} fields",
+                    index + 1,
+                    npi_column.max(type_column) + 1
+                )));
+            };
+            let entity_type = match code.as_str() {
+                "1" => NpiEntityType::Individual,
+                "2" => NpiEntityType::Organization,
+                "" => continue,
+                other => {
+                    return Err(ClearinghouseError::reference(format!("Line {}: unknown entity type code {}", index + 1, other)));
+                }
+            };
+            registry.insert(npi, entity_type);
+        }
+        Ok(registry)
+    }
+
+    pub fn load(path: &Path) -> Result<NpiRegistry, ClearinghouseError> {
+        let with_path = |message: String| ClearinghouseError::Reference { path: Some(path.display().to_string()), message };
+        let contents = std::fs::read_to_string(path).map_err(|e| with_path(format!("failed to read NPI registry: {}", e)))?;
+        NpiRegistry::from_csv(&contents).map_err(|e| with_path(e.to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
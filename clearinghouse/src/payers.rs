@@ -0,0 +1,240 @@
+use crate::code_sets::{csv_rows, load_with};
+use crate::currency::{CurrencyTable, ExchangeRateTable};
+use crate::{ClearinghouseError, PayerSettings};
+use insurance::{Anthem, Insurance, Medicare, PlanCatalog, PlanPayer, UnitedHealthGroup};
+use intake::PayerId;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Maps payer ids to the payers that adjudicate their claims.
+///
+/// Built once at startup, so every claim for a payer reaches the same instance. Ids and aliases
+/// are matched case-insensitively; claims for a payer that isn't registered are rejected.
+///
+/// Each payer accepts claims in a list of currencies and pays in the first of them, its settlement
+/// currency; claims in the others are converted with the registry's exchange rates.
+#[derive(Default)]
+pub struct PayerRegistry {
+    payers: HashMap<String, Box<dyn Insurance>>,
+    aliases: HashMap<String, String>,
+    currencies: HashMap<String, Vec<String>>,
+    exchange_rates: ExchangeRateTable,
+}
+
+// payers that don't list the currencies they accept take US dollars only
+const DEFAULT_CURRENCY: &str = "USD";
+
+impl PayerRegistry {
+    /// A registry with no payers at all.
+    pub fn new() -> PayerRegistry {
+        PayerRegistry::default()
+    }
+
+    /// The three simulated payers, sharing the run-wide `settings`.
+    pub fn with_builtins(settings: &PayerSettings) -> PayerRegistry {
+        let mut registry = PayerRegistry::new();
+        for payer_id in [PayerId::Medicare, PayerId::UnitedHealthGroup, PayerId::Anthem] {
+            let payer = simulator(payer_id.as_str(), payer_id.name(), settings).expect("every built-in payer is a simulator");
+            registry.register(payer_id.as_str(), payer);
+        }
+        registry
+    }
+
+    /// Adds a payer accepting US dollars only, replacing whichever payer was registered under
+    /// `payer_id` before.
+    pub fn register(&mut self, payer_id: &str, payer: Box<dyn Insurance>) {
+        self.payers.insert(normalize(payer_id), payer);
+        self.currencies.insert(normalize(payer_id), vec![DEFAULT_CURRENCY.to_string()]);
+    }
+
+    /// Sets the currencies the payer registered as `payer_id` accepts claims in; it pays in the
+    /// first one.
+    pub fn accept_currencies(&mut self, payer_id: &str, currencies: &[&str]) -> Result<(), ClearinghouseError> {
+        let payer_id = normalize(payer_id);
+        if !self.payers.contains_key(&payer_id) {
+            return Err(ClearinghouseError::reference(format!("currencies given for unknown payer {}", payer_id)));
+        }
+        if currencies.is_empty() {
+            return Err(ClearinghouseError::reference(format!("payer {} must accept at least one currency", payer_id)));
+        }
+        self.currencies.insert(payer_id, currencies.iter().map(|c| c.to_string()).collect());
+        Ok(())
+    }
+
+    /// The currencies claims billed to `payer_id` may be in, settlement currency first, following
+    /// aliases.
+    pub fn accepted_currencies(&self, payer_id: &PayerId) -> Option<&[String]> {
+        self.currencies.get(&self.key(payer_id)).map(|currencies| currencies.as_slice())
+    }
+
+    /// Converts claims in a currency their payer accepts but doesn't settle in with `rates`.
+    pub fn with_exchange_rates(mut self, rates: ExchangeRateTable) -> PayerRegistry {
+        self.exchange_rates = rates;
+        self
+    }
+
+    pub fn exchange_rates(&self) -> &ExchangeRateTable {
+        &self.exchange_rates
+    }
+
+    /// Sends claims billed to `alias` to the payer registered as `payer_id`.
+    pub fn alias(&mut self, alias: &str, payer_id: &str) -> Result<(), ClearinghouseError> {
+        let payer_id = normalize(payer_id);
+        if !self.payers.contains_key(&payer_id) {
+            return Err(ClearinghouseError::reference(format!("alias {} refers to unknown payer {}", alias, payer_id)));
+        }
+        self.aliases.insert(normalize(alias), payer_id);
+        Ok(())
+    }
+
+    /// The payer that adjudicates claims billed to `payer_id`, following aliases.
+    pub fn resolve(&self, payer_id: &PayerId) -> Option<&dyn Insurance> {
+        self.payers.get(&self.key(payer_id)).map(|payer| payer.as_ref())
+    }
+
+    /// The id of the payer registered for `payer_id`, following aliases, so every id a payer's
+    /// claims may be billed under (e.g. an X12 payer id) compares equal. `None` when no payer is
+    /// registered under it.
+    pub fn canonical_id(&self, payer_id: &PayerId) -> Option<PayerId> {
+        let id = self.key(payer_id);
+        self.payers.contains_key(&id).then(|| PayerId::new(&id))
+    }
+
+    fn key(&self, payer_id: &PayerId) -> String {
+        let id = normalize(payer_id.as_str());
+        self.aliases.get(&id).cloned().unwrap_or(id)
+    }
+
+    /// Parses `payer_id,simulator,aliases,currencies` rows and registers them alongside the
+    /// built-in payers. `simulator` is the built-in payer whose adjudication rules the payer
+    /// follows, or a `.json` plan catalog (see `insurance::PlanCatalog`) it adjudicates from
+    /// instead, `aliases` is a space separated list of other ids its claims may be billed under,
+    /// and `currencies` a space separated list of the ISO 4217 currencies it accepts, settlement
+    /// currency first (US dollars only when blank).
+    ///
+    /// Relative plan catalog paths are resolved from the working directory.
+    pub fn from_csv(contents: &str, settings: &PayerSettings) -> Result<PayerRegistry, ClearinghouseError> {
+        PayerRegistry::parse(contents, settings, Path::new(""))
+    }
+
+    /// Loads a payer file; relative plan catalog paths are resolved from the file's directory.
+    pub fn load(path: &Path, settings: &PayerSettings) -> Result<PayerRegistry, ClearinghouseError> {
+        let base = path.parent().unwrap_or(Path::new(""));
+        load_with(path, |contents| PayerRegistry::parse(contents, settings, base))
+    }
+
+    fn parse(contents: &str, settings: &PayerSettings, base: &Path) -> Result<PayerRegistry, ClearinghouseError> {
+        let mut registry = PayerRegistry::with_builtins(settings);
+        let rows = csv_rows(contents, "payer_id,simulator,aliases,currencies", 4)?;
+        let iso_currencies = CurrencyTable::standard();
+
+        for (line_number, fields) in &rows {
+            let payer_id = PayerId::new(&normalize(fields[0]));
+            let payer = if fields[1].ends_with(".json") {
+                let plans = PlanCatalog::load(&base.join(fields[1]))
+                    .map_err(|e| ClearinghouseError::reference(format!("Line {}: {}", line_number, e)))?;
+                plan_payer(payer_id.name(), plans, settings)
+            } else {
+                simulator(&normalize(fields[1]), payer_id.name(), settings).ok_or_else(|| {
+                    ClearinghouseError::reference(format!(
+                        "Line {}: unknown simulator {} (expected medicare, united_health_group, anthem or a .json plan catalog)",
+                        line_number, fields[1]
+                    ))
+                })?
+            };
+            registry.register(payer_id.as_str(), payer);
+
+            let currencies: Vec<&str> = fields[3].split_whitespace().collect();
+            if let Some(code) = currencies.iter().find(|code| !iso_currencies.contains(code)) {
+                return Err(ClearinghouseError::reference(format!("Line {}: {} is not an ISO 4217 currency code", line_number, code)));
+            }
+            if !currencies.is_empty() {
+                registry.accept_currencies(payer_id.as_str(), &currencies)?;
+            }
+        }
+        // aliases may name payers registered further down the file
+        for (line_number, fields) in &rows {
+            for alias in fields[2].split_whitespace() {
+                registry
+                    .alias(alias, fields[0])
+                    .map_err(|e| ClearinghouseError::reference(format!("Line {}: {}", line_number, e)))?;
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.payers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.payers.is_empty()
+    }
+}
+
+impl fmt::Debug for PayerRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut payer_ids: Vec<&String> = self.payers.keys().collect();
+        payer_ids.sort();
+        f.debug_struct("PayerRegistry")
+            .field("payers", &payer_ids)
+            .field("aliases", &self.aliases)
+            .field("currencies", &self.currencies)
+            .field("exchange_rates", &self.exchange_rates)
+            .finish()
+    }
+}
+
+fn normalize(payer_id: &str) -> String {
+    payer_id.trim().to_ascii_lowercase()
+}
+
+/// One of the simulated payers, going by `name` on remittances.
+fn simulator(kind: &str, name: &str, settings: &PayerSettings) -> Option<Box<dyn Insurance>> {
+    let accumulators = Arc::clone(&settings.accumulators);
+    let payer: Box<dyn Insurance> = match kind {
+        "medicare" => Box::new(
+            Medicare::with_accumulators(accumulators)
+                .with_name(name)
+                .with_claim_history(Arc::clone(&settings.claim_history))
+                .with_roster(Arc::clone(&settings.roster))
+                .with_seed(settings.seed)
+                .with_clock(Arc::clone(&settings.clock))
+                .with_outage_rate(settings.outage_rate),
+        ),
+        "united_health_group" => Box::new(
+            UnitedHealthGroup::with_accumulators(accumulators)
+                .with_name(name)
+                .with_claim_history(Arc::clone(&settings.claim_history))
+                .with_roster(Arc::clone(&settings.roster))
+                .with_seed(settings.seed)
+                .with_clock(Arc::clone(&settings.clock))
+                .with_outage_rate(settings.outage_rate),
+        ),
+        "anthem" => Box::new(
+            Anthem::with_accumulators(accumulators)
+                .with_name(name)
+                .with_claim_history(Arc::clone(&settings.claim_history))
+                .with_roster(Arc::clone(&settings.roster))
+                .with_seed(settings.seed)
+                .with_clock(Arc::clone(&settings.clock))
+                .with_outage_rate(settings.outage_rate),
+        ),
+        _ => return None,
+    };
+    Some(payer)
+}
+
+/// A payer adjudicating from `plans`, going by `name` on remittances.
+fn plan_payer(name: &str, plans: PlanCatalog, settings: &PayerSettings) -> Box<dyn Insurance> {
+    Box::new(
+        PlanPayer::with_accumulators(name, plans, Arc::clone(&settings.accumulators))
+            .with_claim_history(Arc::clone(&settings.claim_history))
+            .with_roster(Arc::clone(&settings.roster))
+            .with_seed(settings.seed)
+            .with_clock(Arc::clone(&settings.clock))
+            .with_outage_rate(settings.outage_rate),
+    )
+}
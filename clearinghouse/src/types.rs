@@ -1,5 +1,6 @@
+use intake::Money;
 use serde::{Serialize, Deserialize};
-use insurance::ServiceLine;
+use insurance::{Adjustment, CurrencyConversion, ServiceLine};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ARData {
@@ -9,11 +10,33 @@ pub struct ARData {
     pub payee_npi: String,
     pub patient_id: String,
     pub initial_claim_ts: i64,
-    pub total_billed_amount: f64,
-    pub total_payer_paid_amount: f64,
-    pub total_coinsurance_amount: f64,
-    pub total_copay_amount: f64,
-    pub total_deductible_amount: f64,
-    pub total_not_allowed_amount: f64,
+    /// Takes back the entry with the same `remittance_id`; its totals are negated, so summing the
+    /// ledger nets the original out
+    #[serde(default)]
+    pub reversal: bool,
+    /// 1 when the payer was the patient's primary, 2 for the secondary and so on
+    pub payer_sequence: u8,
+    /// The payer this entry's patient responsibility was passed on to; that payer's entry holds
+    /// what the patient still owes
+    #[serde(default)]
+    pub crossover_payer_id: Option<String>,
+    /// ISO 4217 code of the currency the totals are in
+    #[serde(default = "settlement_currency")]
+    pub currency: String,
+    /// The exchange rate the claim was converted into `currency` with, if it was billed in another
+    #[serde(default)]
+    pub currency_conversion: Option<CurrencyConversion>,
+    pub total_billed_amount: Money,
+    pub total_payer_paid_amount: Money,
+    pub total_coinsurance_amount: Money,
+    pub total_copay_amount: Money,
+    pub total_deductible_amount: Money,
+    pub total_not_allowed_amount: Money,
+    /// Adjustments summed per group/reason code across all service lines
+    pub adjustment_totals: Vec<Adjustment>,
     pub service_lines: Vec<ServiceLine>,
-}
\ No newline at end of file
+}
+
+fn settlement_currency() -> String {
+    "USD".to_string()
+}
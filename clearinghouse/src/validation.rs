@@ -0,0 +1,598 @@
+use crate::code_sets::{ModifierTable, ProcedureCodeKind, ProcedureCodeSet};
+use crate::currency::{is_currency_code, CurrencyTable};
+use crate::npi::{is_valid_npi, NpiEntityType, NpiRegistry};
+use crate::payers::PayerRegistry;
+use intake::{place_of_service, ClaimFrequency, Insurance, MedicareEntitlement, Money, PayerClaim, PayerId, Relationship};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The claim is rejected
+    Error,
+    /// Reported back to the submitter, but the claim is still sent to the payer
+    Warning,
+}
+
+/// One failed rule. `code` is stable across releases so submitters can key off it; `path`
+/// points at the offending field, e.g. `service_lines[2].units`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub code: String,
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+/// Every rule a claim failed, in the order the rules are checked. Serializes to the rejection
+/// file returned to the submitter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub claim_id: String,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    fn new(claim_id: &str) -> ValidationReport {
+        ValidationReport { claim_id: claim_id.to_string(), issues: Vec::new() }
+    }
+
+    fn error(&mut self, code: &str, path: impl Into<String>, message: impl Into<String>) {
+        self.push(code, Severity::Error, path.into(), message.into());
+    }
+
+    fn warning(&mut self, code: &str, path: impl Into<String>, message: impl Into<String>) {
+        self.push(code, Severity::Warning, path.into(), message.into());
+    }
+
+    fn push(&mut self, code: &str, severity: Severity, path: String, message: String) {
+        self.issues.push(ValidationIssue { code: code.to_string(), severity, path, message });
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Warning)
+    }
+
+    /// True when nothing blocks submission (warnings are allowed).
+    pub fn is_accepted(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("validation reports always serialize")
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self.errors().map(|issue| issue.message.as_str()).collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationReport {}
+
+// professional claims (SV101) have room for four modifiers per line
+const MAX_MODIFIERS: usize = 4;
+
+/// Validation rules plus the reference data some of them need. Rules whose reference data isn't
+/// configured are skipped.
+#[derive(Debug, Clone)]
+pub struct ClaimValidator {
+    npi_registry: Option<NpiRegistry>,
+    modifiers: ModifierTable,
+    procedure_codes: Option<ProcedureCodeSet>,
+    currencies: CurrencyTable,
+    payers: Option<Arc<PayerRegistry>>,
+}
+
+impl Default for ClaimValidator {
+    fn default() -> Self {
+        ClaimValidator::new()
+    }
+}
+
+impl ClaimValidator {
+    /// Uses the bundled modifier table and ISO 4217 currency list, and checks procedure codes by
+    /// shape only.
+    pub fn new() -> ClaimValidator {
+        ClaimValidator {
+            npi_registry: None,
+            modifiers: ModifierTable::standard(),
+            procedure_codes: None,
+            currencies: CurrencyTable::standard(),
+            payers: None,
+        }
+    }
+
+    /// Also checks that rendering NPIs belong to individuals and billing NPIs to organizations.
+    pub fn with_npi_registry(mut self, registry: NpiRegistry) -> ClaimValidator {
+        self.npi_registry = Some(registry);
+        self
+    }
+
+    /// Replaces the bundled modifier table, e.g. with this year's release.
+    pub fn with_modifier_table(mut self, modifiers: ModifierTable) -> ClaimValidator {
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// Replaces the bundled currency list, e.g. with the latest ISO 4217 amendment.
+    pub fn with_currency_table(mut self, currencies: CurrencyTable) -> ClaimValidator {
+        self.currencies = currencies;
+        self
+    }
+
+    /// Also rejects well-formed procedure codes that aren't in `procedure_codes`.
+    pub fn with_procedure_codes(mut self, procedure_codes: ProcedureCodeSet) -> ClaimValidator {
+        self.procedure_codes = Some(procedure_codes);
+        self
+    }
+
+    /// Compares coverages by the payer their ids resolve to in `payers`, so a payer billed under
+    /// one of its aliases is still recognised.
+    pub fn with_payer_registry(mut self, payers: Arc<PayerRegistry>) -> ClaimValidator {
+        self.payers = Some(payers);
+        self
+    }
+
+    /// Checks every rule and collects all failures. Returns `Ok` (possibly with warnings) when
+    /// the claim can be submitted and `Err` when at least one error-severity rule failed.
+    pub fn validate(&self, claim: &PayerClaim) -> Result<ValidationReport, ValidationReport> {
+        let mut report = ValidationReport::new(&claim.claim_id);
+        validate_non_empty_fields(claim, &mut report);
+        validate_formats(claim, &mut report);
+        self.validate_currencies(claim, &mut report);
+        validate_business_rules(claim, self.payers.as_deref(), &mut report);
+        validate_diagnoses(claim, &mut report);
+        self.validate_procedures(claim, &mut report);
+        if let Some(ref registry) = self.npi_registry {
+            validate_npi_entity_types(claim, registry, &mut report);
+        }
+
+        if report.is_accepted() {
+            Ok(report)
+        } else {
+            Err(report)
+        }
+    }
+
+    fn validate_currencies(&self, claim: &PayerClaim, report: &mut ValidationReport) {
+        for (i, line) in claim.service_lines.iter().enumerate() {
+            let currency = &line.unit_charge_currency;
+            let path = format!("service_lines[{}].unit_charge_currency", i);
+            if !is_currency_code(currency) {
+                report.error("CURRENCY_FORMAT", path.clone(), format!("{} must be 3 uppercase letters", path));
+                continue;
+            }
+            match self.currencies.minor_units(currency) {
+                None => report.error("CURRENCY_UNKNOWN", path.clone(), format!("{} {} is not an ISO 4217 currency code", path, currency)),
+                // amounts are held in cents, so only currencies without them can be too precise
+                Some(0) if line.unit_charge_amount.cents() % 100 != 0 => {
+                    let path = format!("service_lines[{}].unit_charge_amount", i);
+                    report.error(
+                        "CURRENCY_MINOR_UNITS",
+                        path.clone(),
+                        format!("{} {} has decimals, but {} amounts are whole units", path, line.unit_charge_amount, currency),
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn validate_procedures(&self, claim: &PayerClaim, report: &mut ValidationReport) {
+        for (i, line) in claim.service_lines.iter().enumerate() {
+            let code = &line.procedure_code;
+            let path = format!("service_lines[{}].procedure_code", i);
+            // an empty code is already reported as missing
+            if !code.trim().is_empty() && ProcedureCodeKind::of(code).is_none() {
+                report.error(
+                    "PROCEDURE_CODE_FORMAT",
+                    path.clone(),
+                    format!("{} {} is not a CPT (five characters) or HCPCS Level II code", path, code),
+                );
+            } else if !code.trim().is_empty() && self.procedure_codes.as_ref().is_some_and(|codes| !codes.contains(code)) {
+                report.error("PROCEDURE_CODE_UNKNOWN", path.clone(), format!("{} {} is not in the procedure code set", path, code));
+            }
+
+            let modifiers = line.modifiers.as_deref().unwrap_or(&[]);
+            if modifiers.len() > MAX_MODIFIERS {
+                let path = format!("service_lines[{}].modifiers", i);
+                report.error(
+                    "MODIFIER_LIMIT",
+                    path.clone(),
+                    format!("{} has {} modifiers; at most {} are allowed", path, modifiers.len(), MAX_MODIFIERS),
+                );
+            }
+            for (j, modifier) in modifiers.iter().enumerate() {
+                if !self.modifiers.contains(modifier) {
+                    let path = format!("service_lines[{}].modifiers[{}]", i, j);
+                    report.error("MODIFIER_UNKNOWN", path.clone(), format!("{} {} is not a recognized modifier", path, modifier));
+                }
+                for other in &modifiers[j + 1..] {
+                    if self.modifiers.conflicts(modifier, other) {
+                        report.error(
+                            "MODIFIER_CONFLICT",
+                            format!("service_lines[{}].modifiers", i),
+                            format!("service_lines[{}].modifiers {} and {} can't be billed together", i, modifier, other),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Validates a claim with the built-in rules only; see [`ClaimValidator`] for rules that need
+/// reference data.
+pub fn validate_claim(claim: &PayerClaim) -> Result<ValidationReport, ValidationReport> {
+    ClaimValidator::new().validate(claim)
+}
+
+fn validate_non_empty_fields(claim: &PayerClaim, report: &mut ValidationReport) {
+    let mut empty_check = |field: String, value: &str| {
+        if value.trim().is_empty() {
+            report.error("REQUIRED_FIELD", field.clone(), format!("{} cannot be empty", field));
+        }
+    };
+
+    empty_check("claim_id".to_string(), &claim.claim_id);
+    empty_check("patient.first_name".to_string(), &claim.patient.first_name);
+    empty_check("patient.last_name".to_string(), &claim.patient.last_name);
+    empty_check("organization.name".to_string(), &claim.organization.name);
+    empty_check("rendering_provider.first_name".to_string(), &claim.rendering_provider.first_name);
+    empty_check("rendering_provider.last_name".to_string(), &claim.rendering_provider.last_name);
+    empty_check("rendering_provider.npi".to_string(), &claim.rendering_provider.npi);
+    for (path, insurance) in coverages(claim) {
+        empty_check(format!("{}.patient_member_id", path), &insurance.patient_member_id);
+        if let Some(ref subscriber) = insurance.subscriber {
+            empty_check(format!("{}.subscriber.last_name", path), &subscriber.last_name);
+        }
+    }
+
+    for (i, line) in claim.service_lines.iter().enumerate() {
+        empty_check(format!("service_lines[{}].service_line_id", i), &line.service_line_id);
+        empty_check(format!("service_lines[{}].procedure_code", i), &line.procedure_code);
+        empty_check(format!("service_lines[{}].details", i), &line.details);
+        empty_check(format!("service_lines[{}].unit_charge_currency", i), &line.unit_charge_currency);
+    }
+}
+
+fn validate_formats(claim: &PayerClaim, report: &mut ValidationReport) {
+    // NPI validation (10 digits with a Luhn check digit); an empty NPI is already reported as missing
+    let mut validate_npi = |npi: &str, field: &str| {
+        if npi.trim().is_empty() {
+            return;
+        }
+        if npi.len() != 10 || !npi.chars().all(|c| c.is_ascii_digit()) {
+            report.error("NPI_FORMAT", field, format!("{} must be exactly 10 digits", field));
+        } else if !is_valid_npi(npi) {
+            report.error("NPI_CHECK_DIGIT", field, format!("{} has an invalid check digit", field));
+        }
+    };
+
+    validate_npi(&claim.rendering_provider.npi, "rendering_provider.npi");
+
+    if let Some(ref npi) = claim.organization.billing_npi {
+        validate_npi(npi, "organization.billing_npi");
+    }
+
+    // EIN validation (XX-XXXXXXX)
+    if let Some(ref ein) = claim.organization.ein
+        && (!ein.is_ascii() || ein.len() != 10 || ein.chars().nth(2) != Some('-') ||
+           !ein[..2].chars().all(|c| c.is_ascii_digit()) ||
+           !ein[3..].chars().all(|c| c.is_ascii_digit())) {
+            report.error("EIN_FORMAT", "organization.ein", "organization.ein must match format XX-XXXXXXX");
+        }
+
+    // ZIP code validation
+    if let Some(ref address) = claim.patient.address
+        && let Some(ref zip) = address.zip {
+            let valid_zip = zip.len() == 5 && zip.chars().all(|c| c.is_ascii_digit()) ||
+                          (zip.is_ascii() && zip.len() == 10 && zip.chars().nth(5) == Some('-') &&
+                           zip[..5].chars().all(|c| c.is_ascii_digit()) &&
+                           zip[6..].chars().all(|c| c.is_ascii_digit()));
+            if !valid_zip {
+                report.error("ZIP_FORMAT", "patient.address.zip", "patient.address.zip must be XXXXX or XXXXX-XXXX format");
+            }
+        }
+}
+
+fn validate_business_rules(claim: &PayerClaim, registry: Option<&PayerRegistry>, report: &mut ValidationReport) {
+    // Place of service code must be in range and assigned by CMS
+    if claim.place_of_service_code < 1 || claim.place_of_service_code > 99 {
+        report.error("PLACE_OF_SERVICE_RANGE", "place_of_service_code", "place_of_service_code must be between 1-99");
+    } else if place_of_service(claim.place_of_service_code).is_none() {
+        report.error(
+            "PLACE_OF_SERVICE_UNASSIGNED",
+            "place_of_service_code",
+            format!("place_of_service_code {:02} is not an assigned CMS place of service", claim.place_of_service_code),
+        );
+    }
+
+    let mut payers = HashSet::new();
+    for (path, insurance) in coverages(claim) {
+        // A dependent's claim must say whose policy covers them (the 2010BA subscriber)
+        match (insurance.relationship, &insurance.subscriber) {
+            (Relationship::SelfInsured, Some(_)) => report.warning(
+                "SUBSCRIBER_IGNORED",
+                format!("{}.subscriber", path),
+                format!("{}.subscriber is ignored when the patient is the subscriber (relationship 18)", path),
+            ),
+            (relationship, None) if relationship != Relationship::SelfInsured => report.error(
+                "SUBSCRIBER_MISSING",
+                format!("{}.subscriber", path),
+                format!("{}.subscriber is required for relationship code {}", path, relationship.code()),
+            ),
+            _ => {}
+        }
+
+        // Group or policy number: SBR03 allows 1-50 characters
+        if let Some(ref group_number) = insurance.group_number {
+            let valid = !group_number.trim().is_empty()
+                && group_number.len() <= 50
+                && group_number.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == ' ');
+            if !valid {
+                report.error(
+                    "GROUP_NUMBER_FORMAT",
+                    format!("{}.group_number", path),
+                    format!("{}.group_number must be 1-50 letters, digits, spaces or dashes", path),
+                );
+            }
+        }
+
+        // Each payer pays once; a crossover would otherwise go back to a payer that already paid
+        if !payers.insert(payer_of(&insurance.payer_id, registry)) {
+            report.error(
+                "COVERAGE_PAYER_REPEATED",
+                format!("{}.payer_id", path),
+                format!("{}.payer_id {} is already one of the claim's coverages", path, insurance.payer_id),
+            );
+        }
+    }
+
+    // A patient can't be born after the claim was received
+    let received = claim.received_date();
+    let dob = claim.patient.dob;
+    if dob > received {
+        report.error("DOB_FUTURE", "patient.dob", format!("patient.dob {} is after the claim was received on {}", dob, received));
+    }
+
+    // Medicare covers people under 65 only through a disability or end-stage renal disease
+    let age = claim.patient_age();
+    let entitled_under_65 = matches!(
+        claim.patient.medicare_entitlement,
+        Some(MedicareEntitlement::Disability | MedicareEntitlement::Esrd)
+    );
+    if age < 65 && !entitled_under_65 && claim.coverages().any(|insurance| payer_of(&insurance.payer_id, registry) == PayerId::Medicare) {
+        report.warning(
+            "MEDICARE_UNDER_65",
+            "patient.medicare_entitlement",
+            format!("patient is {} on the date of service; Medicare covers patients under 65 only with a disability or esrd entitlement", age),
+        );
+    }
+
+    // Dates of service can't be after the claim was received or before the patient was born
+    for (i, line) in claim.service_lines.iter().enumerate() {
+        let Some(from) = line.service_date_from else {
+            if line.service_date_to.is_some() {
+                let path = format!("service_lines[{}].service_date_from", i);
+                report.error("SERVICE_DATE_MISSING", path.clone(), format!("{} is required when service_date_to is given", path));
+            }
+            continue;
+        };
+        let to = line.service_date_to.unwrap_or(from);
+        if to < from {
+            let path = format!("service_lines[{}].service_date_to", i);
+            report.error("SERVICE_DATE_RANGE", path.clone(), format!("{} {} is before service_date_from {}", path, to, from));
+        }
+        if to > received {
+            let path = format!("service_lines[{}].{}", i, if line.service_date_to.is_some() { "service_date_to" } else { "service_date_from" });
+            report.error("SERVICE_DATE_FUTURE", path.clone(), format!("{} {} is after the claim was received on {}", path, to, received));
+        }
+        if from < dob {
+            let path = format!("service_lines[{}].service_date_from", i);
+            report.error("SERVICE_DATE_BEFORE_BIRTH", path.clone(), format!("{} {} is before the patient's date of birth {}", path, from, dob));
+        }
+    }
+
+    // Earlier payers' adjudications must be of the lines being billed
+    let line_ids: HashSet<&str> = claim.service_lines.iter().map(|line| line.service_line_id.as_str()).collect();
+    for (i, prior) in claim.prior_payments.iter().enumerate() {
+        for (j, line) in prior.service_lines.iter().enumerate() {
+            if !line_ids.contains(line.service_line_id.as_str()) {
+                let path = format!("prior_payments[{}].service_lines[{}].service_line_id", i, j);
+                report.error(
+                    "PRIOR_PAYMENT_LINE_UNKNOWN",
+                    path.clone(),
+                    format!("{} {} is not one of the claim's service lines", path, line.service_line_id),
+                );
+            }
+        }
+    }
+
+    // Replacements and voids must name the adjudicated claim they correct
+    if claim.frequency_code != ClaimFrequency::Original
+        && claim.payer_claim_control_number.as_deref().is_none_or(|n| n.trim().is_empty())
+    {
+        report.error(
+            "PAYER_CLAIM_CONTROL_NUMBER_MISSING",
+            "payer_claim_control_number",
+            format!("payer_claim_control_number is required for frequency code {}", claim.frequency_code.code()),
+        );
+    }
+
+    // Service lines must not be empty
+    if claim.service_lines.is_empty() {
+        report.error("SERVICE_LINES_EMPTY", "service_lines", "service_lines must contain at least one item");
+        return;
+    }
+
+    // Service line business rules
+    let mut service_line_ids = HashSet::new();
+    let first_currency = &claim.service_lines[0].unit_charge_currency;
+
+    for (i, line) in claim.service_lines.iter().enumerate() {
+        // Unique service line IDs
+        if !service_line_ids.insert(&line.service_line_id) {
+            report.error(
+                "DUPLICATE_SERVICE_LINE_ID",
+                format!("service_lines[{}].service_line_id", i),
+                format!("Duplicate service_line_id: {}", line.service_line_id),
+            );
+        }
+
+        // Units must be >= 1
+        if line.units < 1 {
+            let path = format!("service_lines[{}].units", i);
+            report.error("UNITS_MINIMUM", path.clone(), format!("{} must be at least 1", path));
+        }
+
+        // Amount must be positive
+        if line.unit_charge_amount <= Money::ZERO {
+            let path = format!("service_lines[{}].unit_charge_amount", i);
+            report.error("CHARGE_NOT_POSITIVE", path.clone(), format!("{} must be positive", path));
+        }
+
+        // Currency consistency
+        if line.unit_charge_currency != *first_currency {
+            report.error(
+                "CURRENCY_MISMATCH",
+                format!("service_lines[{}].unit_charge_currency", i),
+                "All service lines must use the same currency",
+            );
+        }
+
+        // The line total (units times the unit charge) has to fit in an amount
+        if line.unit_charge_amount.checked_mul(line.units).is_none() {
+            report.error("LINE_TOTAL_TOO_LARGE", format!("service_lines[{}]", i), format!("service_lines[{}] total is too large", i));
+        }
+    }
+
+    if claim.service_lines.iter().all(|line| line.do_not_bill.unwrap_or(false)) {
+        report.warning("NOTHING_BILLABLE", "service_lines", "every service line is marked do_not_bill; nothing will be paid");
+    }
+
+    // Provider NPI != Organization billing NPI
+    match claim.organization.billing_npi {
+        Some(ref billing_npi) if &claim.rendering_provider.npi == billing_npi => {
+            report.error(
+                "NPI_CONFLICT",
+                "organization.billing_npi",
+                "rendering_provider.npi cannot equal organization.billing_npi",
+            );
+        }
+        Some(_) => {}
+        None => {
+            report.warning(
+                "BILLING_NPI_MISSING",
+                "organization.billing_npi",
+                "organization.billing_npi is missing; the remittance will have no payee",
+            );
+        }
+    }
+}
+
+// Every coverage with the path its fields are reported under, primary first.
+// The payer a coverage is billed to: whatever `registry` resolves its id or alias to, or the id
+// itself, matched case-insensitively, when no registry is configured or none is registered under it
+fn payer_of(payer_id: &PayerId, registry: Option<&PayerRegistry>) -> PayerId {
+    registry
+        .and_then(|registry| registry.canonical_id(payer_id))
+        .unwrap_or_else(|| PayerId::new(&payer_id.as_str().trim().to_ascii_lowercase()))
+}
+
+fn coverages(claim: &PayerClaim) -> impl Iterator<Item = (String, &Insurance)> {
+    claim.coverages().enumerate().map(|(i, insurance)| match i {
+        0 => ("insurance".to_string(), insurance),
+        i => (format!("other_insurance[{}]", i - 1), insurance),
+    })
+}
+
+fn validate_npi_entity_types(claim: &PayerClaim, registry: &NpiRegistry, report: &mut ValidationReport) {
+    let mut check = |npi: &str, field: &str, expected: NpiEntityType| {
+        // malformed NPIs were already reported by validate_formats
+        if !is_valid_npi(npi) {
+            return;
+        }
+        match registry.entity_type(npi) {
+            Some(entity_type) if entity_type == expected => {}
+            Some(entity_type) => report.error(
+                "NPI_ENTITY_TYPE",
+                field,
+                format!("{} {} is registered as {:?}, expected {:?}", field, npi, entity_type, expected),
+            ),
+            None => report.warning("NPI_NOT_REGISTERED", field, format!("{} {} is not in the NPI registry", field, npi)),
+        }
+    };
+
+    check(&claim.rendering_provider.npi, "rendering_provider.npi", NpiEntityType::Individual);
+    if let Some(ref npi) = claim.organization.billing_npi {
+        check(npi, "organization.billing_npi", NpiEntityType::Organization);
+    }
+}
+
+/// ICD-10-CM shape: a letter, a digit, a digit or letter, then up to four more characters,
+/// optionally after a dot (`E11`, `E11.9`, `S72.001A`). Whether the code exists isn't checked.
+pub fn is_icd10_code(code: &str) -> bool {
+    // every valid character is ASCII, and splitting below is by byte
+    if !code.is_ascii() {
+        return false;
+    }
+    let (category, subcategory) = match code.split_once('.') {
+        Some((category, subcategory)) if !subcategory.is_empty() => (category, subcategory),
+        Some(_) => return false,
+        None => code.split_at(code.len().min(3)),
+    };
+    let category: Vec<char> = category.chars().collect();
+
+    category.len() == 3
+        && category[0].is_ascii_uppercase()
+        && category[1].is_ascii_digit()
+        && (category[2].is_ascii_digit() || category[2].is_ascii_uppercase())
+        && subcategory.len() <= 4
+        && subcategory.chars().all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+}
+
+fn validate_diagnoses(claim: &PayerClaim, report: &mut ValidationReport) {
+    if claim.diagnoses.is_empty() {
+        report.error("DIAGNOSES_EMPTY", "diagnoses", "diagnoses must contain at least one ICD-10-CM code");
+        return;
+    }
+
+    for (i, diagnosis) in claim.diagnoses.iter().enumerate() {
+        if !is_icd10_code(&diagnosis.code) {
+            let path = format!("diagnoses[{}].code", i);
+            report.error("ICD10_FORMAT", path.clone(), format!("{} {} is not an ICD-10-CM code", path, diagnosis.code));
+        }
+    }
+
+    if !claim.diagnoses.iter().any(|diagnosis| diagnosis.primary) {
+        report.error("PRIMARY_DIAGNOSIS_MISSING", "diagnoses", "diagnoses must include a primary diagnosis");
+    }
+
+    for (i, line) in claim.service_lines.iter().enumerate() {
+        if line.diagnosis_pointers.is_empty() {
+            let path = format!("service_lines[{}].diagnosis_pointers", i);
+            report.error("DIAGNOSIS_POINTER_MISSING", path.clone(), format!("{} must point to at least one diagnosis", path));
+        }
+        for (j, &pointer) in line.diagnosis_pointers.iter().enumerate() {
+            if pointer < 1 || pointer as usize > claim.diagnoses.len() {
+                let path = format!("service_lines[{}].diagnosis_pointers[{}]", i, j);
+                report.error(
+                    "DIAGNOSIS_POINTER_RANGE",
+                    path.clone(),
+                    format!("{} must be between 1 and {}", path, claim.diagnoses.len()),
+                );
+            }
+        }
+    }
+}
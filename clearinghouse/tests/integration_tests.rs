@@ -1,6 +1,10 @@
 use clearinghouse::*;
 use intake::*;
-use insurance;
+use std::sync::Arc;
+
+fn usd(amount: f64) -> Money {
+    Money::from_dollars(amount)
+}
 
 // Helper function to create a valid test claim
 fn create_valid_test_claim() -> PayerClaim {
@@ -10,12 +14,15 @@ fn create_valid_test_claim() -> PayerClaim {
         insurance: Insurance {
             payer_id: PayerId::Medicare,
             patient_member_id: "MED123456".to_string(),
+            group_number: None,
+            relationship: Relationship::SelfInsured,
+            subscriber: None,
         },
         patient: Patient {
             first_name: "John".to_string(),
             last_name: "Doe".to_string(),
             gender: Gender::Male,
-            dob: "1980-01-15".to_string(),
+            dob: date("1980-01-15"),
             email: Some("john.doe@test.com".to_string()),
             address: Some(Address {
                 street: Some("123 Main St".to_string()),
@@ -24,10 +31,11 @@ fn create_valid_test_claim() -> PayerClaim {
                 zip: Some("90210".to_string()),
                 country: Some("US".to_string()),
             }),
+            medicare_entitlement: Some(MedicareEntitlement::Disability),
         },
         organization: Organization {
             name: "Test Medical Practice".to_string(),
-            billing_npi: Some("1234567890".to_string()),
+            billing_npi: Some("1234567893".to_string()),
             ein: Some("12-3456789".to_string()),
             contact: Some(Contact {
                 first_name: Some("Jane".to_string()),
@@ -45,8 +53,9 @@ fn create_valid_test_claim() -> PayerClaim {
         rendering_provider: RenderingProvider {
             first_name: "Dr. Alice".to_string(),
             last_name: "Johnson".to_string(),
-            npi: "9876543210".to_string(),
+            npi: "9876543213".to_string(),
         },
+        diagnoses: vec![Diagnosis { code: "E11.9".to_string(), primary: true }],
         service_lines: vec![
             ServiceLine {
                 service_line_id: "SL001".to_string(),
@@ -55,36 +64,58 @@ fn create_valid_test_claim() -> PayerClaim {
                 units: 1,
                 details: "Office visit".to_string(),
                 unit_charge_currency: "USD".to_string(),
-                unit_charge_amount: 150.00,
+                unit_charge_amount: usd(150.00),
                 do_not_bill: Some(false),
+                diagnosis_pointers: vec![1],
+                service_date_from: None,
+                service_date_to: None,
             }
         ],
+        frequency_code: ClaimFrequency::Original,
+        payer_claim_control_number: None,
+        other_insurance: Vec::new(),
+        prior_payments: Vec::new(),
         initial_claim_ts: 1640995200000,
     }
 }
 
+// The PR adjustments explaining a line's deductible, coinsurance and copay
+fn patient_adjustments(deductible: f64, coinsurance: f64, copay: f64) -> Vec<insurance::Adjustment> {
+    vec![
+        insurance::Adjustment::new(insurance::GroupCode::PatientResponsibility, "1", usd(deductible)),
+        insurance::Adjustment::new(insurance::GroupCode::PatientResponsibility, "2", usd(coinsurance)),
+        insurance::Adjustment::new(insurance::GroupCode::PatientResponsibility, "3", usd(copay)),
+    ]
+}
+
 // Helper function to create a test remittance
 fn create_test_remittance() -> insurance::Remittance {
     insurance::Remittance {
         remittance_id: "REM123".to_string(),
         claim_id: "CLAIM001".to_string(),
         payer_id: "Medicare".to_string(),
-        payee_npi: "1234567890".to_string(),
+        payee_npi: "1234567893".to_string(),
         patient_id: "Medicare-MED123456".to_string(),
         service_lines: vec![
             insurance::ServiceLine {
                 service_line_id: "SL001".to_string(),
                 procedure_code: "99213".to_string(),
-                billed_amount: 150.0,
-                payer_paid_amount: 120.0,
-                coinsurance_amount: 15.0,
-                copay_amount: 7.5,
-                deductible_amount: 7.5,
-                not_allowed_amount: 0.0,
+                billed_amount: usd(150.0),
+                payer_paid_amount: usd(120.0),
+                coinsurance_amount: usd(15.0),
+                copay_amount: usd(7.5),
+                deductible_amount: usd(7.5),
+                not_allowed_amount: usd(0.0),
+                adjustments: patient_adjustments(7.5, 15.0, 7.5),
                 remark_codes: None,
             }
         ],
         initial_claim_ts: 1640995200000,
+        reversal: false,
+        payer_sequence: 1,
+        crossover_payer_id: None,
+        currency: "USD".to_string(),
+        currency_conversion: None,
     }
 }
 
@@ -109,7 +140,7 @@ fn test_validate_empty_claim_id() {
     claim.claim_id = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("claim_id cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("claim_id cannot be empty"));
 }
 
 #[test]
@@ -118,7 +149,7 @@ fn test_validate_whitespace_only_claim_id() {
     claim.claim_id = "   \t  \n ".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("claim_id cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("claim_id cannot be empty"));
 }
 
 #[test]
@@ -127,7 +158,7 @@ fn test_validate_empty_patient_first_name() {
     claim.patient.first_name = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("patient.first_name cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("patient.first_name cannot be empty"));
 }
 
 #[test]
@@ -136,16 +167,16 @@ fn test_validate_empty_patient_last_name() {
     claim.patient.last_name = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("patient.last_name cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("patient.last_name cannot be empty"));
 }
 
 #[test]
-fn test_validate_empty_patient_dob() {
+fn test_validate_future_patient_dob() {
     let mut claim = create_valid_test_claim();
-    claim.patient.dob = "".to_string();
+    claim.patient.dob = date("2022-01-02");
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("patient.dob cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("patient.dob 2022-01-02 is after the claim was received on 2022-01-01"));
 }
 
 #[test]
@@ -154,7 +185,7 @@ fn test_validate_empty_organization_name() {
     claim.organization.name = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("organization.name cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("organization.name cannot be empty"));
 }
 
 #[test]
@@ -163,7 +194,7 @@ fn test_validate_empty_rendering_provider_first_name() {
     claim.rendering_provider.first_name = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("rendering_provider.first_name cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("rendering_provider.first_name cannot be empty"));
 }
 
 #[test]
@@ -172,7 +203,7 @@ fn test_validate_empty_rendering_provider_last_name() {
     claim.rendering_provider.last_name = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("rendering_provider.last_name cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("rendering_provider.last_name cannot be empty"));
 }
 
 #[test]
@@ -181,7 +212,7 @@ fn test_validate_empty_rendering_provider_npi() {
     claim.rendering_provider.npi = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("rendering_provider.npi cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("rendering_provider.npi cannot be empty"));
 }
 
 #[test]
@@ -190,7 +221,7 @@ fn test_validate_empty_insurance_patient_member_id() {
     claim.insurance.patient_member_id = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("insurance.patient_member_id cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("insurance.patient_member_id cannot be empty"));
 }
 
 #[test]
@@ -199,7 +230,7 @@ fn test_validate_empty_service_line_id() {
     claim.service_lines[0].service_line_id = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].service_line_id cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].service_line_id cannot be empty"));
 }
 
 #[test]
@@ -208,7 +239,7 @@ fn test_validate_empty_procedure_code() {
     claim.service_lines[0].procedure_code = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].procedure_code cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].procedure_code cannot be empty"));
 }
 
 #[test]
@@ -217,7 +248,7 @@ fn test_validate_empty_service_details() {
     claim.service_lines[0].details = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].details cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].details cannot be empty"));
 }
 
 #[test]
@@ -226,14 +257,14 @@ fn test_validate_empty_currency() {
     claim.service_lines[0].unit_charge_currency = "".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].unit_charge_currency cannot be empty"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].unit_charge_currency cannot be empty"));
 }
 
 // Tests for validate_formats
 #[test]
 fn test_validate_rendering_provider_npi_valid() {
     let mut claim = create_valid_test_claim();
-    claim.rendering_provider.npi = "1111111111".to_string();
+    claim.rendering_provider.npi = "1111111112".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_ok());
 }
@@ -244,7 +275,7 @@ fn test_validate_rendering_provider_npi_too_short() {
     claim.rendering_provider.npi = "123456789".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("rendering_provider.npi must be exactly 10 digits"));
+    assert!(result.unwrap_err().to_string().contains("rendering_provider.npi must be exactly 10 digits"));
 }
 
 #[test]
@@ -253,7 +284,7 @@ fn test_validate_rendering_provider_npi_too_long() {
     claim.rendering_provider.npi = "12345678901".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("rendering_provider.npi must be exactly 10 digits"));
+    assert!(result.unwrap_err().to_string().contains("rendering_provider.npi must be exactly 10 digits"));
 }
 
 #[test]
@@ -262,13 +293,13 @@ fn test_validate_rendering_provider_npi_non_numeric() {
     claim.rendering_provider.npi = "123456789A".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("rendering_provider.npi must be exactly 10 digits"));
+    assert!(result.unwrap_err().to_string().contains("rendering_provider.npi must be exactly 10 digits"));
 }
 
 #[test]
 fn test_validate_organization_billing_npi_valid() {
     let mut claim = create_valid_test_claim();
-    claim.organization.billing_npi = Some("2222222222".to_string());
+    claim.organization.billing_npi = Some("2222222228".to_string());
     let result = validate_claim(&claim);
     assert!(result.is_ok());
 }
@@ -279,7 +310,7 @@ fn test_validate_organization_billing_npi_invalid() {
     claim.organization.billing_npi = Some("987654321".to_string());
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("organization.billing_npi must be exactly 10 digits"));
+    assert!(result.unwrap_err().to_string().contains("organization.billing_npi must be exactly 10 digits"));
 }
 
 #[test]
@@ -304,7 +335,7 @@ fn test_validate_ein_invalid_format() {
     claim.organization.ein = Some("123456789".to_string());
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("organization.ein must match format XX-XXXXXXX"));
+    assert!(result.unwrap_err().to_string().contains("organization.ein must match format XX-XXXXXXX"));
 }
 
 #[test]
@@ -313,7 +344,7 @@ fn test_validate_ein_wrong_dash_position() {
     claim.organization.ein = Some("123-456789".to_string());
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("organization.ein must match format XX-XXXXXXX"));
+    assert!(result.unwrap_err().to_string().contains("organization.ein must match format XX-XXXXXXX"));
 }
 
 #[test]
@@ -322,7 +353,7 @@ fn test_validate_ein_non_numeric() {
     claim.organization.ein = Some("1A-3456789".to_string());
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("organization.ein must match format XX-XXXXXXX"));
+    assert!(result.unwrap_err().to_string().contains("organization.ein must match format XX-XXXXXXX"));
 }
 
 #[test]
@@ -361,7 +392,7 @@ fn test_validate_zip_code_invalid_length() {
     }
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("patient.address.zip must be XXXXX or XXXXX-XXXX format"));
+    assert!(result.unwrap_err().to_string().contains("patient.address.zip must be XXXXX or XXXXX-XXXX format"));
 }
 
 #[test]
@@ -372,7 +403,7 @@ fn test_validate_zip_code_non_numeric() {
     }
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("patient.address.zip must be XXXXX or XXXXX-XXXX format"));
+    assert!(result.unwrap_err().to_string().contains("patient.address.zip must be XXXXX or XXXXX-XXXX format"));
 }
 
 #[test]
@@ -399,7 +430,7 @@ fn test_validate_currency_too_short() {
     claim.service_lines[0].unit_charge_currency = "US".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].unit_charge_currency must be 3 uppercase letters"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].unit_charge_currency must be 3 uppercase letters"));
 }
 
 #[test]
@@ -408,7 +439,7 @@ fn test_validate_currency_too_long() {
     claim.service_lines[0].unit_charge_currency = "USDD".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].unit_charge_currency must be 3 uppercase letters"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].unit_charge_currency must be 3 uppercase letters"));
 }
 
 #[test]
@@ -417,7 +448,7 @@ fn test_validate_currency_lowercase() {
     claim.service_lines[0].unit_charge_currency = "usd".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].unit_charge_currency must be 3 uppercase letters"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].unit_charge_currency must be 3 uppercase letters"));
 }
 
 #[test]
@@ -426,7 +457,33 @@ fn test_validate_currency_mixed_case() {
     claim.service_lines[0].unit_charge_currency = "Usd".to_string();
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].unit_charge_currency must be 3 uppercase letters"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].unit_charge_currency must be 3 uppercase letters"));
+}
+
+#[test]
+fn test_validate_currency_not_iso_4217() {
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].unit_charge_currency = "XYZ".to_string();
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.issues[0].code, "CURRENCY_UNKNOWN");
+    assert_eq!(report.issues[0].message, "service_lines[0].unit_charge_currency XYZ is not an ISO 4217 currency code");
+
+    claim.service_lines[0].unit_charge_currency = "EUR".to_string();
+    assert!(validate_claim(&claim).is_ok());
+    assert_eq!(CurrencyTable::standard().name("EUR"), Some("Euro"));
+}
+
+#[test]
+fn test_validate_currency_without_minor_units() {
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].unit_charge_currency = "JPY".to_string();
+    claim.service_lines[0].unit_charge_amount = usd(15000.0);
+    assert!(validate_claim(&claim).is_ok());
+
+    claim.service_lines[0].unit_charge_amount = usd(15000.5);
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.issues[0].code, "CURRENCY_MINOR_UNITS");
+    assert_eq!(report.issues[0].path, "service_lines[0].unit_charge_amount");
 }
 
 // Tests for validate_business_rules
@@ -452,7 +509,7 @@ fn test_validate_place_of_service_code_too_low() {
     claim.place_of_service_code = 0;
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("place_of_service_code must be between 1-99"));
+    assert!(result.unwrap_err().to_string().contains("place_of_service_code must be between 1-99"));
 }
 
 #[test]
@@ -461,7 +518,7 @@ fn test_validate_place_of_service_code_too_high() {
     claim.place_of_service_code = 100;
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("place_of_service_code must be between 1-99"));
+    assert!(result.unwrap_err().to_string().contains("place_of_service_code must be between 1-99"));
 }
 
 #[test]
@@ -470,7 +527,7 @@ fn test_validate_empty_service_lines() {
     claim.service_lines = vec![];
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines must contain at least one item"));
+    assert!(result.unwrap_err().to_string().contains("service_lines must contain at least one item"));
 }
 
 #[test]
@@ -484,8 +541,11 @@ fn test_validate_duplicate_service_line_ids() {
             units: 1,
             details: "Office visit".to_string(),
             unit_charge_currency: "USD".to_string(),
-            unit_charge_amount: 150.00,
+            unit_charge_amount: usd(150.00),
             do_not_bill: None,
+            diagnosis_pointers: vec![1],
+            service_date_from: None,
+            service_date_to: None,
         },
         ServiceLine {
             service_line_id: "SL001".to_string(), // Duplicate ID
@@ -494,13 +554,16 @@ fn test_validate_duplicate_service_line_ids() {
             units: 1,
             details: "Follow-up visit".to_string(),
             unit_charge_currency: "USD".to_string(),
-            unit_charge_amount: 200.00,
+            unit_charge_amount: usd(200.00),
             do_not_bill: None,
+            diagnosis_pointers: vec![1],
+            service_date_from: None,
+            service_date_to: None,
         },
     ];
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("Duplicate service_line_id: SL001"));
+    assert!(result.unwrap_err().to_string().contains("Duplicate service_line_id: SL001"));
 }
 
 #[test]
@@ -509,7 +572,7 @@ fn test_validate_units_zero() {
     claim.service_lines[0].units = 0;
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].units must be at least 1"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].units must be at least 1"));
 }
 
 #[test]
@@ -518,25 +581,25 @@ fn test_validate_units_negative() {
     claim.service_lines[0].units = -1;
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].units must be at least 1"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].units must be at least 1"));
 }
 
 #[test]
 fn test_validate_unit_charge_amount_zero() {
     let mut claim = create_valid_test_claim();
-    claim.service_lines[0].unit_charge_amount = 0.0;
+    claim.service_lines[0].unit_charge_amount = usd(0.0);
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].unit_charge_amount must be positive"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].unit_charge_amount must be positive"));
 }
 
 #[test]
 fn test_validate_unit_charge_amount_negative() {
     let mut claim = create_valid_test_claim();
-    claim.service_lines[0].unit_charge_amount = -100.0;
+    claim.service_lines[0].unit_charge_amount = usd(-100.0);
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("service_lines[0].unit_charge_amount must be positive"));
+    assert!(result.unwrap_err().to_string().contains("service_lines[0].unit_charge_amount must be positive"));
 }
 
 #[test]
@@ -550,8 +613,11 @@ fn test_validate_currency_consistency() {
             units: 1,
             details: "Office visit".to_string(),
             unit_charge_currency: "USD".to_string(),
-            unit_charge_amount: 150.00,
+            unit_charge_amount: usd(150.00),
             do_not_bill: None,
+            diagnosis_pointers: vec![1],
+            service_date_from: None,
+            service_date_to: None,
         },
         ServiceLine {
             service_line_id: "SL002".to_string(),
@@ -560,30 +626,33 @@ fn test_validate_currency_consistency() {
             units: 1,
             details: "Follow-up visit".to_string(),
             unit_charge_currency: "EUR".to_string(), // Different currency
-            unit_charge_amount: 200.00,
+            unit_charge_amount: usd(200.00),
             do_not_bill: None,
+            diagnosis_pointers: vec![1],
+            service_date_from: None,
+            service_date_to: None,
         },
     ];
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("All service lines must use the same currency"));
+    assert!(result.unwrap_err().to_string().contains("All service lines must use the same currency"));
 }
 
 #[test]
 fn test_validate_provider_npi_equals_organization_npi() {
     let mut claim = create_valid_test_claim();
-    claim.rendering_provider.npi = "1234567890".to_string();
-    claim.organization.billing_npi = Some("1234567890".to_string()); // Same NPI
+    claim.rendering_provider.npi = "1234567893".to_string();
+    claim.organization.billing_npi = Some("1234567893".to_string()); // Same NPI
     let result = validate_claim(&claim);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("rendering_provider.npi cannot equal organization.billing_npi"));
+    assert!(result.unwrap_err().to_string().contains("rendering_provider.npi cannot equal organization.billing_npi"));
 }
 
 #[test]
 fn test_validate_provider_npi_different_from_organization_npi() {
     let mut claim = create_valid_test_claim();
-    claim.rendering_provider.npi = "1234567890".to_string();
-    claim.organization.billing_npi = Some("9876543210".to_string()); // Different NPI
+    claim.rendering_provider.npi = "1234567893".to_string();
+    claim.organization.billing_npi = Some("9876543213".to_string()); // Different NPI
     let result = validate_claim(&claim);
     assert!(result.is_ok());
 }
@@ -599,8 +668,11 @@ fn test_validate_multiple_service_lines_valid() {
             units: 1,
             details: "Office visit".to_string(),
             unit_charge_currency: "USD".to_string(),
-            unit_charge_amount: 150.00,
+            unit_charge_amount: usd(150.00),
             do_not_bill: Some(false),
+            diagnosis_pointers: vec![1],
+            service_date_from: None,
+            service_date_to: None,
         },
         ServiceLine {
             service_line_id: "SL002".to_string(),
@@ -609,8 +681,11 @@ fn test_validate_multiple_service_lines_valid() {
             units: 2,
             details: "Follow-up visit".to_string(),
             unit_charge_currency: "USD".to_string(),
-            unit_charge_amount: 200.00,
+            unit_charge_amount: usd(200.00),
             do_not_bill: None,
+            diagnosis_pointers: vec![1],
+            service_date_from: None,
+            service_date_to: None,
         },
         ServiceLine {
             service_line_id: "SL003".to_string(),
@@ -619,28 +694,150 @@ fn test_validate_multiple_service_lines_valid() {
             units: 1,
             details: "Complex visit".to_string(),
             unit_charge_currency: "USD".to_string(),
-            unit_charge_amount: 300.00,
+            unit_charge_amount: usd(300.00),
             do_not_bill: Some(true),
+            diagnosis_pointers: vec![1],
+            service_date_from: None,
+            service_date_to: None,
         },
     ];
     let result = validate_claim(&claim);
     assert!(result.is_ok());
 }
 
+#[test]
+fn test_validate_collects_every_failure() {
+    let mut claim = create_valid_test_claim();
+    claim.patient.first_name = "".to_string();
+    claim.organization.ein = Some("123456789".to_string());
+    claim.place_of_service_code = 0;
+    claim.service_lines.push(claim.service_lines[0].clone());
+    claim.service_lines[1].units = 0;
+    claim.service_lines[1].unit_charge_currency = "EUR".to_string();
+
+    let report = validate_claim(&claim).unwrap_err();
+    let codes: Vec<(&str, &str)> = report.errors().map(|i| (i.code.as_str(), i.path.as_str())).collect();
+    assert_eq!(codes, vec![
+        ("REQUIRED_FIELD", "patient.first_name"),
+        ("EIN_FORMAT", "organization.ein"),
+        ("PLACE_OF_SERVICE_RANGE", "place_of_service_code"),
+        ("DUPLICATE_SERVICE_LINE_ID", "service_lines[1].service_line_id"),
+        ("UNITS_MINIMUM", "service_lines[1].units"),
+        ("CURRENCY_MISMATCH", "service_lines[1].unit_charge_currency"),
+    ]);
+    assert_eq!(report.claim_id, "CLAIM001");
+    assert!(!report.is_accepted());
+    assert!(report.to_string().contains("patient.first_name cannot be empty; organization.ein"));
+}
+
+#[test]
+fn test_validate_non_ascii_ein_and_zip_from_json() {
+    // byte lengths that pass the length check, with a multi-byte character where the value is split
+    let json_line = r#"{"claim_id":"CLAIM001","place_of_service_code":11,"insurance":{"payer_id":"medicare","patient_member_id":"MED123"},"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1950-01-15","address":{"zip":"1234é-123"}},"organization":{"name":"Test Clinic","ein":"aé-123456"},"rendering_provider":{"first_name":"Jane","last_name":"Smith","npi":"1234567893"},"diagnoses":[{"code":"I10","primary":true}],"service_lines":[{"service_line_id":"SL001","procedure_code":"99213","units":1,"details":"Office visit","unit_charge_currency":"USD","unit_charge_amount":100.00,"diagnosis_pointers":[1]}]}"#;
+    let claim = parse_line(json_line).unwrap();
+
+    let report = validate_claim(&claim).unwrap_err();
+    let codes: Vec<(&str, &str)> = report.errors().map(|i| (i.code.as_str(), i.path.as_str())).collect();
+    assert!(codes.contains(&("EIN_FORMAT", "organization.ein")), "{:?}", codes);
+    assert!(codes.contains(&("ZIP_FORMAT", "patient.address.zip")), "{:?}", codes);
+}
+
+#[test]
+fn test_validate_empty_npi_is_not_also_a_format_error() {
+    let mut claim = create_valid_test_claim();
+    claim.rendering_provider.npi = "".to_string();
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.errors().count(), 1);
+    assert_eq!(report.issues[0].code, "REQUIRED_FIELD");
+}
+
+#[test]
+fn test_validate_warnings_do_not_block_submission() {
+    let mut claim = create_valid_test_claim();
+    claim.organization.billing_npi = None;
+    claim.service_lines[0].do_not_bill = Some(true);
+
+    let report = validate_claim(&claim).unwrap();
+    assert!(report.is_accepted());
+    let warnings: Vec<&str> = report.warnings().map(|i| i.code.as_str()).collect();
+    assert_eq!(warnings, vec!["NOTHING_BILLABLE", "BILLING_NPI_MISSING"]);
+    assert_eq!(report.to_string(), "");
+}
+
+#[test]
+fn test_validate_subscriber_for_dependents() {
+    let mut claim = create_valid_test_claim();
+    claim.insurance.relationship = Relationship::Child;
+    let report = validate_claim(&claim).unwrap_err();
+    let codes: Vec<(&str, &str)> = report.errors().map(|i| (i.code.as_str(), i.path.as_str())).collect();
+    assert_eq!(codes, vec![("SUBSCRIBER_MISSING", "insurance.subscriber")]);
+    assert!(report.to_string().contains("required for relationship code 19"));
+
+    let subscriber = Subscriber { first_name: "Robert".to_string(), last_name: "Doe".to_string(), dob: None, gender: None };
+    claim.insurance.subscriber = Some(subscriber.clone());
+    assert!(validate_claim(&claim).is_ok());
+
+    claim.insurance.subscriber = Some(Subscriber { last_name: " ".to_string(), ..subscriber.clone() });
+    assert!(validate_claim(&claim).unwrap_err().to_string().contains("insurance.subscriber.last_name cannot be empty"));
+
+    claim.insurance.relationship = Relationship::SelfInsured;
+    claim.insurance.subscriber = Some(subscriber);
+    let report = validate_claim(&claim).unwrap();
+    assert_eq!(report.warnings().map(|i| i.code.as_str()).collect::<Vec<_>>(), vec!["SUBSCRIBER_IGNORED"]);
+}
+
+#[test]
+fn test_validate_group_number_format() {
+    let mut claim = create_valid_test_claim();
+    claim.insurance.group_number = Some("GRP-001 A".to_string());
+    assert!(validate_claim(&claim).is_ok());
+
+    for invalid in ["", "GRP#001", &"9".repeat(51)] {
+        claim.insurance.group_number = Some(invalid.to_string());
+        let report = validate_claim(&claim).unwrap_err();
+        assert_eq!(report.issues[0].code, "GROUP_NUMBER_FORMAT", "{:?}", invalid);
+    }
+}
+
+#[test]
+fn test_validation_report_serializes_as_rejection_file() {
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].units = 0;
+    let report = validate_claim(&claim).unwrap_err();
+
+    let json = report.to_json();
+    assert!(json.contains("\"code\": \"UNITS_MINIMUM\""));
+    assert!(json.contains("\"severity\": \"error\""));
+    assert!(json.contains("\"path\": \"service_lines[0].units\""));
+    let parsed: ValidationReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, report);
+}
+
+// Payer latency is simulated with a zero-delay clock so these tests don't wait 10-30 seconds each
+fn instant_payer_settings() -> PayerSettings {
+    PayerSettings { clock: Arc::new(ManualClock::new(1640995200000)), ..PayerSettings::new() }
+}
+
+fn instant_payers() -> PayerRegistry {
+    PayerRegistry::with_builtins(&instant_payer_settings())
+}
+
 // Tests for submit_claim_to_payer
 #[test]
 fn test_submit_claim_to_medicare() {
     let mut claim = create_valid_test_claim();
     claim.insurance.payer_id = PayerId::Medicare;
     
-    let result = submit_claim_to_payer(&claim);
+    let result = submit_claim_to_payer(&claim, &instant_payers());
     assert!(result.is_ok());
     
-    let remittance = result.unwrap();
+    let remittances = result.unwrap();
+    assert_eq!(remittances.len(), 1);
+    let remittance = &remittances[0];
     assert_eq!(remittance.claim_id, "CLAIM001");
     assert_eq!(remittance.payer_id, "Medicare");
     assert_eq!(remittance.patient_id, "Medicare-MED123456");
-    assert_eq!(remittance.payee_npi, "1234567890");
+    assert_eq!(remittance.payee_npi, "1234567893");
     assert_eq!(remittance.service_lines.len(), 1);
 }
 
@@ -650,14 +847,16 @@ fn test_submit_claim_to_united_health_group() {
     claim.insurance.payer_id = PayerId::UnitedHealthGroup;
     claim.insurance.patient_member_id = "UHG123456".to_string();
     
-    let result = submit_claim_to_payer(&claim);
+    let result = submit_claim_to_payer(&claim, &instant_payers());
     assert!(result.is_ok());
     
-    let remittance = result.unwrap();
+    let remittances = result.unwrap();
+    assert_eq!(remittances.len(), 1);
+    let remittance = &remittances[0];
     assert_eq!(remittance.claim_id, "CLAIM001");
     assert_eq!(remittance.payer_id, "UnitedHealthGroup");
     assert_eq!(remittance.patient_id, "UnitedHealthGroup-UHG123456");
-    assert_eq!(remittance.payee_npi, "1234567890");
+    assert_eq!(remittance.payee_npi, "1234567893");
     assert_eq!(remittance.service_lines.len(), 1);
 }
 
@@ -667,14 +866,16 @@ fn test_submit_claim_to_anthem() {
     claim.insurance.payer_id = PayerId::Anthem;
     claim.insurance.patient_member_id = "ANT123456".to_string();
     
-    let result = submit_claim_to_payer(&claim);
+    let result = submit_claim_to_payer(&claim, &instant_payers());
     assert!(result.is_ok());
     
-    let remittance = result.unwrap();
+    let remittances = result.unwrap();
+    assert_eq!(remittances.len(), 1);
+    let remittance = &remittances[0];
     assert_eq!(remittance.claim_id, "CLAIM001");
     assert_eq!(remittance.payer_id, "Anthem");
     assert_eq!(remittance.patient_id, "Anthem-ANT123456");
-    assert_eq!(remittance.payee_npi, "1234567890");
+    assert_eq!(remittance.payee_npi, "1234567893");
     assert_eq!(remittance.service_lines.len(), 1);
 }
 
@@ -690,7 +891,7 @@ fn test_submit_remittance_to_submitter_success() {
     assert_eq!(ar_data.claim_id, "CLAIM001");
     assert_eq!(ar_data.remittance_id, "REM123");
     assert_eq!(ar_data.payer_id, "Medicare");
-    assert_eq!(ar_data.payee_npi, "1234567890");
+    assert_eq!(ar_data.payee_npi, "1234567893");
     assert_eq!(ar_data.patient_id, "Medicare-MED123456");
     assert_eq!(ar_data.initial_claim_ts, 1640995200000);
     assert_eq!(ar_data.service_lines.len(), 1);
@@ -703,23 +904,25 @@ fn test_submit_remittance_calculates_totals() {
         insurance::ServiceLine {
             service_line_id: "SL001".to_string(),
             procedure_code: "99213".to_string(),
-            billed_amount: 150.0,
-            payer_paid_amount: 120.0,
-            coinsurance_amount: 15.0,
-            copay_amount: 7.5,
-            deductible_amount: 7.5,
-            not_allowed_amount: 0.0,
+            billed_amount: usd(150.0),
+            payer_paid_amount: usd(120.0),
+            coinsurance_amount: usd(15.0),
+            copay_amount: usd(7.5),
+            deductible_amount: usd(7.5),
+            not_allowed_amount: usd(0.0),
+            adjustments: patient_adjustments(7.5, 15.0, 7.5),
             remark_codes: None,
         },
         insurance::ServiceLine {
             service_line_id: "SL002".to_string(),
             procedure_code: "99214".to_string(),
-            billed_amount: 400.0,
-            payer_paid_amount: 320.0,
-            coinsurance_amount: 40.0,
-            copay_amount: 20.0,
-            deductible_amount: 20.0,
-            not_allowed_amount: 0.0,
+            billed_amount: usd(400.0),
+            payer_paid_amount: usd(320.0),
+            coinsurance_amount: usd(40.0),
+            copay_amount: usd(20.0),
+            deductible_amount: usd(20.0),
+            not_allowed_amount: usd(0.0),
+            adjustments: patient_adjustments(20.0, 40.0, 20.0),
             remark_codes: None,
         },
     ];
@@ -730,10 +933,843 @@ fn test_submit_remittance_calculates_totals() {
     let ar_data = result.unwrap();
     
     // Verify totals are calculated correctly
-    assert_eq!(ar_data.total_billed_amount, 550.0);        // 150 + 400
-    assert_eq!(ar_data.total_payer_paid_amount, 440.0);    // 120 + 320
-    assert_eq!(ar_data.total_coinsurance_amount, 55.0);    // 15 + 40
-    assert_eq!(ar_data.total_copay_amount, 27.5);          // 7.5 + 20
-    assert_eq!(ar_data.total_deductible_amount, 27.5);     // 7.5 + 20
-    assert_eq!(ar_data.total_not_allowed_amount, 0.0);     // 0 + 0
-}
\ No newline at end of file
+    assert_eq!(ar_data.total_billed_amount, usd(550.0));        // 150 + 400
+    assert_eq!(ar_data.total_payer_paid_amount, usd(440.0));    // 120 + 320
+    assert_eq!(ar_data.total_coinsurance_amount, usd(55.0));    // 15 + 40
+    assert_eq!(ar_data.total_copay_amount, usd(27.5));          // 7.5 + 20
+    assert_eq!(ar_data.total_deductible_amount, usd(27.5));     // 7.5 + 20
+    assert_eq!(ar_data.total_not_allowed_amount, usd(0.0));     // 0 + 0
+}
+
+#[test]
+fn test_submit_unbalanced_remittance_is_rejected() {
+    let mut remittance = create_test_remittance();
+    remittance.service_lines[0].payer_paid_amount = usd(119.99);
+    let err = submit_remittance_to_submitter(&remittance).unwrap_err();
+    assert!(err.to_string().contains("150.00 billed but 149.99 paid, owed by the patient or not allowed"), "{}", err);
+
+    // the amounts add up but the adjustments don't explain what wasn't paid
+    let mut remittance = create_test_remittance();
+    remittance.service_lines[0].adjustments.pop();
+    assert!(submit_remittance_to_submitter(&remittance).is_err());
+}
+#[test]
+fn test_submit_remittance_totals_adjustments_by_code() {
+    let mut remittance = create_test_remittance();
+    let intake_line = create_valid_test_claim().service_lines[0].clone();
+    remittance.service_lines = vec![
+        insurance::ServiceLine::new(&intake_line, usd(150.0), usd(100.0), usd(20.0), usd(0.0), usd(25.0), usd(5.0)).unwrap(),
+        insurance::ServiceLine::new(&intake_line, usd(200.0), usd(150.0), usd(30.0), usd(10.0), usd(0.0), usd(10.0)).unwrap(),
+    ];
+
+    let ar_data = submit_remittance_to_submitter(&remittance).unwrap();
+
+    assert_eq!(ar_data.adjustment_totals, vec![
+        insurance::Adjustment::new(insurance::GroupCode::PatientResponsibility, "1", usd(25.0)),
+        insurance::Adjustment::new(insurance::GroupCode::PatientResponsibility, "2", usd(50.0)),
+        insurance::Adjustment::new(insurance::GroupCode::ContractualObligation, "45", usd(15.0)),
+        insurance::Adjustment::new(insurance::GroupCode::PatientResponsibility, "3", usd(10.0)),
+    ]);
+    assert_eq!(ar_data.service_lines[0].adjustments.len(), 3);
+}
+
+#[test]
+fn test_clearinghouse_error_rejection_is_not_retryable() {
+    let mut claim = create_valid_test_claim();
+    claim.claim_id = "".to_string();
+    let err = ClearinghouseError::from(validate_claim(&claim).unwrap_err());
+
+    assert!(!err.is_retryable());
+    assert!(err.to_string().starts_with("Validation failed"));
+}
+
+#[test]
+fn test_submit_claim_payer_outage_is_retryable() {
+    let claim = create_valid_test_claim();
+    let payers = PayerRegistry::with_builtins(&PayerSettings { outage_rate: 1.0, ..instant_payer_settings() });
+
+    let err = submit_claim_to_payer(&claim, &payers).unwrap_err();
+    assert!(err.is_retryable());
+    assert_eq!(err.claim_id(), Some("CLAIM001"));
+    match err {
+        ClearinghouseError::Payer { source, .. } => {
+            assert!(matches!(source, insurance::InsuranceError::PayerUnavailable { .. }));
+        }
+        other => panic!("Expected payer error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_submit_remittance_without_service_lines_is_rejected() {
+    let mut remittance = create_test_remittance();
+    remittance.service_lines.clear();
+
+    let err = submit_remittance_to_submitter(&remittance).unwrap_err();
+    assert!(matches!(err, ClearinghouseError::Remittance { ref field, .. } if field == "service_lines"));
+    assert!(!err.is_retryable());
+}
+
+#[test]
+fn test_is_valid_npi_luhn_check_digit() {
+    assert!(is_valid_npi("1234567893"));
+    assert!(is_valid_npi("9876543213"));
+    assert!(!is_valid_npi("1234567890"));
+    assert!(!is_valid_npi("123456789"));
+    assert!(!is_valid_npi("123456789X"));
+}
+
+#[test]
+fn test_validate_npi_check_digit() {
+    let mut claim = create_valid_test_claim();
+    claim.rendering_provider.npi = "9876543210".to_string();
+    claim.organization.billing_npi = Some("1234567890".to_string());
+
+    let report = validate_claim(&claim).unwrap_err();
+    let issues: Vec<(&str, &str)> = report.errors().map(|i| (i.code.as_str(), i.path.as_str())).collect();
+    assert_eq!(issues, vec![
+        ("NPI_CHECK_DIGIT", "rendering_provider.npi"),
+        ("NPI_CHECK_DIGIT", "organization.billing_npi"),
+    ]);
+}
+
+#[test]
+fn test_npi_registry_from_csv() {
+    let registry = NpiRegistry::from_csv(
+        "\"NPI\",\"Entity Type Code\",\"Provider Name\"\n\"9876543213\",\"1\",\"BROWN\"\n\"1234567893\",\"2\",\"CITY MEDICAL\"\n\"1111111112\",\"\",\"\"\n",
+    )
+    .unwrap();
+
+    assert_eq!(registry.len(), 2);
+    assert_eq!(registry.entity_type("9876543213"), Some(NpiEntityType::Individual));
+    assert_eq!(registry.entity_type("1234567893"), Some(NpiEntityType::Organization));
+    assert_eq!(registry.entity_type("1111111112"), None);
+
+    assert!(NpiRegistry::from_csv("npi,name\n").unwrap_err().to_string().contains("Entity Type Code"));
+    assert!(NpiRegistry::from_csv("npi,entity type code\n1234567893,3\n").unwrap_err().to_string().contains("Line 2"));
+}
+
+#[test]
+fn test_claim_validator_checks_npi_entity_types() {
+    let mut registry = NpiRegistry::new();
+    registry.insert("9876543213", NpiEntityType::Individual);
+    registry.insert("1234567893", NpiEntityType::Organization);
+    let validator = ClaimValidator::new().with_npi_registry(registry);
+
+    let claim = create_valid_test_claim();
+    assert!(validator.validate(&claim).unwrap().issues.is_empty());
+
+    // NPIs swapped between provider and organization
+    let mut swapped = create_valid_test_claim();
+    swapped.rendering_provider.npi = "1234567893".to_string();
+    swapped.organization.billing_npi = Some("9876543213".to_string());
+    let report = validator.validate(&swapped).unwrap_err();
+    assert_eq!(report.errors().filter(|i| i.code == "NPI_ENTITY_TYPE").count(), 2);
+
+    // an NPI missing from the registry is only a warning
+    let mut unknown = create_valid_test_claim();
+    unknown.rendering_provider.npi = "1111111112".to_string();
+    let report = validator.validate(&unknown).unwrap();
+    assert_eq!(report.warnings().map(|i| i.code.as_str()).collect::<Vec<_>>(), vec!["NPI_NOT_REGISTERED"]);
+}
+
+#[test]
+fn test_npi_registry_load_missing_file() {
+    let err = NpiRegistry::load(std::path::Path::new("no_such_registry.csv")).unwrap_err();
+    assert!(matches!(err, ClearinghouseError::Reference { path: Some(ref path), .. } if path == "no_such_registry.csv"));
+    assert_eq!(err.claim_id(), None);
+}
+
+#[test]
+fn test_is_icd10_code() {
+    for code in ["E11", "E11.9", "E119", "S72.001A", "Z01.818", "U07.1", "C4A.0"] {
+        assert!(is_icd10_code(code), "{}", code);
+    }
+    for code in ["", "E1", "11.9", "e11.9", "E11.", "E11.12345", "E1.19", "E11-9", "ÉÉ", "E1É.9", "E11.É"] {
+        assert!(!is_icd10_code(code), "{}", code);
+    }
+}
+
+#[test]
+fn test_validate_diagnoses_empty() {
+    let mut claim = create_valid_test_claim();
+    claim.diagnoses.clear();
+
+    let report = validate_claim(&claim).unwrap_err();
+    // pointers into an empty list aren't reported on top of the missing diagnoses
+    assert_eq!(report.errors().map(|i| i.code.as_str()).collect::<Vec<_>>(), vec!["DIAGNOSES_EMPTY"]);
+}
+
+#[test]
+fn test_validate_diagnosis_format_and_primary() {
+    let mut claim = create_valid_test_claim();
+    claim.diagnoses = vec![
+        Diagnosis { code: "E11.9".to_string(), primary: false },
+        Diagnosis { code: "250.00".to_string(), primary: false },
+    ];
+
+    let report = validate_claim(&claim).unwrap_err();
+    let issues: Vec<(&str, &str)> = report.errors().map(|i| (i.code.as_str(), i.path.as_str())).collect();
+    assert_eq!(issues, vec![
+        ("ICD10_FORMAT", "diagnoses[1].code"),
+        ("PRIMARY_DIAGNOSIS_MISSING", "diagnoses"),
+    ]);
+}
+
+#[test]
+fn test_validate_diagnosis_pointers() {
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].diagnosis_pointers = vec![1, 0, 2];
+    let mut second_line = claim.service_lines[0].clone();
+    second_line.service_line_id = "SL002".to_string();
+    second_line.diagnosis_pointers = vec![];
+    claim.service_lines.push(second_line);
+
+    let report = validate_claim(&claim).unwrap_err();
+    let issues: Vec<(&str, &str)> = report.errors().map(|i| (i.code.as_str(), i.path.as_str())).collect();
+    assert_eq!(issues, vec![
+        ("DIAGNOSIS_POINTER_RANGE", "service_lines[0].diagnosis_pointers[1]"),
+        ("DIAGNOSIS_POINTER_RANGE", "service_lines[0].diagnosis_pointers[2]"),
+        ("DIAGNOSIS_POINTER_MISSING", "service_lines[1].diagnosis_pointers"),
+    ]);
+}
+
+#[test]
+fn test_procedure_code_kind() {
+    assert_eq!(ProcedureCodeKind::of("99213"), Some(ProcedureCodeKind::CptCategoryI));
+    assert_eq!(ProcedureCodeKind::of("3074F"), Some(ProcedureCodeKind::CptCategoryII));
+    assert_eq!(ProcedureCodeKind::of("0042T"), Some(ProcedureCodeKind::CptCategoryIII));
+    assert_eq!(ProcedureCodeKind::of("0001U"), Some(ProcedureCodeKind::ProprietaryLab));
+    assert_eq!(ProcedureCodeKind::of("J1100"), Some(ProcedureCodeKind::HcpcsLevelII));
+    for code in ["9921", "992133", "D0120", "N1234", "j1100", "J110A", "3074X", "A12F4"] {
+        assert_eq!(ProcedureCodeKind::of(code), None, "{}", code);
+    }
+}
+
+#[test]
+fn test_validate_procedure_code_format() {
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].procedure_code = "9921".to_string();
+
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.errors().map(|i| i.code.as_str()).collect::<Vec<_>>(), vec!["PROCEDURE_CODE_FORMAT"]);
+    assert_eq!(report.issues[0].path, "service_lines[0].procedure_code");
+}
+
+#[test]
+fn test_validate_modifiers() {
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].modifiers = Some(["LT", "RT", "ZZ", "59", "XS"].iter().map(|m| m.to_string()).collect());
+
+    let report = validate_claim(&claim).unwrap_err();
+    let issues: Vec<(&str, &str)> = report.errors().map(|i| (i.code.as_str(), i.path.as_str())).collect();
+    assert_eq!(issues, vec![
+        ("MODIFIER_LIMIT", "service_lines[0].modifiers"),
+        ("MODIFIER_CONFLICT", "service_lines[0].modifiers"),
+        ("MODIFIER_UNKNOWN", "service_lines[0].modifiers[2]"),
+        ("MODIFIER_CONFLICT", "service_lines[0].modifiers"),
+    ]);
+    assert!(report.issues[1].message.contains("LT and RT"));
+}
+
+#[test]
+fn test_claim_validator_with_custom_code_tables() {
+    let modifiers = ModifierTable::from_csv("modifier,conflicts_with,description\n25,,Separate E/M, same day\nZZ,,Local modifier\n").unwrap();
+    assert_eq!(modifiers.description("25"), Some("Separate E/M, same day"));
+    let codes = ProcedureCodeSet::from_csv("procedure_code,description\n99214,Office visit, moderate\n").unwrap();
+    let validator = ClaimValidator::new().with_modifier_table(modifiers).with_procedure_codes(codes);
+
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].modifiers = Some(vec!["ZZ".to_string()]);
+    let report = validator.validate(&claim).unwrap_err();
+    assert_eq!(report.errors().map(|i| i.code.as_str()).collect::<Vec<_>>(), vec!["PROCEDURE_CODE_UNKNOWN"]);
+
+    claim.service_lines[0].procedure_code = "99214".to_string();
+    assert!(validator.validate(&claim).is_ok());
+}
+
+#[test]
+fn test_code_table_csv_errors() {
+    assert!(ModifierTable::from_csv("modifier,description\n").unwrap_err().to_string().contains("expected header"));
+    assert!(ModifierTable::from_csv("modifier,conflicts_with,description\nLEFT,,Left\n").unwrap_err().to_string().contains("Line 2"));
+    assert!(ProcedureCodeSet::from_csv("procedure_code,description\n9921,Short\n").unwrap_err().to_string().contains("Line 2"));
+    assert!(ProcedureCodeSet::from_csv("").unwrap_err().to_string().contains("empty"));
+
+    let bundled = ProcedureCodeSet::load(std::path::Path::new("code_sets/procedure_codes.csv")).unwrap();
+    assert!(bundled.contains("99213"));
+    assert!(ModifierTable::standard().conflicts("TC", "26"));
+}
+
+#[test]
+fn test_validate_place_of_service_unassigned() {
+    let mut claim = create_valid_test_claim();
+    claim.place_of_service_code = 30;
+
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.errors().map(|i| i.code.as_str()).collect::<Vec<_>>(), vec!["PLACE_OF_SERVICE_UNASSIGNED"]);
+    assert!(report.to_string().contains("place_of_service_code 30 is not an assigned CMS place of service"));
+    assert!(place_of_service(22).unwrap().facility);
+}
+
+#[test]
+fn test_duplicate_claim_id_rejected() {
+    let index = DuplicateIndex::new();
+    let claim = create_valid_test_claim();
+
+    assert_eq!(index.reserve(&claim).unwrap(), DuplicateCheck::New);
+    let error = index.reserve(&claim).unwrap_err();
+    assert!(matches!(error, ClearinghouseError::Duplicate { .. }));
+    assert!(!error.is_retryable());
+    assert_eq!(error.claim_id(), Some(claim.claim_id.as_str()));
+}
+
+#[test]
+fn test_suspected_duplicate_under_new_claim_id() {
+    let index = DuplicateIndex::new();
+    let original = create_valid_test_claim();
+    index.reserve(&original).unwrap();
+
+    let mut resubmitted = original.clone();
+    resubmitted.claim_id = "CLAIM999".to_string();
+    resubmitted.service_lines.reverse();
+    match index.reserve(&resubmitted).unwrap() {
+        DuplicateCheck::Suspected { original_claim_id, reason } => {
+            assert_eq!(original_claim_id, original.claim_id);
+            assert!(reason.contains("same patient"));
+        }
+        other => panic!("expected a suspected duplicate, got {:?}", other),
+    }
+
+    let mut different_patient = original.clone();
+    different_patient.claim_id = "CLAIM1000".to_string();
+    different_patient.insurance.patient_member_id = "OTHER".to_string();
+    assert_eq!(index.reserve(&different_patient).unwrap(), DuplicateCheck::New);
+}
+
+#[test]
+fn test_released_claim_can_be_resubmitted() {
+    let index = DuplicateIndex::new();
+    let claim = create_valid_test_claim();
+
+    index.reserve(&claim).unwrap();
+    index.release(&claim);
+    assert!(index.is_empty());
+    assert_eq!(index.reserve(&claim).unwrap(), DuplicateCheck::New);
+}
+
+#[test]
+fn test_duplicate_store_persists_across_runs() {
+    let path = std::env::temp_dir().join(format!("duplicate_store_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let claim = create_valid_test_claim();
+
+    let first_run = DuplicateIndex::open(&path).unwrap();
+    first_run.reserve(&claim).unwrap();
+    first_run.commit(&claim).unwrap();
+    drop(first_run);
+
+    let second_run = DuplicateIndex::open(&path).unwrap();
+    assert_eq!(second_run.len(), 1);
+    assert!(matches!(second_run.reserve(&claim), Err(ClearinghouseError::Duplicate { .. })));
+
+    std::fs::write(&path, "not json\n").unwrap();
+    assert!(DuplicateIndex::open(&path).unwrap_err().to_string().contains("Line 1"));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_validate_replacement_requires_control_number() {
+    let mut claim = create_valid_test_claim();
+    claim.frequency_code = ClaimFrequency::Replacement;
+
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.errors().map(|i| i.code.as_str()).collect::<Vec<_>>(), vec!["PAYER_CLAIM_CONTROL_NUMBER_MISSING"]);
+    assert!(report.to_string().contains("required for frequency code 7"));
+
+    claim.payer_claim_control_number = Some("ICN001".to_string());
+    assert!(validate_claim(&claim).is_ok());
+}
+
+#[test]
+fn test_submit_replacement_and_void_claims() {
+    let payers = instant_payers();
+    let original = create_valid_test_claim();
+    let remittances = submit_claim_to_payer(&original, &payers).unwrap();
+    let original_remittance = &remittances[0];
+
+    let mut replacement = create_valid_test_claim();
+    replacement.frequency_code = ClaimFrequency::Replacement;
+    replacement.payer_claim_control_number = Some(original_remittance.remittance_id.clone());
+    replacement.service_lines[0].unit_charge_amount = usd(200.0);
+    let remittances = submit_claim_to_payer(&replacement, &payers).unwrap();
+    assert_eq!(remittances.len(), 2);
+    assert!(remittances[0].reversal);
+    assert!(!remittances[1].reversal);
+
+    let ledger: Vec<ARData> = [original_remittance].into_iter().chain(&remittances).map(|r| submit_remittance_to_submitter(r).unwrap()).collect();
+    assert!(ledger[1].reversal);
+    assert_eq!(ledger[1].remittance_id, ledger[0].remittance_id);
+    let net_billed: Money = ledger.iter().map(|ar| ar.total_billed_amount).sum();
+    assert_eq!(net_billed, usd(200.0));
+
+    let mut void = create_valid_test_claim();
+    void.frequency_code = ClaimFrequency::Void;
+    void.payer_claim_control_number = Some(remittances[1].remittance_id.clone());
+    let remittances = submit_claim_to_payer(&void, &payers).unwrap();
+    assert_eq!(remittances.len(), 1);
+    assert_eq!(remittances[0].service_lines[0].billed_amount, usd(-200.0));
+
+    // the original was already reversed by the replacement
+    let mut stale = void.clone();
+    stale.claim_id = "CLAIM002".to_string();
+    stale.payer_claim_control_number = Some(original_remittance.remittance_id.clone());
+    let err = submit_claim_to_payer(&stale, &payers).unwrap_err();
+    assert!(matches!(err, ClearinghouseError::Payer { source: insurance::InsuranceError::Reversal { .. }, .. }));
+    assert!(!err.is_retryable());
+}
+
+#[test]
+fn test_replacement_passes_duplicate_check() {
+    let index = DuplicateIndex::new();
+    let original = create_valid_test_claim();
+    index.reserve(&original).unwrap();
+
+    let mut replacement = original.clone();
+    replacement.frequency_code = ClaimFrequency::Replacement;
+    replacement.payer_claim_control_number = Some("ICN001".to_string());
+    assert_eq!(index.reserve(&replacement).unwrap(), DuplicateCheck::New);
+    assert_eq!(index.len(), 1);
+}
+
+#[test]
+fn test_unknown_payer_is_rejected() {
+    let mut claim = create_valid_test_claim();
+    claim.insurance.payer_id = PayerId::Other("aetna".to_string());
+
+    let err = submit_claim_to_payer(&claim, &instant_payers()).unwrap_err();
+    assert!(matches!(err, ClearinghouseError::UnknownPayer { ref payer_id, .. } if payer_id == "aetna"));
+    assert!(!err.is_retryable());
+    assert_eq!(err.claim_id(), Some("CLAIM001"));
+}
+
+#[test]
+fn test_payer_registry_from_csv_routes_aliases() {
+    let csv = "payer_id,simulator,aliases,currencies\naetna,united_health_group,60054 AET,\nuhc_alias_only,united_health_group,,\nmedicare,medicare,00430,USD\n";
+    let payers = PayerRegistry::from_csv(csv, &instant_payer_settings()).unwrap();
+    assert_eq!(payers.len(), 5);
+
+    let mut claim = create_valid_test_claim();
+    claim.insurance.payer_id = PayerId::Other("aet".to_string());
+    let remittances = submit_claim_to_payer(&claim, &payers).unwrap();
+    assert_eq!(remittances[0].payer_id, "aetna");
+    assert_eq!(remittances[0].patient_id, "aetna-MED123456");
+
+    claim.claim_id = "CLAIM002".to_string();
+    claim.insurance.payer_id = PayerId::Other("00430".to_string());
+    assert_eq!(submit_claim_to_payer(&claim, &payers).unwrap()[0].payer_id, "Medicare");
+
+    let bundled = PayerRegistry::load(std::path::Path::new("../payers.csv"), &instant_payer_settings()).unwrap();
+    assert!(bundled.resolve(&PayerId::Other("UHC".to_string())).is_some());
+    assert!(bundled.resolve(&PayerId::Other("tricare".to_string())).is_none());
+
+    // X12 payer ids come in as the NM109 id; the registry says which payer it stands for
+    assert_eq!(bundled.canonical_id(&PayerId::Other("00430".to_string())), Some(PayerId::Medicare));
+    assert_eq!(bundled.canonical_id(&PayerId::Other("UHC".to_string())), Some(PayerId::UnitedHealthGroup));
+    assert_eq!(bundled.canonical_id(&PayerId::Anthem), Some(PayerId::Anthem));
+    assert_eq!(payers.canonical_id(&PayerId::Other("60054".to_string())), Some(PayerId::Other("aetna".to_string())));
+    assert_eq!(bundled.canonical_id(&PayerId::Other("tricare".to_string())), None);
+}
+
+#[test]
+fn test_payer_registry_plan_catalog_payer() {
+    // seeded so the plan's random denials leave this line alone
+    let settings = PayerSettings { seed: Some(7), ..instant_payer_settings() };
+    let payers = PayerRegistry::load(std::path::Path::new("../payers.csv"), &settings).unwrap();
+    let mut claim = create_valid_test_claim();
+    claim.insurance.payer_id = PayerId::Other("61101".to_string());
+    claim.insurance.patient_member_id = "HUM100200300".to_string();
+
+    let remittances = submit_claim_to_payer(&claim, &payers).unwrap();
+    assert_eq!(remittances[0].payer_id, "humana");
+    // the member's bronze plan has a $3,300 deductible and no office visit copay
+    let line = &remittances[0].service_lines[0];
+    assert!(line.deductible_amount > usd(0.0), "{:?}", line);
+    assert_eq!(line.payer_paid_amount, usd(0.0));
+    assert_eq!(line.copay_amount, usd(0.0));
+
+    let err = PayerRegistry::from_csv("payer_id,simulator,aliases,currencies\nhumana,plans/missing.json,,\n", &instant_payer_settings()).unwrap_err();
+    assert!(err.to_string().contains("Line 2: Plans plans/missing.json: failed to read"), "{}", err);
+}
+
+#[test]
+fn test_payer_registry_csv_errors() {
+    let settings = instant_payer_settings();
+    let err = PayerRegistry::from_csv("payer_id,simulator,aliases,currencies\naetna,tricare,,\n", &settings).unwrap_err();
+    assert!(err.to_string().contains("Line 2: unknown simulator tricare"), "{}", err);
+    let err = PayerRegistry::from_csv("payer_id,simulator\n", &settings).unwrap_err();
+    assert!(err.to_string().contains("expected header"), "{}", err);
+    let err = PayerRegistry::from_csv("payer_id,simulator,aliases,currencies\naetna,united_health_group,,USD XYZ\n", &settings).unwrap_err();
+    assert!(err.to_string().contains("Line 2: XYZ is not an ISO 4217 currency code"), "{}", err);
+
+    let mut payers = PayerRegistry::new();
+    assert!(payers.is_empty());
+    assert!(payers.alias("uhc", "united_health_group").is_err());
+    assert!(payers.accept_currencies("united_health_group", &["USD"]).is_err());
+}
+
+#[test]
+fn test_exchange_rates_apply_from_their_effective_date() {
+    let rates = ExchangeRateTable::from_csv("from_currency,to_currency,effective_date,rate\nEUR,USD,2022-06-01,1.07\nEUR,USD,2022-01-01,1.13\n").unwrap();
+    assert_eq!(rates.len(), 2);
+    assert!(rates.rate("EUR", "USD", date("2021-12-31")).is_none());
+    assert_eq!(rates.rate("EUR", "USD", date("2022-05-31")).unwrap().rate, 1.13);
+    let conversion = rates.rate("EUR", "USD", date("2022-06-01")).unwrap();
+    assert_eq!((conversion.rate, conversion.effective_date), (1.07, date("2022-06-01")));
+    // rates only apply in the direction they're listed
+    assert!(rates.rate("USD", "EUR", date("2022-06-01")).is_none());
+
+    let err = ExchangeRateTable::from_csv("from_currency,to_currency,effective_date,rate\nEUR,USD,2022-01-01,-1\n").unwrap_err();
+    assert!(err.to_string().contains("Line 2: rate -1 must be a positive number"), "{}", err);
+    let err = ExchangeRateTable::from_csv("from_currency,to_currency,effective_date,rate\nEUR,USD,01/01/2022,1.1\n").unwrap_err();
+    assert!(err.to_string().contains("Line 2: invalid effective_date 01/01/2022"), "{}", err);
+    assert_eq!(ExchangeRateTable::load(std::path::Path::new("../exchange_rates.csv")).unwrap().len(), 12);
+}
+
+#[test]
+fn test_submit_claim_converts_into_the_settlement_currency() {
+    let rates = ExchangeRateTable::load(std::path::Path::new("../exchange_rates.csv")).unwrap();
+    let payers = PayerRegistry::load(std::path::Path::new("../payers.csv"), &instant_payer_settings()).unwrap().with_exchange_rates(rates);
+    assert_eq!(payers.accepted_currencies(&PayerId::Other("62308".to_string())).unwrap(), ["USD", "EUR", "GBP", "CAD"]);
+
+    let mut claim = create_valid_test_claim();
+    claim.insurance.payer_id = PayerId::Other("cigna".to_string());
+    claim.service_lines[0].unit_charge_currency = "EUR".to_string();
+    claim.other_insurance = vec![medigap_coverage()];
+    let mut remittances = submit_claim_to_payer(&claim, &payers).unwrap();
+
+    // received 2022-01-01: EUR 150.00 at 1.1318
+    let remittance = &remittances[0];
+    assert_eq!(remittance.currency, "USD");
+    assert_eq!(remittance.service_lines[0].billed_amount, usd(169.77));
+    let conversion = remittance.currency_conversion.clone().unwrap();
+    assert_eq!((conversion.from_currency.as_str(), conversion.to_currency.as_str()), ("EUR", "USD"));
+    assert_eq!((conversion.rate, conversion.effective_date), (1.1318, date("2022-01-01")));
+    let ar = submit_remittance_to_submitter(remittance).unwrap();
+    assert_eq!(ar.currency, "USD");
+    assert_eq!(ar.currency_conversion, Some(conversion));
+
+    // the secondary is billed in the dollars the primary paid in
+    let secondary_claim = crossover_claim(&claim, &mut remittances).unwrap();
+    assert_eq!(secondary_claim.service_lines[0].unit_charge_currency, "USD");
+    assert_eq!(secondary_claim.service_lines[0].unit_charge_amount, usd(169.77));
+    assert!(submit_claim_to_payer(&secondary_claim, &payers).unwrap()[0].currency_conversion.is_none());
+}
+
+#[test]
+fn test_submit_claim_in_a_currency_the_payer_cannot_settle() {
+    let payers = PayerRegistry::load(std::path::Path::new("../payers.csv"), &instant_payer_settings()).unwrap();
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].unit_charge_currency = "EUR".to_string();
+    let err = submit_claim_to_payer(&claim, &payers).unwrap_err();
+    assert_eq!(err, ClearinghouseError::Currency {
+        claim_id: "CLAIM001".to_string(),
+        message: "payer medicare doesn't accept EUR (accepts USD)".to_string(),
+    });
+    assert!(!err.is_retryable());
+
+    // accepted, but no rate was loaded
+    claim.insurance.payer_id = PayerId::Other("cigna".to_string());
+    let err = submit_claim_to_payer(&claim, &payers).unwrap_err();
+    assert!(err.to_string().contains("no EUR to USD exchange rate in effect on 2022-01-01"), "{}", err);
+}
+
+#[test]
+fn test_check_eligibility_through_registry() {
+    let roster = insurance::MemberRoster::from_csv("payer_id,member_id,first_name,last_name,dob,gender,plan,coverage_start,coverage_end\nmedicare,MED123456,John,Doe,1980-01-15,m,,2020-01-01,2021-12-31\n").unwrap();
+    let settings = PayerSettings { roster: Arc::new(roster), ..instant_payer_settings() };
+    let payers = PayerRegistry::with_builtins(&settings);
+    let date = chrono::NaiveDate::from_ymd_opt(2022, 1, 1).unwrap();
+
+    let response = check_eligibility(&PayerId::Medicare, "MED123456", date, &payers).unwrap();
+    assert_eq!(response.status, insurance::CoverageStatus::Terminated);
+    let response = check_eligibility(&PayerId::Anthem, "MED123456", date, &payers).unwrap();
+    assert!(response.benefits.is_some());
+
+    // the claim itself is denied the same way
+    let claim = create_valid_test_claim();
+    let remittances = submit_claim_to_payer(&claim, &payers).unwrap();
+    assert!(remittances[0].service_lines.iter().all(|line| line.payer_paid_amount == usd(0.0)));
+    assert_eq!(remittances[0].service_lines[0].adjustments[0].reason_code, insurance::eligibility::CARC_AFTER_COVERAGE);
+
+    let err = check_eligibility(&PayerId::Other("tricare".to_string()), "MED123456", date, &payers).unwrap_err();
+    assert!(matches!(err, ClearinghouseError::Eligibility { source: None, .. }));
+    assert!(!err.is_retryable());
+    assert_eq!(err.claim_id(), None);
+}
+
+fn medigap_coverage() -> Insurance {
+    Insurance {
+        payer_id: PayerId::Other("36273".to_string()),
+        patient_member_id: "AARP100".to_string(),
+        group_number: None,
+        relationship: Relationship::SelfInsured,
+        subscriber: None,
+    }
+}
+
+#[test]
+fn test_crossover_to_secondary_reduces_patient_responsibility() {
+    let payers = PayerRegistry::load(std::path::Path::new("../payers.csv"), &instant_payer_settings()).unwrap();
+    let mut claim = create_valid_test_claim();
+    claim.other_insurance = vec![medigap_coverage()];
+
+    let mut primary = submit_claim_to_payer(&claim, &payers).unwrap();
+    let secondary_claim = crossover_claim(&claim, &mut primary).unwrap();
+    assert_eq!(primary[0].crossover_payer_id.as_deref(), Some("36273"));
+    assert_eq!(secondary_claim.insurance.patient_member_id, "AARP100");
+    assert!(secondary_claim.other_insurance.is_empty());
+    let prior = &secondary_claim.prior_payments[0];
+    assert_eq!(prior.payer_id, PayerId::Medicare);
+    assert_eq!(prior.payer_claim_control_number, primary[0].remittance_id);
+    assert!(validate_claim(&secondary_claim).is_ok());
+
+    let secondary = submit_claim_to_payer(&secondary_claim, &payers).unwrap();
+    assert_eq!(secondary[0].payer_id, "aarp_medigap");
+    assert_eq!(secondary[0].payer_sequence, 2);
+
+    let primary_ar = submit_remittance_to_submitter(&primary[0]).unwrap();
+    let secondary_ar = submit_remittance_to_submitter(&secondary[0]).unwrap();
+    let owed = |ar: &ARData| ar.total_deductible_amount + ar.total_coinsurance_amount + ar.total_copay_amount;
+    // Plan F picks up everything Medicare left the patient, deductible included
+    assert!(owed(&primary_ar) > Money::ZERO);
+    assert_eq!(owed(&secondary_ar), Money::ZERO);
+    assert_eq!(secondary_ar.total_payer_paid_amount, owed(&primary_ar));
+    assert_eq!(primary_ar.total_payer_paid_amount + secondary_ar.total_payer_paid_amount + primary_ar.total_not_allowed_amount, primary_ar.total_billed_amount);
+    assert_eq!(primary_ar.crossover_payer_id.as_deref(), Some("36273"));
+    assert!(crossover_claim(&secondary_claim, &mut secondary.clone()).is_none());
+}
+
+#[test]
+fn test_crossover_skipped_without_patient_balance_or_for_corrections() {
+    let mut claim = create_valid_test_claim();
+    claim.other_insurance = vec![medigap_coverage()];
+    let mut remittances = vec![create_test_remittance()];
+    assert!(crossover_claim(&claim, &mut remittances).is_some());
+
+    // nothing left for the patient to owe
+    let mut paid_in_full = vec![create_test_remittance()];
+    let line = &mut paid_in_full[0].service_lines[0];
+    (line.coinsurance_amount, line.copay_amount, line.deductible_amount) = (Money::ZERO, Money::ZERO, Money::ZERO);
+    assert!(crossover_claim(&claim, &mut paid_in_full).is_none());
+    assert!(paid_in_full[0].crossover_payer_id.is_none());
+
+    claim.frequency_code = ClaimFrequency::Void;
+    assert!(crossover_claim(&claim, &mut [create_test_remittance()]).is_none());
+    claim.frequency_code = ClaimFrequency::Original;
+    claim.other_insurance.clear();
+    assert!(crossover_claim(&claim, &mut [create_test_remittance()]).is_none());
+}
+
+#[test]
+fn test_validate_other_coverages() {
+    let mut claim = create_valid_test_claim();
+    claim.other_insurance = vec![Insurance { patient_member_id: " ".to_string(), relationship: Relationship::Spouse, ..medigap_coverage() }];
+    let report = validate_claim(&claim).unwrap_err();
+    let codes: Vec<(&str, &str)> = report.errors().map(|i| (i.code.as_str(), i.path.as_str())).collect();
+    assert_eq!(
+        codes,
+        vec![
+            ("REQUIRED_FIELD", "other_insurance[0].patient_member_id"),
+            ("SUBSCRIBER_MISSING", "other_insurance[0].subscriber"),
+        ]
+    );
+
+    claim.other_insurance = vec![Insurance { payer_id: PayerId::Medicare, ..medigap_coverage() }];
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.issues[0].code, "COVERAGE_PAYER_REPEATED");
+    assert_eq!(report.issues[0].path, "other_insurance[0].payer_id");
+
+    claim.other_insurance = vec![medigap_coverage()];
+    claim.prior_payments = vec![PriorPayment {
+        payer_id: PayerId::Medicare,
+        payer_claim_control_number: "REM123".to_string(),
+        service_lines: vec![PriorLinePayment { service_line_id: "SL404".to_string(), paid_amount: usd(0.0), adjustments: Vec::new() }],
+    }];
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.issues[0].code, "PRIOR_PAYMENT_LINE_UNKNOWN");
+    assert_eq!(report.issues[0].path, "prior_payments[0].service_lines[0].service_line_id");
+}
+
+fn date(s: &str) -> chrono::NaiveDate {
+    chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+#[test]
+fn test_validate_service_dates() {
+    // the claim is received on 2022-01-01 for a patient born 1980-01-15
+    let mut claim = create_valid_test_claim();
+    claim.service_lines[0].service_date_from = Some(date("2021-12-30"));
+    claim.service_lines[0].service_date_to = Some(date("2022-01-01"));
+    assert!(validate_claim(&claim).is_ok());
+
+    let issues = |claim: &PayerClaim| -> Vec<(String, String)> {
+        validate_claim(claim).unwrap_err().errors().map(|i| (i.code.clone(), i.path.clone())).collect()
+    };
+    claim.service_lines[0].service_date_to = Some(date("2022-01-02"));
+    assert_eq!(issues(&claim), vec![("SERVICE_DATE_FUTURE".to_string(), "service_lines[0].service_date_to".to_string())]);
+
+    claim.service_lines[0].service_date_to = Some(date("2021-12-29"));
+    assert_eq!(issues(&claim), vec![("SERVICE_DATE_RANGE".to_string(), "service_lines[0].service_date_to".to_string())]);
+
+    claim.service_lines[0].service_date_from = Some(date("1979-06-01"));
+    claim.service_lines[0].service_date_to = None;
+    let report = validate_claim(&claim).unwrap_err();
+    assert_eq!(report.issues[0].code, "SERVICE_DATE_BEFORE_BIRTH");
+    assert!(report.to_string().contains("1979-06-01 is before the patient's date of birth 1980-01-15"), "{}", report);
+
+    claim.service_lines[0].service_date_from = None;
+    claim.service_lines[0].service_date_to = Some(date("2021-12-01"));
+    assert_eq!(issues(&claim), vec![("SERVICE_DATE_MISSING".to_string(), "service_lines[0].service_date_from".to_string())]);
+}
+
+#[test]
+fn test_same_services_on_different_days_are_not_duplicates() {
+    let index = DuplicateIndex::new();
+    let mut monday = create_valid_test_claim();
+    monday.service_lines[0].service_date_from = Some(date("2021-12-27"));
+    index.reserve(&monday).unwrap();
+
+    let mut tuesday = monday.clone();
+    tuesday.claim_id = "CLAIM002".to_string();
+    tuesday.service_lines[0].service_date_from = Some(date("2021-12-28"));
+    assert_eq!(index.reserve(&tuesday).unwrap(), DuplicateCheck::New);
+
+    let mut resubmitted = monday.clone();
+    resubmitted.claim_id = "CLAIM003".to_string();
+    assert!(matches!(index.reserve(&resubmitted).unwrap(), DuplicateCheck::Suspected { .. }));
+}
+
+#[test]
+fn test_medicare_patients_under_65_need_a_disability_or_esrd_entitlement() {
+    let warnings = |claim: &PayerClaim| -> Vec<String> {
+        validate_claim(claim).unwrap().warnings().map(|issue| issue.code.clone()).collect()
+    };
+    // 41 years old on 2022-01-01
+    let mut claim = create_valid_test_claim();
+    assert!(warnings(&claim).is_empty());
+
+    claim.patient.medicare_entitlement = Some(MedicareEntitlement::Esrd);
+    assert!(warnings(&claim).is_empty());
+
+    claim.patient.medicare_entitlement = None;
+    let report = validate_claim(&claim).unwrap();
+    assert_eq!(report.issues[0].code, "MEDICARE_UNDER_65");
+    assert_eq!(report.issues[0].path, "patient.medicare_entitlement");
+    assert!(report.issues[0].message.contains("patient is 41"), "{}", report.issues[0].message);
+
+    claim.patient.medicare_entitlement = Some(MedicareEntitlement::Age);
+    assert_eq!(warnings(&claim), vec!["MEDICARE_UNDER_65".to_string()]);
+
+    // turning 65 on the date of service is enough
+    claim.patient.dob = date("1956-12-30");
+    claim.service_lines[0].service_date_from = Some(date("2021-12-30"));
+    assert!(warnings(&claim).is_empty());
+    claim.service_lines[0].service_date_from = Some(date("2021-12-29"));
+    assert_eq!(warnings(&claim), vec!["MEDICARE_UNDER_65".to_string()]);
+
+    // the rule follows Medicare wherever it is among the coverages
+    claim.patient.dob = date("1980-01-15");
+    claim.insurance.payer_id = PayerId::Anthem;
+    assert!(warnings(&claim).is_empty());
+    claim.other_insurance.push(Insurance { payer_id: PayerId::Medicare, ..claim.insurance.clone() });
+    assert_eq!(warnings(&claim), vec!["MEDICARE_UNDER_65".to_string()]);
+}
+
+#[test]
+fn test_medicare_under_65_follows_payer_aliases() {
+    let payers = Arc::new(PayerRegistry::load(std::path::Path::new("../payers.csv"), &instant_payer_settings()).unwrap());
+    let validator = ClaimValidator::new().with_payer_registry(payers);
+    let warnings = |claim: &PayerClaim| -> Vec<String> {
+        validator.validate(claim).unwrap().warnings().map(|issue| issue.code.clone()).collect()
+    };
+    // 41 years old on 2022-01-01 with no disability or esrd, billed to Medicare under its X12
+    // payer id or an alias
+    let mut claim = create_valid_test_claim();
+    claim.patient.medicare_entitlement = None;
+    for alias in ["00430", "CMS", "MEDICARE"] {
+        claim.insurance.payer_id = PayerId::Other(alias.to_string());
+        assert_eq!(warnings(&claim), vec!["MEDICARE_UNDER_65".to_string()], "{}", alias);
+    }
+
+    claim.insurance.payer_id = PayerId::Other("uhc".to_string());
+    assert!(warnings(&claim).is_empty());
+    claim.other_insurance.push(Insurance { payer_id: PayerId::Other("cms".to_string()), ..claim.insurance.clone() });
+    assert_eq!(warnings(&claim), vec!["MEDICARE_UNDER_65".to_string()]);
+}
+
+#[test]
+fn test_coverage_payer_repeated_under_an_alias() {
+    let payers = Arc::new(PayerRegistry::load(std::path::Path::new("../payers.csv"), &instant_payer_settings()).unwrap());
+    let validator = ClaimValidator::new().with_payer_registry(payers);
+    let mut claim = create_valid_test_claim();
+    claim.insurance.payer_id = PayerId::UnitedHealthGroup;
+    for alias in ["87726", "UHC", "United_Health_Group"] {
+        claim.other_insurance = vec![Insurance { payer_id: PayerId::Other(alias.to_string()), ..claim.insurance.clone() }];
+        let report = validator.validate(&claim).unwrap_err();
+        assert_eq!(report.issues[0].code, "COVERAGE_PAYER_REPEATED", "{}", alias);
+        assert_eq!(report.issues[0].path, "other_insurance[0].payer_id");
+    }
+
+    claim.other_insurance = vec![Insurance { payer_id: PayerId::Other("cms".to_string()), ..claim.insurance.clone() }];
+    assert!(validator.validate(&claim).is_ok());
+}
+
+#[test]
+fn test_replacement_the_payer_fails_on_leaves_the_original_standing() {
+    // seed 57: the replacement's reversal gets through, then the payer is down for the
+    // replacement itself; the retry gets through both
+    let settings = PayerSettings { seed: Some(57), outage_rate: 0.5, ..instant_payer_settings() };
+    let payers = PayerRegistry::with_builtins(&settings);
+    let original = create_valid_test_claim();
+    let original_remittance = submit_claim_to_payer(&original, &payers).unwrap().remove(0);
+    let after_original = settings.accumulators.individual("Medicare", "MED123456", 2022).unwrap();
+    assert!(after_original.deductible_met > usd(0.0));
+
+    let mut replacement = create_valid_test_claim();
+    replacement.claim_id = "CLAIM002".to_string();
+    replacement.frequency_code = ClaimFrequency::Replacement;
+    replacement.payer_claim_control_number = Some(original_remittance.remittance_id.clone());
+    let err = submit_claim_to_payer(&replacement, &payers).unwrap_err();
+    assert!(err.is_retryable(), "{}", err);
+    // no remittance reached the ledger, so the payer still has the original adjudicated and counted
+    assert_eq!(settings.accumulators.individual("Medicare", "MED123456", 2022), Some(after_original));
+
+    let remittances = submit_claim_to_payer(&replacement, &payers).unwrap();
+    assert!(remittances[0].reversal);
+    assert_eq!(remittances[0].remittance_id, original_remittance.remittance_id);
+    assert_eq!(remittances[1].service_lines[0].deductible_amount, original_remittance.service_lines[0].deductible_amount);
+    assert_eq!(settings.accumulators.individual("Medicare", "MED123456", 2022), Some(after_original));
+}
+
+#[test]
+fn test_stale_dated_replacement_is_denied_after_reversing_the_original() {
+    let settings = instant_payer_settings();
+    let payers = PayerRegistry::with_builtins(&settings);
+    let original = create_valid_test_claim();
+    let original_remittance = submit_claim_to_payer(&original, &payers).unwrap().remove(0);
+
+    // billed more than Medicare's year after the date of service
+    let mut replacement = create_valid_test_claim();
+    replacement.claim_id = "CLAIM002".to_string();
+    replacement.frequency_code = ClaimFrequency::Replacement;
+    replacement.payer_claim_control_number = Some(original_remittance.remittance_id.clone());
+    replacement.service_lines[0].service_date_from = Some(date("2020-06-01"));
+    let remittances = submit_claim_to_payer(&replacement, &payers).unwrap();
+    assert!(remittances[0].reversal);
+    assert_eq!(remittances[1].service_lines[0].adjustments[0].reason_code, "29");
+    assert_eq!(remittances[1].service_lines[0].payer_paid_amount, usd(0.0));
+
+    // the ledger nets the original out, and so do the member's accumulators
+    let ledger: Vec<ARData> = [&original_remittance].into_iter().chain(&remittances).map(|r| submit_remittance_to_submitter(r).unwrap()).collect();
+    assert_eq!(ledger.iter().map(|ar| ar.total_payer_paid_amount).sum::<Money>(), usd(0.0));
+    assert_eq!(settings.accumulators.individual("Medicare", "MED123456", 2022).unwrap().deductible_met, usd(0.0));
+}
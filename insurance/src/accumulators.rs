@@ -0,0 +1,306 @@
+use crate::InsuranceError;
+use chrono::Datelike;
+use intake::{Money, PayerClaim, Relationship};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Annual cost-sharing limits for a member's plan. `None` means the plan has no such limit
+/// (e.g. Original Medicare has no out-of-pocket maximum).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenefitLimits {
+    pub deductible: Money,
+    pub out_of_pocket_max: Option<Money>,
+    pub family_deductible: Option<Money>,
+    pub family_out_of_pocket_max: Option<Money>,
+}
+
+/// Running totals for one member (or one family) within one plan year.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accumulator {
+    pub deductible_limit: Money,
+    pub out_of_pocket_limit: Option<Money>,
+    pub deductible_met: Money,
+    pub coinsurance_paid: Money,
+    pub out_of_pocket_paid: Money,
+}
+
+impl Accumulator {
+    fn new(deductible_limit: Money, out_of_pocket_limit: Option<Money>) -> Accumulator {
+        Accumulator {
+            deductible_limit,
+            out_of_pocket_limit,
+            deductible_met: Money::ZERO,
+            coinsurance_paid: Money::ZERO,
+            out_of_pocket_paid: Money::ZERO,
+        }
+    }
+
+    pub fn remaining_deductible(&self) -> Money {
+        (self.deductible_limit - self.deductible_met).max(Money::ZERO)
+    }
+
+    /// `Money::MAX` when the plan has no out-of-pocket maximum.
+    pub fn remaining_out_of_pocket(&self) -> Money {
+        self.out_of_pocket_limit
+            .map(|limit| (limit - self.out_of_pocket_paid).max(Money::ZERO))
+            .unwrap_or(Money::MAX)
+    }
+
+    fn record(&mut self, share: &CostShare) {
+        self.deductible_met += share.deductible;
+        self.coinsurance_paid += share.coinsurance;
+        self.out_of_pocket_paid += share.deductible + share.copay + share.coinsurance;
+    }
+
+    fn unrecord(&mut self, share: &CostShare) {
+        self.deductible_met = (self.deductible_met - share.deductible).max(Money::ZERO);
+        self.coinsurance_paid = (self.coinsurance_paid - share.coinsurance).max(Money::ZERO);
+        self.out_of_pocket_paid = (self.out_of_pocket_paid - share.deductible - share.copay - share.coinsurance).max(Money::ZERO);
+    }
+}
+
+/// How one line's allowed amount was split between the payer and the member.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostShare {
+    pub deductible: Money,
+    pub copay: Money,
+    pub coinsurance: Money,
+    pub payer_paid: Money,
+}
+
+/// The individual and (if the member belongs to one) family accumulators, borrowed for the
+/// duration of a single claim's adjudication.
+pub struct MemberBenefits<'a> {
+    pub individual: &'a mut Accumulator,
+    pub family: Option<&'a mut Accumulator>,
+}
+
+impl MemberBenefits<'_> {
+    /// An embedded family deductible: the member is done once either their own or the family's is met.
+    pub fn remaining_deductible(&self) -> Money {
+        let individual = self.individual.remaining_deductible();
+        self.family.as_ref().map(|f| individual.min(f.remaining_deductible())).unwrap_or(individual)
+    }
+
+    pub fn remaining_out_of_pocket(&self) -> Money {
+        let individual = self.individual.remaining_out_of_pocket();
+        self.family.as_ref().map(|f| individual.min(f.remaining_out_of_pocket())).unwrap_or(individual)
+    }
+
+    /// Splits an allowed amount into deductible, copay and coinsurance, then records it.
+    ///
+    /// The deductible is applied first, then the copay (only if enough remains to cover it), then
+    /// `payer_rate` of what is left is paid by the plan, rounded to the cent, and the rest is
+    /// coinsurance. Member cost sharing beyond the remaining out-of-pocket maximum is shifted back
+    /// to the payer, coinsurance first. The shares always add up to `allowed` exactly.
+    pub fn adjudicate(&mut self, allowed: Money, copay: Money, payer_rate: f64) -> CostShare {
+        let deductible = allowed.min(self.remaining_deductible());
+        let remaining_after_deductible = allowed - deductible;
+
+        let copay = if remaining_after_deductible > copay { copay } else { Money::ZERO };
+        let remaining_after_copay = remaining_after_deductible - copay;
+
+        let payer_paid = remaining_after_copay.times(payer_rate);
+        let mut share = CostShare {
+            deductible,
+            copay,
+            coinsurance: remaining_after_copay - payer_paid,
+            payer_paid,
+        };
+
+        let mut excess = (share.deductible + share.copay + share.coinsurance - self.remaining_out_of_pocket()).max(Money::ZERO);
+        for bucket in [&mut share.coinsurance, &mut share.copay, &mut share.deductible] {
+            let shifted = excess.min(*bucket);
+            *bucket -= shifted;
+            share.payer_paid += shifted;
+            excess -= shifted;
+        }
+
+        self.individual.record(&share);
+        if let Some(family) = self.family.as_mut() {
+            family.record(&share);
+        }
+        share
+    }
+}
+
+/// Whose accumulators a claim's cost sharing counts toward. The subscriber goes by their member
+/// id; their dependents share it, so each is told apart by name and date of birth, as the roster
+/// does, and all of them count toward the family under the member id.
+pub fn accumulator_member(claim: &PayerClaim) -> String {
+    let member_id = &claim.insurance.patient_member_id;
+    if claim.insurance.relationship == Relationship::SelfInsured {
+        return member_id.clone();
+    }
+    let patient = &claim.patient;
+    let name = format!("{} {}", patient.first_name.trim(), patient.last_name.trim()).to_ascii_lowercase();
+    format!("{}/{} {}", member_id, name, patient.dob)
+}
+
+type AccumulatorKey = (String, String, i32);
+
+#[derive(Default)]
+struct AccumulatorState {
+    individuals: HashMap<AccumulatorKey, Accumulator>,
+    families: HashMap<AccumulatorKey, Accumulator>,
+    family_of: HashMap<(String, String), String>,
+}
+
+/// Per-member, per-plan-year deductible and out-of-pocket tracking shared by every worker.
+///
+/// All of a claim's lines are adjudicated inside a single `transaction`, so two workers
+/// processing claims for the same member can never both apply the same deductible dollars.
+pub struct AccumulatorStore {
+    plan_year_start: (u32, u32),
+    state: Mutex<AccumulatorState>,
+}
+
+impl Default for AccumulatorStore {
+    fn default() -> Self {
+        AccumulatorStore::new()
+    }
+}
+
+impl AccumulatorStore {
+    /// Calendar-year plans, resetting on January 1.
+    pub fn new() -> AccumulatorStore {
+        AccumulatorStore { plan_year_start: (1, 1), state: Mutex::new(AccumulatorState::default()) }
+    }
+
+    /// Plans that reset on the given month and day (e.g. `(7, 1)` for a July plan year).
+    pub fn with_plan_year_start(month: u32, day: u32) -> Result<AccumulatorStore, InsuranceError> {
+        if chrono::NaiveDate::from_ymd_opt(2001, month, day).is_none() {
+            return Err(InsuranceError::Plan {
+                field: "plan_year_start".to_string(),
+                message: format!("Invalid plan year start {}-{}", month, day),
+            });
+        }
+        Ok(AccumulatorStore { plan_year_start: (month, day), state: Mutex::new(AccumulatorState::default()) })
+    }
+
+    /// The plan year (named by the calendar year it starts in) containing the given timestamp.
+    pub fn plan_year(&self, timestamp_millis: i64) -> i32 {
+        let date = chrono::DateTime::from_timestamp_millis(timestamp_millis)
+            .map(|dt| dt.date_naive())
+            .unwrap_or_default();
+        self.plan_year_of(date)
+    }
+
+    /// The plan year containing the given day.
+    pub fn plan_year_of(&self, date: chrono::NaiveDate) -> i32 {
+        if (date.month(), date.day()) >= self.plan_year_start {
+            date.year()
+        } else {
+            date.year() - 1
+        }
+    }
+
+    pub fn add_family_member(&self, payer_id: &str, member_id: &str, family_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.family_of.insert((payer_id.to_string(), member_id.to_string()), family_id.to_string());
+    }
+
+    /// Like `add_family_member`, but a member already in a family stays in it.
+    pub fn join_family(&self, payer_id: &str, member_id: &str, family_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.family_of.entry((payer_id.to_string(), member_id.to_string())).or_insert_with(|| family_id.to_string());
+    }
+
+    /// Runs `f` with exclusive access to the member's accumulators, creating them from `limits`
+    /// the first time the member is seen in a plan year.
+    pub fn transaction<T>(
+        &self,
+        payer_id: &str,
+        member_id: &str,
+        plan_year: i32,
+        limits: impl FnOnce() -> BenefitLimits,
+        f: impl FnOnce(&mut MemberBenefits) -> T,
+    ) -> T {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let family_id = state.family_of.get(&(payer_id.to_string(), member_id.to_string())).cloned();
+
+        let key = (payer_id.to_string(), member_id.to_string(), plan_year);
+        let family_key = family_id.map(|family_id| (payer_id.to_string(), family_id, plan_year));
+        let needs_limits = !state.individuals.contains_key(&key)
+            || family_key.as_ref().is_some_and(|k| !state.families.contains_key(k));
+        let limits = needs_limits.then(limits);
+
+        let individual = state
+            .individuals
+            .entry(key)
+            .or_insert_with(|| Accumulator::new(limits.unwrap().deductible, limits.unwrap().out_of_pocket_max));
+        let family = family_key.map(|key| {
+            state.families.entry(key).or_insert_with(|| {
+                let limits = limits.unwrap();
+                Accumulator::new(limits.family_deductible.unwrap_or(Money::MAX), limits.family_out_of_pocket_max)
+            })
+        });
+
+        f(&mut MemberBenefits { individual, family })
+    }
+
+    /// Like `transaction`, except that when `f` fails the member's (and their family's)
+    /// accumulators are put back as they were before it ran, inside the same lock.
+    pub fn try_transaction<T, E>(
+        &self,
+        payer_id: &str,
+        member_id: &str,
+        plan_year: i32,
+        limits: impl FnOnce() -> BenefitLimits,
+        f: impl FnOnce(&mut MemberBenefits) -> Result<T, E>,
+    ) -> Result<T, E> {
+        self.transaction(payer_id, member_id, plan_year, limits, |benefits| {
+            let individual = *benefits.individual;
+            let family = benefits.family.as_deref().copied();
+            let result = f(benefits);
+            if result.is_err() {
+                *benefits.individual = individual;
+                if let (Some(accumulator), Some(family)) = (benefits.family.as_deref_mut(), family) {
+                    *accumulator = family;
+                }
+            }
+            result
+        })
+    }
+
+    /// Takes a reversed claim's cost sharing back out of the member's (and their family's)
+    /// totals, so the replacement claim meets the deductible as if the original never happened.
+    pub fn reverse(&self, payer_id: &str, member_id: &str, plan_year: i32, share: &CostShare) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if let Some(individual) = state.individuals.get_mut(&(payer_id.to_string(), member_id.to_string(), plan_year)) {
+            individual.unrecord(share);
+        }
+        if let Some(family_id) = state.family_of.get(&(payer_id.to_string(), member_id.to_string()))
+            && let Some(family) = state.families.get_mut(&(payer_id.to_string(), family_id.clone(), plan_year))
+        {
+            family.unrecord(share);
+        }
+    }
+
+    /// Puts a reversed claim's cost sharing back, when the claim replacing it couldn't be
+    /// adjudicated after all.
+    pub fn restore(&self, payer_id: &str, member_id: &str, plan_year: i32, share: &CostShare) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if let Some(individual) = state.individuals.get_mut(&(payer_id.to_string(), member_id.to_string(), plan_year)) {
+            individual.record(share);
+        }
+        if let Some(family_id) = state.family_of.get(&(payer_id.to_string(), member_id.to_string()))
+            && let Some(family) = state.families.get_mut(&(payer_id.to_string(), family_id.clone(), plan_year))
+        {
+            family.record(share);
+        }
+    }
+
+    pub fn individual(&self, payer_id: &str, member_id: &str, plan_year: i32) -> Option<Accumulator> {
+        let state = self.state.lock().unwrap();
+        state.individuals.get(&(payer_id.to_string(), member_id.to_string(), plan_year)).copied()
+    }
+
+    pub fn family(&self, payer_id: &str, family_id: &str, plan_year: i32) -> Option<Accumulator> {
+        let state = self.state.lock().unwrap();
+        state.families.get(&(payer_id.to_string(), family_id.to_string(), plan_year)).copied()
+    }
+}
@@ -0,0 +1,157 @@
+use crate::accumulators::{accumulator_member, AccumulatorStore, CostShare};
+use crate::types::{Remittance, ServiceLine};
+use crate::InsuranceError;
+use intake::{ClaimFrequency, PayerClaim};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// One adjudicated claim, kept so a later replacement or void can reverse it.
+struct Adjudication {
+    member_id: String,
+    /// Whose accumulators its cost sharing went to (see `accumulator_member`)
+    person: String,
+    plan_year: i32,
+    remittance: Remittance,
+    /// The claim that reversed it, and the reversal that produced
+    reversed_by: Option<(String, Remittance)>,
+}
+
+type HistoryKey = (String, String);
+
+#[derive(Default)]
+struct HistoryState {
+    /// (payer, remittance id) -> the adjudication behind it
+    adjudications: HashMap<HistoryKey, Adjudication>,
+    /// (payer, claim id) -> the remittance id of that claim's latest adjudication
+    latest: HashMap<HistoryKey, String>,
+}
+
+/// Every claim the payers have adjudicated, shared by every worker like the accumulators.
+///
+/// Replacement (7) and void (8) claims name the claim they correct by its payer claim control
+/// number, which is the `remittance_id` the payer assigned it. The original `claim_id` is
+/// accepted as well, matching that claim's latest adjudication.
+#[derive(Default)]
+pub struct ClaimHistory {
+    state: Mutex<HistoryState>,
+}
+
+impl ClaimHistory {
+    pub fn new() -> ClaimHistory {
+        ClaimHistory::default()
+    }
+
+    /// Remembers an adjudicated claim so it can be reversed later.
+    pub fn record(&self, payer_id: &str, plan_year: i32, claim: &PayerClaim, remittance: &Remittance) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .latest
+            .insert((payer_id.to_string(), claim.claim_id.clone()), remittance.remittance_id.clone());
+        state.adjudications.insert(
+            (payer_id.to_string(), remittance.remittance_id.clone()),
+            Adjudication {
+                member_id: claim.insurance.patient_member_id.clone(),
+                person: accumulator_member(claim),
+                plan_year,
+                remittance: remittance.clone(),
+                reversed_by: None,
+            },
+        );
+    }
+
+    /// Reverses the claim that `claim` replaces or voids. The original's cost sharing is taken
+    /// back out of `accumulators`, and the returned remittance negates the original's amounts.
+    ///
+    /// Reversing again for the same claim (a replacement being retried) returns the same
+    /// reversal without touching the accumulators twice.
+    pub fn reverse(&self, payer_id: &str, claim: &PayerClaim, accumulators: &AccumulatorStore) -> Result<Remittance, InsuranceError> {
+        let invalid = |message: String| InsuranceError::Reversal { claim_id: claim.claim_id.clone(), message };
+        if claim.frequency_code == ClaimFrequency::Original {
+            return Err(invalid("an original claim has nothing to reverse".to_string()));
+        }
+        let control_number = claim
+            .payer_claim_control_number
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .ok_or_else(|| invalid(format!("frequency code {} claims need a payer claim control number", claim.frequency_code.code())))?;
+
+        let mut state = self.state.lock().unwrap();
+        let original = state
+            .original_of(payer_id, control_number)
+            .ok_or_else(|| invalid(format!("{} has no adjudicated claim with control number {}", payer_id, control_number)))?;
+
+        if let Some((reversed_by, reversal)) = &original.reversed_by {
+            return if *reversed_by == claim.claim_id {
+                Ok(reversal.clone())
+            } else {
+                Err(invalid(format!("claim {} was already reversed by claim {}", original.remittance.claim_id, reversed_by)))
+            };
+        }
+        if original.member_id != claim.insurance.patient_member_id {
+            return Err(invalid(format!("claim {} belongs to a different member", original.remittance.claim_id)));
+        }
+
+        let reversal = Remittance {
+            service_lines: original.remittance.service_lines.iter().map(ServiceLine::reversed).collect(),
+            reversal: true,
+            ..original.remittance.clone()
+        };
+        // nothing is taken back unless the reversal balances
+        reversal.check_balance()?;
+
+        accumulators.reverse(payer_id, &original.person, original.plan_year, &original.cost_share());
+        original.reversed_by = Some((claim.claim_id.clone(), reversal.clone()));
+        Ok(reversal)
+    }
+
+    /// Undoes `reverse` for a replacement the payer then couldn't adjudicate: the claim it replaces
+    /// stands again with its cost sharing back in `accumulators`, so nothing was reversed that the
+    /// submitter never got a remittance for. A retried replacement reverses it afresh.
+    pub fn restore(&self, payer_id: &str, claim: &PayerClaim, accumulators: &AccumulatorStore) {
+        let Some(control_number) = claim.payer_claim_control_number.as_deref().map(str::trim) else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        // an earlier attempt at the replacement that was adjudicated keeps its reversal
+        if state.latest.contains_key(&(payer_id.to_string(), claim.claim_id.clone())) {
+            return;
+        }
+        if let Some(original) = state.original_of(payer_id, control_number)
+            && original.reversed_by.as_ref().is_some_and(|(reversed_by, _)| *reversed_by == claim.claim_id)
+        {
+            accumulators.restore(payer_id, &original.person, original.plan_year, &original.cost_share());
+            original.reversed_by = None;
+        }
+    }
+}
+
+impl HistoryState {
+    // The adjudication a control number names: a remittance id, or a claim id standing for that
+    // claim's latest adjudication
+    fn original_of(&mut self, payer_id: &str, control_number: &str) -> Option<&mut Adjudication> {
+        let key = (payer_id.to_string(), control_number.to_string());
+        let remittance_id = match self.latest.get(&key) {
+            _ if self.adjudications.contains_key(&key) => control_number.to_string(),
+            Some(remittance_id) => remittance_id.clone(),
+            None => return None,
+        };
+        let original = self
+            .adjudications
+            .get_mut(&(payer_id.to_string(), remittance_id))
+            .expect("latest only points at recorded adjudications");
+        Some(original)
+    }
+}
+
+impl Adjudication {
+    fn cost_share(&self) -> CostShare {
+        let lines = &self.remittance.service_lines;
+        CostShare {
+            deductible: lines.iter().map(|l| l.deductible_amount).sum(),
+            copay: lines.iter().map(|l| l.copay_amount).sum(),
+            coinsurance: lines.iter().map(|l| l.coinsurance_amount).sum(),
+            payer_paid: lines.iter().map(|l| l.payer_paid_amount).sum(),
+        }
+    }
+}
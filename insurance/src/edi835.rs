@@ -0,0 +1,309 @@
+use crate::InsuranceError;
+use crate::types::{Adjustment, GroupCode, Remittance, ServiceLine};
+use intake::Money;
+use intake::x12::{format_amount, tokenize, EnvelopeEvent, Envelopes, InterchangeHeader, InterchangeWriter, Segment};
+
+const VERSION: &str = "005010X221A1";
+
+// the simulated payers have no federal tax id, so every TRN carries the same originator
+const ORIGINATOR_ID: &str = "1999999999";
+
+// payments without a CUR segment are in US dollars
+const DEFAULT_CURRENCY: &str = "USD";
+
+/// Serializes remittances into an X12 835 (005010X221A1) interchange.
+///
+/// Each payer/payee pair becomes its own ST/SE transaction (one payment) per currency, with a CLP
+/// loop per remittance and an SVC loop per service line. Payments in anything but US dollars
+/// carry a CUR segment. Each line's adjustments become CAS segments
+/// (one per group code) and its remark codes become LQ segments.
+pub fn write_835(remittances: &[Remittance], header: &InterchangeHeader) -> String {
+    let mut writer = InterchangeWriter::new(header, "HP", VERSION);
+    let date = header.created.format("%Y%m%d").to_string();
+
+    let mut payments: Vec<(&str, &str, &str, Vec<&Remittance>)> = Vec::new();
+    for remittance in remittances {
+        match payments.iter_mut().find(|(payer, payee, currency, _)| {
+            *payer == remittance.payer_id && *payee == remittance.payee_npi && *currency == remittance.currency
+        }) {
+            Some((_, _, _, claims)) => claims.push(remittance),
+            None => payments.push((&remittance.payer_id, &remittance.payee_npi, &remittance.currency, vec![remittance])),
+        }
+    }
+
+    for (index, (payer_id, payee_npi, currency, claims)) in payments.iter().enumerate() {
+        writer.begin_transaction("835");
+
+        let total_paid: Money = claims.iter().flat_map(|r| &r.service_lines).map(|l| l.payer_paid_amount).sum();
+        // BPR02 is never negative: a payment holding only reversals is a debit of what they take back
+        if total_paid > Money::ZERO {
+            writer.segment("BPR", &["I", &format_amount(total_paid), "C", "CHK", "", "", "", "", "", "", "", "", "", "", "", &date]);
+        } else if total_paid < Money::ZERO {
+            writer.segment("BPR", &["H", &format_amount(-total_paid), "D", "NON", "", "", "", "", "", "", "", "", "", "", "", &date]);
+        } else {
+            writer.segment("BPR", &["H", &format_amount(total_paid), "C", "NON", "", "", "", "", "", "", "", "", "", "", "", &date]);
+        }
+        let trace = format!("{}{:04}", header.control_number, index + 1);
+        writer.segment("TRN", &["1", &trace, ORIGINATOR_ID]);
+        if *currency != DEFAULT_CURRENCY {
+            writer.segment("CUR", &["PR", currency]);
+        }
+        writer.segment("DTM", &["405", &date]);
+
+        writer.segment("N1", &["PR", payer_id]);
+        if payee_npi.is_empty() {
+            writer.segment("N1", &["PE", "UNKNOWN PAYEE"]);
+        } else {
+            writer.segment("N1", &["PE", payee_npi, "XX", payee_npi]);
+        }
+
+        writer.segment("LX", &["1"]);
+        for remittance in claims {
+            write_claim(&mut writer, remittance);
+        }
+        writer.end_transaction();
+    }
+
+    writer.finish()
+}
+
+fn write_claim(writer: &mut InterchangeWriter, remittance: &Remittance) {
+    let lines = &remittance.service_lines;
+    let billed: Money = lines.iter().map(|l| l.billed_amount).sum();
+    let paid: Money = lines.iter().map(|l| l.payer_paid_amount).sum();
+    let patient_responsibility: Money = lines
+        .iter()
+        .map(|l| l.deductible_amount + l.coinsurance_amount + l.copay_amount)
+        .sum();
+
+    // 22 = reversal of a previous payment; 4 = denied: nothing paid and nothing passed on to the
+    // patient; 1-3 = processed as primary to tertiary, or 19-21 when also forwarded to the next payer
+    let status = if remittance.reversal {
+        "22".to_string()
+    } else if billed > Money::ZERO && paid.is_zero() && patient_responsibility.is_zero() {
+        "4".to_string()
+    } else if remittance.crossover_payer_id.is_some() {
+        (remittance.payer_sequence + 18).to_string()
+    } else {
+        remittance.payer_sequence.to_string()
+    };
+    let filing_indicator = if remittance.payer_id == "Medicare" { "MB" } else { "CI" };
+    writer.segment("CLP", &[
+        &remittance.claim_id,
+        &status,
+        &format_amount(billed),
+        &format_amount(paid),
+        &format_amount(patient_responsibility),
+        filing_indicator,
+        &remittance.remittance_id,
+    ]);
+
+    let member_id = remittance
+        .patient_id
+        .strip_prefix(&format!("{}-", remittance.payer_id))
+        .unwrap_or(&remittance.patient_id);
+    writer.segment("NM1", &["QC", "1", "", "", "", "", "", "MI", member_id]);
+    if let Some(crossover_payer_id) = &remittance.crossover_payer_id {
+        writer.segment("NM1", &["TT", "2", crossover_payer_id]);
+    }
+
+    if let Some(received) = chrono::DateTime::from_timestamp_millis(remittance.initial_claim_ts) {
+        writer.segment("DTM", &["050", &received.format("%Y%m%d").to_string()]);
+    }
+
+    for line in lines {
+        writer.segment("SVC", &[
+            &format!("HC:{}", line.procedure_code),
+            &format_amount(line.billed_amount),
+            &format_amount(line.payer_paid_amount),
+        ]);
+
+        let mut groups: Vec<GroupCode> = Vec::new();
+        for adjustment in &line.adjustments {
+            if !groups.contains(&adjustment.group_code) {
+                groups.push(adjustment.group_code);
+            }
+        }
+        for group in groups {
+            let triplets: Vec<String> = line
+                .adjustments
+                .iter()
+                .filter(|a| a.group_code == group)
+                .flat_map(|a| [a.reason_code.clone(), format_amount(a.amount), String::new()])
+                .collect();
+            // a CAS segment holds at most six reason/amount/quantity triplets
+            for chunk in triplets.chunks(18) {
+                let mut elements = vec![group.code()];
+                elements.extend(chunk.iter().map(|t| t.as_str()));
+                writer.segment("CAS", &elements);
+            }
+        }
+
+        writer.segment("REF", &["6R", &line.service_line_id]);
+        for remark in line.remark_codes.iter().flatten() {
+            writer.segment("LQ", &["HE", remark]);
+        }
+    }
+}
+
+/// Parses an X12 835 interchange back into remittances, one per CLP claim loop.
+///
+/// PR-1, PR-2 and PR-3 adjustments map to deductible, coinsurance and copay; every other
+/// adjustment is treated as not allowed. Claims whose lines don't balance are rejected.
+pub fn parse_835(input: &str) -> Result<Vec<Remittance>, InsuranceError> {
+    let remittances = parse_segments(input)
+        .map_err(|message| InsuranceError::Remittance { message: format!("Failed to parse 835: {}", message) })?;
+    for remittance in &remittances {
+        remittance.check_balance()?;
+    }
+    Ok(remittances)
+}
+
+fn parse_segments(input: &str) -> Result<Vec<Remittance>, String> {
+    let (_, segments) = tokenize(input)?;
+    let mut envelopes = Envelopes::new("HP", "835");
+    let mut remittances = Vec::new();
+    let mut payer_id: Option<String> = None;
+    let mut payee_npi = String::new();
+    let mut currency = DEFAULT_CURRENCY.to_string();
+    let mut current: Option<Remittance> = None;
+
+    for seg in &segments {
+        match envelopes.accept(seg)? {
+            EnvelopeEvent::TransactionStart => {
+                payer_id = None;
+                payee_npi.clear();
+                currency = DEFAULT_CURRENCY.to_string();
+                continue;
+            }
+            EnvelopeEvent::TransactionEnd => {
+                remittances.extend(current.take());
+                continue;
+            }
+            EnvelopeEvent::Envelope => continue,
+            EnvelopeEvent::Body => {}
+        }
+
+        match seg.id.as_str() {
+            "CUR" if seg.element(1) == "PR" => currency = seg.element(2).to_string(),
+            "N1" => match seg.element(1) {
+                "PR" => payer_id = Some(seg.element(2).to_string()),
+                "PE" if seg.element(3) == "XX" => payee_npi = seg.element(4).to_string(),
+                _ => {}
+            },
+            "CLP" => {
+                remittances.extend(current.take());
+                let payer_id = payer_id.clone().ok_or_else(|| seg.error("CLP before the N1*PR payer loop"))?;
+                let claim_id = seg.element(1).to_string();
+                let remittance_id = match seg.element(7) {
+                    "" => claim_id.clone(),
+                    id => id.to_string(),
+                };
+                current = Some(Remittance {
+                    remittance_id,
+                    claim_id,
+                    patient_id: payer_id.clone(),
+                    payer_id,
+                    payee_npi: payee_npi.clone(),
+                    service_lines: Vec::new(),
+                    initial_claim_ts: 0,
+                    reversal: seg.element(2) == "22",
+                    payer_sequence: match seg.element(2) {
+                        "2" | "20" => 2,
+                        "3" | "21" => 3,
+                        _ => 1,
+                    },
+                    crossover_payer_id: None,
+                    currency: currency.clone(),
+                    currency_conversion: None,
+                });
+            }
+            "NM1" if seg.element(1) == "QC" => {
+                let remittance = claim_mut(&mut current, seg)?;
+                remittance.patient_id = format!("{}-{}", remittance.payer_id, seg.element(9));
+            }
+            "NM1" if seg.element(1) == "TT" => {
+                claim_mut(&mut current, seg)?.crossover_payer_id = non_empty(seg.element(3));
+            }
+            "DTM" if seg.element(1) == "050" => {
+                let received = chrono::NaiveDate::parse_from_str(seg.element(2), "%Y%m%d")
+                    .map_err(|_| seg.error(format!("invalid date {}", seg.element(2))))?;
+                claim_mut(&mut current, seg)?.initial_claim_ts = received.and_time(chrono::NaiveTime::MIN).and_utc().timestamp_millis();
+            }
+            "SVC" => {
+                let remittance = claim_mut(&mut current, seg)?;
+                let procedure = seg.components(1);
+                if procedure.len() < 2 {
+                    return Err(seg.error("SVC01 must be a qualifier:procedure composite"));
+                }
+                let line_number = remittance.service_lines.len() + 1;
+                remittance.service_lines.push(ServiceLine {
+                    service_line_id: format!("{}-{}", remittance.claim_id, line_number),
+                    procedure_code: procedure[1].to_string(),
+                    billed_amount: parse_amount(seg, 2)?,
+                    payer_paid_amount: parse_amount(seg, 3)?,
+                    coinsurance_amount: Money::ZERO,
+                    copay_amount: Money::ZERO,
+                    deductible_amount: Money::ZERO,
+                    not_allowed_amount: Money::ZERO,
+                    adjustments: Vec::new(),
+                    remark_codes: None,
+                });
+            }
+            "CAS" => {
+                let line = line_mut(&mut current, seg)?;
+                let group = seg.element(1);
+                let group_code = GroupCode::from_code(group)
+                    .ok_or_else(|| seg.error(format!("unknown claim adjustment group code {}", group)))?;
+                for reason_index in (2..=17).step_by(3) {
+                    let reason = seg.element(reason_index);
+                    if reason.is_empty() {
+                        continue;
+                    }
+                    let amount = parse_amount(seg, reason_index + 1)?;
+                    match (group, reason) {
+                        ("PR", "1") => line.deductible_amount += amount,
+                        ("PR", "2") => line.coinsurance_amount += amount,
+                        ("PR", "3") => line.copay_amount += amount,
+                        _ => line.not_allowed_amount += amount,
+                    }
+                    line.adjustments.push(Adjustment::new(group_code, reason, amount));
+                }
+            }
+            "REF" if seg.element(1) == "6R" => {
+                line_mut(&mut current, seg)?.service_line_id = seg.element(2).to_string();
+            }
+            "LQ" if seg.element(1) == "HE" => {
+                line_mut(&mut current, seg)?
+                    .remark_codes
+                    .get_or_insert_with(Vec::new)
+                    .push(seg.element(2).to_string());
+            }
+            _ => {}
+        }
+    }
+
+    envelopes.finish(segments.last())?;
+    Ok(remittances)
+}
+
+fn claim_mut<'a>(current: &'a mut Option<Remittance>, seg: &Segment) -> Result<&'a mut Remittance, String> {
+    current.as_mut().ok_or_else(|| seg.error("segment appears outside of a CLP claim loop"))
+}
+
+fn line_mut<'a>(current: &'a mut Option<Remittance>, seg: &Segment) -> Result<&'a mut ServiceLine, String> {
+    claim_mut(current, seg)?
+        .service_lines
+        .last_mut()
+        .ok_or_else(|| seg.error("claim-level adjustments are not supported; expected an SVC loop first"))
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn parse_amount(seg: &Segment, index: usize) -> Result<Money, String> {
+    seg.element(index)
+        .parse()
+        .map_err(|e| seg.error(format!("{} in element {:02}", e, index)))
+}
@@ -0,0 +1,244 @@
+use crate::InsuranceError;
+use chrono::NaiveDate;
+use intake::{Gender, Money, Patient, PayerId};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// CARCs for claims whose member isn't covered on the date of service
+pub const CARC_BEFORE_COVERAGE: &str = "26";
+pub const CARC_AFTER_COVERAGE: &str = "27";
+pub const CARC_MEMBER_NOT_FOUND: &str = "31";
+/// The patient's name doesn't match the member id
+pub const CARC_NAME_MISMATCH: &str = "140";
+
+/// One coverage period for a person enrolled under a member id. A subscriber's dependents share
+/// their member id, so the name, date of birth and gender tell them apart; blank ones aren't checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enrollment {
+    pub member_id: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub dob: Option<NaiveDate>,
+    pub gender: Option<Gender>,
+    /// Plan name reported on eligibility responses; empty for the payer's default
+    pub plan: String,
+    pub coverage_start: NaiveDate,
+    /// Last covered day, or `None` while coverage is ongoing
+    pub coverage_end: Option<NaiveDate>,
+}
+
+impl Enrollment {
+    fn matches_name(&self, patient: &Patient) -> bool {
+        let same = |enrolled: &str, claimed: &str| enrolled.is_empty() || enrolled.eq_ignore_ascii_case(claimed.trim());
+        same(&self.first_name, &patient.first_name) && same(&self.last_name, &patient.last_name)
+    }
+
+    fn matches_demographics(&self, patient: &Patient) -> bool {
+        let dob_matches = self.dob.is_none_or(|dob| dob == patient.dob);
+        dob_matches && self.gender.is_none_or(|gender| gender == patient.gender)
+    }
+}
+
+/// Whether a member is covered on a given day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageStatus {
+    Active,
+    /// The member's coverage starts after the date of service
+    NotYetEffective,
+    /// The member's coverage ended before the date of service
+    Terminated,
+    /// The payer has no member with that id
+    NotFound,
+    /// Nobody enrolled under the member id has the patient's name
+    NameMismatch,
+    /// The patient's date of birth or gender doesn't match the enrolled person with their name
+    DemographicsMismatch,
+}
+
+impl CoverageStatus {
+    pub fn is_active(&self) -> bool {
+        *self == CoverageStatus::Active
+    }
+
+    /// The CARC a claim line is denied with when the member isn't covered.
+    pub fn reason_code(&self) -> Option<&'static str> {
+        match self {
+            CoverageStatus::Active => None,
+            CoverageStatus::NotYetEffective => Some(CARC_BEFORE_COVERAGE),
+            CoverageStatus::Terminated => Some(CARC_AFTER_COVERAGE),
+            CoverageStatus::NotFound | CoverageStatus::DemographicsMismatch => Some(CARC_MEMBER_NOT_FOUND),
+            CoverageStatus::NameMismatch => Some(CARC_NAME_MISMATCH),
+        }
+    }
+}
+
+/// Every payer's enrolled members, shared by every payer like the accumulators.
+///
+/// Payers without any rows aren't checked: all of their members count as active, which is how
+/// the simulators behave when no roster is loaded. Once a payer has rows, members it doesn't list
+/// are `NotFound`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemberRoster {
+    /// (payer name, member id) -> the member's coverage periods
+    enrollments: HashMap<(String, String), Vec<Enrollment>>,
+    payers: HashSet<String>,
+}
+
+impl MemberRoster {
+    pub fn new() -> MemberRoster {
+        MemberRoster::default()
+    }
+
+    /// Enrolls a member with the payer going by `payer_name` on remittances.
+    pub fn insert(&mut self, payer_name: &str, enrollment: Enrollment) {
+        self.payers.insert(payer_name.to_string());
+        self.enrollments.entry((payer_name.to_string(), enrollment.member_id.clone())).or_default().push(enrollment);
+    }
+
+    /// Parses `payer_id,member_id,first_name,last_name,dob,gender,plan,coverage_start,coverage_end`
+    /// rows, with dates as `YYYY-MM-DD`, gender as `m` or `f` and an empty `coverage_end` for
+    /// ongoing coverage. Payer ids are the ids claims are billed to (`medicare`,
+    /// `united_health_group`, `anthem` or any other payer's id). The header row is required;
+    /// blank lines and lines starting with `#` are skipped.
+    pub fn from_csv(contents: &str) -> Result<MemberRoster, InsuranceError> {
+        let header = "payer_id,member_id,first_name,last_name,dob,gender,plan,coverage_start,coverage_end";
+        let mut roster = MemberRoster::new();
+        let mut rows = contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        match rows.next() {
+            Some((_, first)) if first == header => {}
+            Some((line_number, _)) => {
+                return Err(InsuranceError::roster(format!("Line {}: expected header {}", line_number, header)));
+            }
+            None => return Err(InsuranceError::roster("Member roster is empty")),
+        }
+
+        for (line_number, row) in rows {
+            let fields: Vec<&str> = row.split(',').map(|f| f.trim()).collect();
+            if fields.len() != 9 || fields[0].is_empty() || fields[1].is_empty() {
+                return Err(InsuranceError::roster(format!("Line {}: expected 9 fields", line_number)));
+            }
+            let date = |field: &str| {
+                NaiveDate::parse_from_str(field, "%Y-%m-%d")
+                    .map_err(|_| InsuranceError::roster(format!("Line {}: invalid date {} (expected YYYY-MM-DD)", line_number, field)))
+            };
+            let optional_date = |field: &str| if field.is_empty() { Ok(None) } else { date(field).map(Some) };
+            let gender = match fields[5].to_ascii_lowercase().as_str() {
+                "" => None,
+                "m" => Some(Gender::Male),
+                "f" => Some(Gender::Female),
+                other => return Err(InsuranceError::roster(format!("Line {}: invalid gender {} (expected m or f)", line_number, other))),
+            };
+            let coverage_start = date(fields[7])?;
+            let coverage_end = optional_date(fields[8])?;
+            if coverage_end.is_some_and(|end| end < coverage_start) {
+                return Err(InsuranceError::roster(format!("Line {}: coverage ends before it starts", line_number)));
+            }
+
+            let payer_name = PayerId::new(&fields[0].to_ascii_lowercase()).name().to_string();
+            roster.insert(
+                &payer_name,
+                Enrollment {
+                    member_id: fields[1].to_string(),
+                    first_name: fields[2].to_string(),
+                    last_name: fields[3].to_string(),
+                    dob: optional_date(fields[4])?,
+                    gender,
+                    plan: fields[6].to_string(),
+                    coverage_start,
+                    coverage_end,
+                },
+            );
+        }
+        Ok(roster)
+    }
+
+    pub fn load(path: &Path) -> Result<MemberRoster, InsuranceError> {
+        let with_path = |message: String| InsuranceError::Roster { path: Some(path.display().to_string()), message };
+        let contents = std::fs::read_to_string(path).map_err(|e| with_path(format!("failed to read: {}", e)))?;
+        MemberRoster::from_csv(&contents).map_err(|e| with_path(e.to_string()))
+    }
+
+    /// The member's coverage on `date`, and the enrollment behind it when they're active.
+    pub fn coverage(&self, payer_name: &str, member_id: &str, date: NaiveDate) -> (CoverageStatus, Option<&Enrollment>) {
+        if !self.payers.contains(payer_name) {
+            return (CoverageStatus::Active, None);
+        }
+        match self.enrollments.get(&(payer_name.to_string(), member_id.to_string())) {
+            Some(enrollments) => coverage_on(enrollments.iter(), date),
+            None => (CoverageStatus::NotFound, None),
+        }
+    }
+
+    /// The patient's coverage on `date`: like `coverage`, but only counting the people enrolled
+    /// under the member id whose name, date of birth and gender match the patient's.
+    pub fn verify(&self, payer_name: &str, member_id: &str, patient: &Patient, date: NaiveDate) -> CoverageStatus {
+        if !self.payers.contains(payer_name) {
+            return CoverageStatus::Active;
+        }
+        let Some(enrollments) = self.enrollments.get(&(payer_name.to_string(), member_id.to_string())) else {
+            return CoverageStatus::NotFound;
+        };
+        let named: Vec<&Enrollment> = enrollments.iter().filter(|e| e.matches_name(patient)).collect();
+        if named.is_empty() {
+            return CoverageStatus::NameMismatch;
+        }
+        let people: Vec<&Enrollment> = named.into_iter().filter(|e| e.matches_demographics(patient)).collect();
+        if people.is_empty() {
+            return CoverageStatus::DemographicsMismatch;
+        }
+        coverage_on(people.into_iter(), date).0
+    }
+
+    pub fn status(&self, payer_name: &str, member_id: &str, date: NaiveDate) -> CoverageStatus {
+        self.coverage(payer_name, member_id, date).0
+    }
+
+    pub fn len(&self) -> usize {
+        self.enrollments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.enrollments.is_empty()
+    }
+}
+
+fn coverage_on<'a>(enrollments: impl Iterator<Item = &'a Enrollment> + Clone, date: NaiveDate) -> (CoverageStatus, Option<&'a Enrollment>) {
+    if let Some(enrollment) = enrollments.clone().find(|e| e.coverage_start <= date && e.coverage_end.is_none_or(|end| date <= end)) {
+        return (CoverageStatus::Active, Some(enrollment));
+    }
+    // between or after coverage periods counts as terminated
+    if enrollments.into_iter().any(|e| e.coverage_start <= date) {
+        (CoverageStatus::Terminated, None)
+    } else {
+        (CoverageStatus::NotYetEffective, None)
+    }
+}
+
+/// What the member's plan would leave them paying, as of the eligibility request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenefitSummary {
+    pub plan_name: String,
+    pub deductible_remaining: Money,
+    /// `None` for plans without an out-of-pocket maximum
+    pub out_of_pocket_remaining: Option<Money>,
+    /// Copay for an office visit
+    pub office_visit_copay: Money,
+    /// Member's share (0-1) after the deductible and copay
+    pub coinsurance: f64,
+}
+
+/// A payer's answer to an eligibility inquiry (the 271 to a 270).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EligibilityResponse {
+    pub payer_id: String,
+    pub member_id: String,
+    pub date_of_service: NaiveDate,
+    pub status: CoverageStatus,
+    /// Only present when the member is active
+    pub benefits: Option<BenefitSummary>,
+}
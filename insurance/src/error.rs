@@ -0,0 +1,68 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InsuranceError {
+    /// The payer didn't respond; resubmitting the claim later may succeed
+    PayerUnavailable { payer_id: String, claim_id: String },
+    /// A service line couldn't be adjudicated as given
+    InvalidServiceLine { service_line_id: String, field: String, message: String },
+    /// A remittance line's amounts don't add up to its billed amount
+    Unbalanced { service_line_id: String, message: String },
+    /// An 835 remittance file couldn't be parsed
+    Remittance { message: String },
+    /// A fee schedule couldn't be loaded
+    FeeSchedule { path: Option<String>, message: String },
+    /// A coverage policy (such as the medical necessity rules) couldn't be loaded
+    Policy { path: Option<String>, message: String },
+    /// A member roster couldn't be loaded
+    Roster { path: Option<String>, message: String },
+    /// Plan settings (such as the plan year start) are invalid
+    Plan { field: String, message: String },
+    /// A replacement or void claim doesn't match a claim the payer can reverse
+    Reversal { claim_id: String, message: String },
+}
+
+impl InsuranceError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, InsuranceError::PayerUnavailable { .. })
+    }
+
+    pub(crate) fn fee_schedule(message: impl Into<String>) -> InsuranceError {
+        InsuranceError::FeeSchedule { path: None, message: message.into() }
+    }
+
+    pub(crate) fn policy(message: impl Into<String>) -> InsuranceError {
+        InsuranceError::Policy { path: None, message: message.into() }
+    }
+
+    pub(crate) fn roster(message: impl Into<String>) -> InsuranceError {
+        InsuranceError::Roster { path: None, message: message.into() }
+    }
+}
+
+impl fmt::Display for InsuranceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsuranceError::PayerUnavailable { payer_id, claim_id } => {
+                write!(f, "{} is unavailable; claim {} was not adjudicated", payer_id, claim_id)
+            }
+            InsuranceError::InvalidServiceLine { service_line_id, field, message } => {
+                write!(f, "Service line {} {}: {}", service_line_id, field, message)
+            }
+            InsuranceError::Unbalanced { service_line_id, message } => {
+                write!(f, "Service line {} doesn't balance: {}", service_line_id, message)
+            }
+            InsuranceError::Remittance { message } => write!(f, "{}", message),
+            InsuranceError::FeeSchedule { path: Some(path), message } => write!(f, "Fee schedule {}: {}", path, message),
+            InsuranceError::FeeSchedule { path: None, message } => write!(f, "{}", message),
+            InsuranceError::Policy { path: Some(path), message } => write!(f, "Policy {}: {}", path, message),
+            InsuranceError::Policy { path: None, message } => write!(f, "{}", message),
+            InsuranceError::Roster { path: Some(path), message } => write!(f, "Member roster {}: {}", path, message),
+            InsuranceError::Roster { path: None, message } => write!(f, "{}", message),
+            InsuranceError::Plan { message, .. } => write!(f, "{}", message),
+            InsuranceError::Reversal { claim_id, message } => write!(f, "Claim {} can't be reversed: {}", claim_id, message),
+        }
+    }
+}
+
+impl std::error::Error for InsuranceError {}
@@ -0,0 +1,179 @@
+use crate::InsuranceError;
+use intake::{Money, ServiceLine};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One priced procedure. `modifier`, `place_of_service` and `facility` are optional qualifiers;
+/// an entry with any of them set only applies to lines that match it. `facility` selects the
+/// facility or non-facility rate for every place of service in that setting.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FeeScheduleEntry {
+    pub procedure_code: String,
+    #[serde(default)]
+    pub modifier: Option<String>,
+    #[serde(default)]
+    pub place_of_service: Option<i32>,
+    #[serde(default)]
+    pub facility: Option<bool>,
+    pub allowed_amount: Money,
+}
+
+/// Per-unit allowed amounts keyed by CPT/HCPCS code.
+///
+/// Codes that are not on the schedule are allowed at `fallback_percent_of_billed` of the billed
+/// amount. The allowed amount never exceeds what was billed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeSchedule {
+    entries: HashMap<String, Vec<FeeScheduleEntry>>,
+    pub fallback_percent_of_billed: f64,
+}
+
+impl FeeSchedule {
+    /// An empty schedule; `fallback_percent_of_billed` must be between 0 and 1.
+    pub fn new(fallback_percent_of_billed: f64) -> Result<FeeSchedule, InsuranceError> {
+        if !(0.0..=1.0).contains(&fallback_percent_of_billed) {
+            return Err(InsuranceError::fee_schedule(format!(
+                "fallback_percent_of_billed must be between 0 and 1, got {}",
+                fallback_percent_of_billed
+            )));
+        }
+        Ok(FeeSchedule { entries: HashMap::new(), fallback_percent_of_billed })
+    }
+
+    /// Adds an entry; its allowed amount must not be negative.
+    pub fn insert(&mut self, entry: FeeScheduleEntry) -> Result<(), InsuranceError> {
+        if entry.allowed_amount < Money::ZERO {
+            return Err(InsuranceError::fee_schedule(format!(
+                "allowed_amount {} for {} must not be negative",
+                entry.allowed_amount, entry.procedure_code
+            )));
+        }
+        self.entries.entry(entry.procedure_code.clone()).or_default().push(entry);
+        Ok(())
+    }
+
+    /// Parses `procedure_code,modifier,place_of_service,allowed_amount` rows, where
+    /// `place_of_service` is a code, `facility`, `non_facility` or blank. The header row is
+    /// required; blank lines and lines starting with `#` are skipped.
+    pub fn from_csv(contents: &str, fallback_percent_of_billed: f64) -> Result<FeeSchedule, InsuranceError> {
+        let mut schedule = FeeSchedule::new(fallback_percent_of_billed)?;
+        let mut rows = contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        match rows.next() {
+            Some((_, "procedure_code,modifier,place_of_service,allowed_amount")) => {}
+            Some((line_number, _)) => {
+                return Err(InsuranceError::fee_schedule(format!(
+                    "Line {}: expected header procedure_code,modifier,place_of_service,allowed_amount",
+                    line_number
+                )));
+            }
+            None => return Err(InsuranceError::fee_schedule("Fee schedule is empty")),
+        }
+
+        for (line_number, row) in rows {
+            let fields: Vec<&str> = row.split(',').map(|f| f.trim()).collect();
+            if fields.len() != 4 {
+                return Err(InsuranceError::fee_schedule(format!("Line {}: expected 4 fields, found {}", line_number, fields.len())));
+            }
+            if fields[0].is_empty() {
+                return Err(InsuranceError::fee_schedule(format!("Line {}: procedure_code is required", line_number)));
+            }
+            let (place_of_service, facility) = match fields[2] {
+                "" => (None, None),
+                "facility" => (None, Some(true)),
+                "non_facility" => (None, Some(false)),
+                pos => (
+                    Some(pos.parse().map_err(|_| {
+                        InsuranceError::fee_schedule(format!("Line {}: invalid place_of_service {}", line_number, pos))
+                    })?),
+                    None,
+                ),
+            };
+            let allowed_amount = fields[3]
+                .parse()
+                .map_err(|_| InsuranceError::fee_schedule(format!("Line {}: invalid allowed_amount {}", line_number, fields[3])))?;
+
+            schedule
+                .insert(FeeScheduleEntry {
+                    procedure_code: fields[0].to_string(),
+                    modifier: (!fields[1].is_empty()).then(|| fields[1].to_string()),
+                    place_of_service,
+                    facility,
+                    allowed_amount,
+                })
+                .map_err(|e| InsuranceError::fee_schedule(format!("Line {}: {}", line_number, e)))?;
+        }
+
+        Ok(schedule)
+    }
+
+    /// Parses a JSON array of entries.
+    pub fn from_json(contents: &str, fallback_percent_of_billed: f64) -> Result<FeeSchedule, InsuranceError> {
+        let entries: Vec<FeeScheduleEntry> =
+            serde_json::from_str(contents).map_err(|e| InsuranceError::fee_schedule(format!("Invalid fee schedule JSON: {}", e)))?;
+
+        let mut schedule = FeeSchedule::new(fallback_percent_of_billed)?;
+        for entry in entries {
+            schedule.insert(entry)?;
+        }
+        Ok(schedule)
+    }
+
+    /// Loads a `.csv` or `.json` fee schedule from disk.
+    pub fn load(path: &Path, fallback_percent_of_billed: f64) -> Result<FeeSchedule, InsuranceError> {
+        let with_path = |message: String| InsuranceError::FeeSchedule { path: Some(path.display().to_string()), message };
+        let contents = std::fs::read_to_string(path).map_err(|e| with_path(format!("failed to read: {}", e)))?;
+        let schedule = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => FeeSchedule::from_csv(&contents, fallback_percent_of_billed),
+            Some("json") => FeeSchedule::from_json(&contents, fallback_percent_of_billed),
+            _ => return Err(with_path("unsupported format (expected .csv or .json)".to_string())),
+        };
+        schedule.map_err(|e| with_path(e.to_string()))
+    }
+
+    /// The most specific matching entry: a modifier match beats a place of service match, which
+    /// beats a facility/non-facility match, which beats the bare code. Facility entries never
+    /// match an unassigned place of service.
+    pub fn lookup(&self, procedure_code: &str, modifiers: &[String], place_of_service: i32) -> Option<&FeeScheduleEntry> {
+        let facility = intake::place_of_service(place_of_service).map(|place| place.facility);
+        self.entries
+            .get(procedure_code)?
+            .iter()
+            .filter(|entry| entry.modifier.as_ref().is_none_or(|m| modifiers.contains(m)))
+            .filter(|entry| entry.place_of_service.is_none_or(|pos| pos == place_of_service))
+            .filter(|entry| entry.facility.is_none_or(|f| Some(f) == facility))
+            .max_by_key(|entry| (entry.modifier.is_some(), entry.place_of_service.is_some(), entry.facility.is_some()))
+    }
+
+    /// The allowed amount for a whole line (all units), capped at the billed amount. Unlisted
+    /// codes are rounded to the cent.
+    pub fn allowed_amount(&self, line: &ServiceLine, place_of_service: i32) -> Money {
+        let billed_amount = line.unit_charge_amount * line.units;
+        let modifiers = line.modifiers.as_deref().unwrap_or(&[]);
+
+        match self.lookup(&line.procedure_code, modifiers, place_of_service) {
+            Some(entry) => (entry.allowed_amount * line.units).min(billed_amount),
+            None => billed_amount.times(self.fallback_percent_of_billed).min(billed_amount),
+        }
+    }
+
+    /// The bundled schedule for Medicare, with unlisted codes allowed at 60% of billed.
+    pub fn medicare() -> FeeSchedule {
+        FeeSchedule::from_csv(include_str!("../fee_schedules/medicare.csv"), 0.6).unwrap()
+    }
+
+    /// The bundled schedule for UnitedHealth, with unlisted codes allowed at 75% of billed.
+    pub fn united_health_group() -> FeeSchedule {
+        FeeSchedule::from_csv(include_str!("../fee_schedules/united_health_group.csv"), 0.75).unwrap()
+    }
+
+    /// The bundled schedule for Anthem, with unlisted codes allowed at 70% of billed.
+    pub fn anthem() -> FeeSchedule {
+        FeeSchedule::from_csv(include_str!("../fee_schedules/anthem.csv"), 0.7).unwrap()
+    }
+}
@@ -1,209 +1,575 @@
 pub mod types;
+pub mod edi835;
+pub mod accumulators;
+pub mod claim_history;
+pub mod eligibility;
+pub mod fee_schedule;
+pub mod medical_necessity;
+pub mod plans;
+pub mod rng;
+pub mod error;
 
-pub use types::{Remittance, ServiceLine};
-use intake::{PayerClaim, PayerId};
-use uuid::Uuid;
-use std::thread;
+pub use types::{Adjustment, CurrencyConversion, GroupCode, Remittance, ServiceLine, CARC_DIAGNOSIS_INCONSISTENT, CARC_NON_COVERED, CARC_PRIOR_PAYER, CARC_TIMELY_FILING};
+pub use edi835::{parse_835, write_835};
+pub use accumulators::{accumulator_member, Accumulator, AccumulatorStore, BenefitLimits};
+pub use claim_history::ClaimHistory;
+pub use eligibility::{BenefitSummary, CoverageStatus, EligibilityResponse, Enrollment, MemberRoster};
+pub use fee_schedule::{FeeSchedule, FeeScheduleEntry};
+pub use medical_necessity::MedicalNecessityPolicy;
+pub use plans::{BenefitPlan, DenialRules, PlanCatalog, ServiceCategory};
+pub use rng::adjudication_rng;
+pub use error::InsuranceError;
+pub use intake::x12::InterchangeHeader;
+use chrono::NaiveDate;
+use intake::{Clock, Money, PayerClaim, PayerId, ServiceLine as IntakeServiceLine, SystemClock};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-pub struct Medicare {
+/// What sets one payer apart from another. Screening, pricing, accumulators and claim history are
+/// the same for every payer and live in `PayerCore`.
+pub trait PayerRules: Send + Sync {
+    /// The member's deductible and out-of-pocket maximum for a plan year. Anything drawn at random
+    /// comes from `rng`, the member's own stream for the year, so it doesn't depend on which of their
+    /// claims arrives first.
+    fn benefit_limits(&self, member_id: &str, rng: &mut impl Rng) -> BenefitLimits;
+
+    /// Whether lines whose diagnoses don't support the procedure are denied (CO-11).
+    fn checks_medical_necessity(&self, _member_id: &str) -> bool {
+        true
+    }
+
+    /// A denial of the payer's own for a line the shared checks let through, as its CARC.
+    fn deny_line(&self, _member_id: &str, _line: &IntakeServiceLine, _rng: &mut impl Rng) -> Option<&str> {
+        None
+    }
+
+    /// The line's copay, and the share of the allowed amount the payer covers after the deductible.
+    fn cost_sharing(&self, member_id: &str, line: &IntakeServiceLine, rng: &mut impl Rng) -> (Money, f64);
+
+    /// N130: consult plan benefit documents, sent whenever member cost sharing was applied
+    fn remark_codes(&self, line: &ServiceLine) -> Vec<String> {
+        if line.deductible_amount + line.copay_amount + line.coinsurance_amount > Money::ZERO {
+            vec!["N130".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// What the member's plan charges, for eligibility responses.
+    fn plan_terms(&self, member_id: &str) -> PlanTerms<'_>;
+}
+
+/// Original Medicare Part B.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MedicareRules;
+
+impl PayerRules for MedicareRules {
+    /// Medicare Part B 2025 deductible: $257 per year; Original Medicare has no out-of-pocket maximum
+    fn benefit_limits(&self, _member_id: &str, _rng: &mut impl Rng) -> BenefitLimits {
+        BenefitLimits {
+            deductible: Money::from_dollars(257.0),
+            out_of_pocket_max: None,
+            family_deductible: None,
+            family_out_of_pocket_max: None,
+        }
+    }
+
+    fn cost_sharing(&self, _member_id: &str, _line: &IntakeServiceLine, _rng: &mut impl Rng) -> (Money, f64) {
+        // Medicare Part B standard: 80% coverage, 20% coinsurance after deductible
+        // Medicare Part B typically doesn't use copays for physician services
+        (Money::ZERO, 0.8)
+    }
+
+    /// MA01: Medicare appeal-rights notice, sent on every line that was not paid in full
+    fn remark_codes(&self, line: &ServiceLine) -> Vec<String> {
+        if line.payer_paid_amount < line.billed_amount {
+            vec!["MA01".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn plan_terms(&self, _member_id: &str) -> PlanTerms<'_> {
+        PlanTerms { plan_name: "Medicare Part B", office_visit_copay: Money::ZERO, coinsurance: 0.2 }
+    }
+}
+
+/// UnitedHealthcare Choice Plus.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnitedHealthGroupRules;
+
+impl PayerRules for UnitedHealthGroupRules {
+    /// UnitedHealth average individual deductible: ~$1,800; out-of-pocket max at the 2025 ACA limit
+    fn benefit_limits(&self, _member_id: &str, _rng: &mut impl Rng) -> BenefitLimits {
+        BenefitLimits {
+            deductible: Money::from_dollars(1800.0),
+            out_of_pocket_max: Some(Money::from_dollars(9200.0)),
+            family_deductible: Some(Money::from_dollars(3600.0)),
+            family_out_of_pocket_max: Some(Money::from_dollars(18400.0)),
+        }
+    }
+
+    fn cost_sharing(&self, _member_id: &str, _line: &IntakeServiceLine, rng: &mut impl Rng) -> (Money, f64) {
+        // UnitedHealth typical copay for routine services: $25-35
+        let copay = Money::from_dollars(25.0 + (rng.random::<f64>() * 10.0)); // $25-35
+
+        // UnitedHealth typical coverage: 75% (between 70-80% range)
+        // Patient coinsurance: 25% (typical private insurance 20-30% range)
+        let coverage_rate = 0.70 + (rng.random::<f64>() * 0.1); // 70-80%
+        (copay, coverage_rate)
+    }
+
+    fn plan_terms(&self, _member_id: &str) -> PlanTerms<'_> {
+        // typical copay and coinsurance, the middle of the ranges claims are adjudicated with
+        PlanTerms { plan_name: "UnitedHealthcare Choice Plus", office_visit_copay: Money::from_dollars(30.0), coinsurance: 0.25 }
+    }
+}
+
+/// Anthem Silver.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnthemRules;
+
+impl PayerRules for AnthemRules {
+    /// Anthem average individual deductible: ~$1,650-2,000, fixed per member once their plan year starts
+    fn benefit_limits(&self, _member_id: &str, rng: &mut impl Rng) -> BenefitLimits {
+        let deductible = Money::from_dollars(1650.0 + (rng.random::<f64>() * 350.0)); // $1,650-2,000
+        BenefitLimits {
+            deductible,
+            out_of_pocket_max: Some(Money::from_dollars(9200.0)),
+            family_deductible: Some(deductible * 2),
+            family_out_of_pocket_max: Some(Money::from_dollars(18400.0)),
+        }
+    }
+
+    fn cost_sharing(&self, _member_id: &str, _line: &IntakeServiceLine, rng: &mut impl Rng) -> (Money, f64) {
+        // Anthem typical copay for routine services: $20-30
+        let copay = Money::from_dollars(20.0 + (rng.random::<f64>() * 10.0)); // $20-30
+
+        // Anthem Silver plan structure: 70% coverage, 30% coinsurance
+        // This is based on typical Anthem Silver plan coinsurance rates
+        (copay, 0.7)
+    }
+
+    fn plan_terms(&self, _member_id: &str) -> PlanTerms<'_> {
+        // typical copay, the middle of the range claims are adjudicated with
+        PlanTerms { plan_name: "Anthem Silver", office_visit_copay: Money::from_dollars(25.0), coinsurance: 0.3 }
+    }
+}
+
+/// Benefits from a `PlanCatalog` rather than built-in rules, so each member is adjudicated under
+/// the plan they're enrolled in.
+pub struct PlanRules {
+    pub plans: Arc<PlanCatalog>,
+}
+
+impl PayerRules for PlanRules {
+    fn benefit_limits(&self, member_id: &str, _rng: &mut impl Rng) -> BenefitLimits {
+        self.plans.plan_for(member_id).limits()
+    }
+
+    fn checks_medical_necessity(&self, member_id: &str) -> bool {
+        self.plans.plan_for(member_id).denials.medical_necessity
+    }
+
+    fn deny_line(&self, member_id: &str, line: &IntakeServiceLine, rng: &mut impl Rng) -> Option<&str> {
+        let plan = self.plans.plan_for(member_id);
+        if !plan.covers(&line.procedure_code) {
+            return Some(CARC_NON_COVERED);
+        }
+        // only drawn when the plan denies at random, so plans without it keep the same stream
+        (plan.denials.rate > 0.0 && rng.random::<f64>() < plan.denials.rate).then_some(plan.denials.reason_code.as_str())
+    }
+
+    fn cost_sharing(&self, member_id: &str, line: &IntakeServiceLine, _rng: &mut impl Rng) -> (Money, f64) {
+        let plan = self.plans.plan_for(member_id);
+        (plan.copay(&line.procedure_code), 1.0 - plan.coinsurance)
+    }
+
+    fn plan_terms(&self, member_id: &str) -> PlanTerms<'_> {
+        let plan = self.plans.plan_for(member_id);
+        PlanTerms { plan_name: &plan.plan_id, office_visit_copay: plan.copay("99213"), coinsurance: plan.coinsurance }
+    }
+}
+
+/// A simulated payer. Everything but its `rules` works the same way for every payer: lines are
+/// screened for timely filing and coverage, priced from the fee schedule and taken against the
+/// member's accumulators, and the claim is recorded so it can be reversed.
+pub struct PayerCore<R> {
+    /// Goes on remittances and keys the member's accumulators and claim history
+    pub name: String,
+    pub rules: R,
     pub min_response_time_secs: u64,
     pub max_response_time_secs: u64,
+    pub accumulators: Arc<AccumulatorStore>,
+    pub claim_history: Arc<ClaimHistory>,
+    /// Members the payer covers; claims for anyone else are denied
+    pub roster: Arc<MemberRoster>,
+    pub fee_schedule: Arc<FeeSchedule>,
+    pub medical_necessity: Arc<MedicalNecessityPolicy>,
+    pub seed: Option<u64>,
+    pub clock: Arc<dyn Clock>,
+    /// Chance (0-1) that the payer is down when a claim arrives
+    pub outage_rate: f64,
+    /// Days after a line's date of service the payer still accepts it
+    pub timely_filing_days: u32,
+    /// Times each claim has been sent to the payer, which keys its outage draws
+    pub attempts: Mutex<HashMap<String, u32>>,
+}
+
+pub type Medicare = PayerCore<MedicareRules>;
+pub type UnitedHealthGroup = PayerCore<UnitedHealthGroupRules>;
+pub type Anthem = PayerCore<AnthemRules>;
+/// A payer whose benefits come from a `PlanCatalog` rather than built-in rules.
+pub type PlanPayer = PayerCore<PlanRules>;
+
+impl Default for Medicare {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Medicare {
     pub fn new() -> Self {
-        Self { min_response_time_secs: 10, max_response_time_secs: 30 }
+        Self::with_accumulators(Arc::new(AccumulatorStore::new()))
+    }
+
+    pub fn with_accumulators(accumulators: Arc<AccumulatorStore>) -> Self {
+        // Medicare: one calendar year from the date of service
+        Self::with_rules(PayerId::Medicare.name(), MedicareRules, accumulators, FeeSchedule::medicare(), 365)
     }
 }
 
-pub struct UnitedHealthGroup {
-    pub min_response_time_secs: u64,
-    pub max_response_time_secs: u64,
+impl Default for UnitedHealthGroup {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UnitedHealthGroup {
     pub fn new() -> Self {
-        Self { min_response_time_secs: 10, max_response_time_secs: 30 }
+        Self::with_accumulators(Arc::new(AccumulatorStore::new()))
+    }
+
+    pub fn with_accumulators(accumulators: Arc<AccumulatorStore>) -> Self {
+        // UnitedHealthcare commercial, participating providers
+        Self::with_rules(PayerId::UnitedHealthGroup.name(), UnitedHealthGroupRules, accumulators, FeeSchedule::united_health_group(), 90)
     }
 }
 
-pub struct Anthem {
-    pub min_response_time_secs: u64,
-    pub max_response_time_secs: u64,
+impl Default for Anthem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Anthem {
     pub fn new() -> Self {
-        Self { min_response_time_secs: 10, max_response_time_secs: 30 }
-    }
-}
-
-pub trait Insurance {
-    fn submit_claim(&self, claim: &PayerClaim) -> Result<Remittance, String>;
-}
-
-impl Insurance for Medicare {
-    fn submit_claim(&self, claim: &PayerClaim) -> Result<Remittance, String> {
-        let mut service_lines = Vec::new();
-
-        for line in &claim.service_lines {
-            let billed_amount = line.unit_charge_amount * line.units as f64;
-            
-            // Medicare denial rate: 5-10% for services not meeting guidelines
-            let denial_rate = 0.05 + (rand::random::<f64>() * 0.05); // 5-10%
-            let not_allowed_amount = billed_amount * denial_rate;
-            let allowed_amount = billed_amount - not_allowed_amount;
-            
-            // Medicare Part B 2025 deductible: $257 per year (applied to allowed amount)
-            let deductible_amount = if allowed_amount > 257.0 { 257.0 } else { allowed_amount };
-            let remaining_after_deductible = allowed_amount - deductible_amount;
-            
-            // Medicare Part B standard: 80% coverage, 20% coinsurance after deductible
-            let payer_paid_amount = remaining_after_deductible * 0.8;
-            let coinsurance_amount = remaining_after_deductible * 0.2;
-            
-            // Medicare Part B typically doesn't use copays for physician services
-            let copay_amount = 0.0;
-
-            let service_line = ServiceLine::new(
-                line, 
-                billed_amount, 
-                payer_paid_amount, 
-                coinsurance_amount, 
-                copay_amount, 
-                deductible_amount, 
-                not_allowed_amount
-            )?;
-            
-            service_lines.push(service_line);
-        }
+        Self::with_accumulators(Arc::new(AccumulatorStore::new()))
+    }
 
-        // random sleep because insurance is slow
-        let sleep_duration = rand::random_range(self.min_response_time_secs..=self.max_response_time_secs);
-        thread::sleep(Duration::from_secs(sleep_duration));
+    pub fn with_accumulators(accumulators: Arc<AccumulatorStore>) -> Self {
+        // Anthem, participating providers
+        Self::with_rules(PayerId::Anthem.name(), AnthemRules, accumulators, FeeSchedule::anthem(), 90)
+    }
+}
 
-        let remittance = create_remittance(service_lines, claim);
-        Ok(remittance)
+impl PlanPayer {
+    /// Priced from the Medicare fee schedule until `with_fee_schedule()` says otherwise.
+    pub fn new(name: &str, plans: PlanCatalog) -> Self {
+        Self::with_accumulators(name, plans, Arc::new(AccumulatorStore::new()))
+    }
+
+    pub fn with_accumulators(name: &str, plans: PlanCatalog, accumulators: Arc<AccumulatorStore>) -> Self {
+        let timely_filing_days = plans.timely_filing_days;
+        Self::with_rules(name, PlanRules { plans: Arc::new(plans) }, accumulators, FeeSchedule::medicare(), timely_filing_days)
     }
 }
 
-impl Insurance for UnitedHealthGroup {
-    fn submit_claim(&self, claim: &PayerClaim) -> Result<Remittance, String> {
-        let mut service_lines = Vec::new();
-
-        for line in &claim.service_lines {
-            let billed_amount = line.unit_charge_amount * line.units as f64;
-            
-            // Private insurers typically have lower denial rates: 3-7%
-            let denial_rate = 0.03 + (rand::random::<f64>() * 0.04); // 3-7%
-            let not_allowed_amount = billed_amount * denial_rate;
-            let allowed_amount = billed_amount - not_allowed_amount;
-            
-            // UnitedHealth average individual deductible: ~$1,800 (applied to allowed amount)
-            let deductible_amount = if allowed_amount > 1800.0 { 1800.0 } else { allowed_amount };
-            let remaining_after_deductible = allowed_amount - deductible_amount;
-            
-            // UnitedHealth typical copay for routine services: $25-35
-            let copay_base = 25.0 + (rand::random::<f64>() * 10.0); // $25-35
-            let copay_amount = if remaining_after_deductible > copay_base { copay_base } else { 0.0 };
-            let remaining_after_copay = remaining_after_deductible - copay_amount;
-            
-            // UnitedHealth typical coverage: 75% (between 70-80% range)
-            // Patient coinsurance: 25% (typical private insurance 20-30% range)
-            let coverage_rate = 0.70 + (rand::random::<f64>() * 0.1); // 70-80%
-            let payer_paid_amount = remaining_after_copay * coverage_rate;
-            let coinsurance_amount = remaining_after_copay * (1.0 - coverage_rate);
-
-            let service_line = ServiceLine::new(
-                line, 
-                billed_amount, 
-                payer_paid_amount, 
-                coinsurance_amount, 
-                copay_amount, 
-                deductible_amount, 
-                not_allowed_amount
-            )?;
-            
-            service_lines.push(service_line);
+impl<R: PayerRules> PayerCore<R> {
+    pub fn with_rules(name: &str, rules: R, accumulators: Arc<AccumulatorStore>, fee_schedule: FeeSchedule, timely_filing_days: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            rules,
+            min_response_time_secs: 10,
+            max_response_time_secs: 30,
+            accumulators,
+            claim_history: Arc::new(ClaimHistory::new()),
+            roster: Arc::new(MemberRoster::new()),
+            seed: None,
+            clock: Arc::new(SystemClock),
+            outage_rate: 0.0,
+            timely_filing_days,
+            fee_schedule: Arc::new(fee_schedule),
+            medical_necessity: Arc::new(MedicalNecessityPolicy::standard()),
+            attempts: Mutex::new(HashMap::new()),
         }
+    }
+
+    pub fn with_name(self, name: &str) -> Self {
+        Self { name: name.to_string(), ..self }
+    }
+
+    pub fn with_claim_history(self, claim_history: Arc<ClaimHistory>) -> Self {
+        Self { claim_history, ..self }
+    }
+
+    pub fn with_roster(self, roster: Arc<MemberRoster>) -> Self {
+        Self { roster, ..self }
+    }
 
-        // random sleep because insurance is slow
-        let sleep_duration = rand::random_range(self.min_response_time_secs..=self.max_response_time_secs);
-        thread::sleep(Duration::from_secs(sleep_duration));
+    pub fn with_fee_schedule(self, fee_schedule: Arc<FeeSchedule>) -> Self {
+        Self { fee_schedule, ..self }
+    }
 
-        let remittance = create_remittance(service_lines, claim);
+    pub fn with_medical_necessity(self, medical_necessity: Arc<MedicalNecessityPolicy>) -> Self {
+        Self { medical_necessity, ..self }
+    }
+
+    /// Takes a seed or an `Option` of one, so an unseeded run can pass `None` through.
+    pub fn with_seed(self, seed: impl Into<Option<u64>>) -> Self {
+        Self { seed: seed.into(), ..self }
+    }
+
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    pub fn with_outage_rate(self, outage_rate: f64) -> Self {
+        Self { outage_rate, ..self }
+    }
+
+    pub fn with_timely_filing_days(self, timely_filing_days: u32) -> Self {
+        Self { timely_filing_days, ..self }
+    }
+
+    pub fn benefit_limits(&self, member_id: &str, plan_year: i32) -> BenefitLimits {
+        let mut rng = adjudication_rng(self.seed, &[&self.name, member_id, &plan_year.to_string()]);
+        self.rules.benefit_limits(member_id, &mut rng)
+    }
+
+    // Outages are drawn from a stream of their own, keyed by the claim and how many times it has been
+    // sent: a seeded run has the same outages whatever order workers get to claims in, a retry can get
+    // through, and the claim adjudicates the same way whichever attempt does. An unavailable payer
+    // times out after its slowest response.
+    fn check_availability(&self, claim: &PayerClaim) -> Result<(), InsuranceError> {
+        if self.outage_rate <= 0.0 {
+            return Ok(());
+        }
+        let attempt = {
+            let mut attempts = self.attempts.lock().unwrap();
+            let attempt = attempts.entry(claim.claim_id.clone()).or_insert(0);
+            *attempt += 1;
+            *attempt
+        };
+        let mut rng = adjudication_rng(self.seed, &[&self.name, &claim.claim_id, "outage", &attempt.to_string()]);
+        if rng.random::<f64>() < self.outage_rate {
+            self.clock.sleep(Duration::from_secs(self.max_response_time_secs));
+            return Err(InsuranceError::PayerUnavailable { payer_id: self.name.clone(), claim_id: claim.claim_id.clone() });
+        }
+        Ok(())
+    }
+
+    // Every line of every claim goes through here. Denied lines count toward neither the deductible
+    // nor the out-of-pocket maximum.
+    fn adjudicate_line(
+        &self,
+        claim: &PayerClaim,
+        line: &IntakeServiceLine,
+        coverage: &CoverageStatus,
+        benefits: &mut accumulators::MemberBenefits,
+        rng: &mut impl Rng,
+    ) -> Result<ServiceLine, InsuranceError> {
+        if line.do_not_bill.unwrap_or(false) {
+            return ServiceLine::new(line, Money::ZERO, Money::ZERO, Money::ZERO, Money::ZERO, Money::ZERO, Money::ZERO);
+        }
+        let billed_amount = line.unit_charge_amount * line.units;
+        let member_id = &claim.insurance.patient_member_id;
+
+        if let Some(reason_code) = screen_line(claim, line, self.timely_filing_days, coverage) {
+            return Ok(ServiceLine::denied(line, billed_amount, reason_code));
+        }
+        // lines whose diagnoses don't support the procedure are denied outright (CO-11)
+        if self.rules.checks_medical_necessity(member_id) && !self.medical_necessity.supports(&line.procedure_code, claim.line_diagnoses(line)) {
+            return Ok(ServiceLine::denied(line, billed_amount, CARC_DIAGNOSIS_INCONSISTENT));
+        }
+        if let Some(reason_code) = self.rules.deny_line(member_id, line, rng) {
+            return Ok(ServiceLine::denied(line, billed_amount, reason_code));
+        }
+
+        let (copay, coverage_rate) = self.rules.cost_sharing(member_id, line, rng);
+        let service_line = price_line(benefits, &self.fee_schedule, claim, line, billed_amount, copay, coverage_rate)?;
+        let remark_codes = self.rules.remark_codes(&service_line);
+        Ok(service_line.with_remark_codes(remark_codes))
+    }
+}
+
+pub trait Insurance: Send + Sync {
+    fn submit_claim(&self, claim: &PayerClaim) -> Result<Remittance, InsuranceError>;
+
+    /// Reverses the adjudicated claim that a void refers to.
+    fn reverse_claim(&self, claim: &PayerClaim) -> Result<Remittance, InsuranceError>;
+
+    /// Reverses the adjudicated claim that a replacement refers to and adjudicates the replacement
+    /// in its place, returning the reversal and then the replacement's remittance. When the
+    /// replacement can't be adjudicated the original isn't reversed either.
+    fn replace_claim(&self, claim: &PayerClaim) -> Result<(Remittance, Remittance), InsuranceError>;
+
+    /// Answers an eligibility inquiry: whether the member is covered on `date_of_service` and,
+    /// if so, what their plan would leave them paying. Nothing is adjudicated or recorded.
+    fn check_eligibility(&self, member_id: &str, date_of_service: NaiveDate) -> Result<EligibilityResponse, InsuranceError>;
+}
+
+impl<R: PayerRules> Insurance for PayerCore<R> {
+    fn submit_claim(&self, claim: &PayerClaim) -> Result<Remittance, InsuranceError> {
+        let member_id = &claim.insurance.patient_member_id;
+        let plan_year = self.accumulators.plan_year_of(claim.date_of_service());
+        let limits = || self.benefit_limits(member_id, plan_year);
+
+        self.check_availability(claim)?;
+        let mut rng = adjudication_rng(self.seed, &[&self.name, &claim.claim_id]);
+        let coverage = self.roster.verify(&self.name, member_id, &claim.patient, claim.date_of_service());
+        // the subscriber and each dependent have accumulators of their own within the member id's family
+        let person = accumulator_member(claim);
+        self.accumulators.join_family(&self.name, &person, member_id);
+
+        // the patient's accumulators only keep the claim if every line adjudicates and the remittance
+        // balances; anything else leaves them as they were
+        let (remittance, sleep_duration) = self.accumulators.try_transaction(&self.name, &person, plan_year, limits, |benefits| {
+            let service_lines = claim.service_lines.iter().map(|line| self.adjudicate_line(claim, line, &coverage, benefits, &mut rng)).collect::<Result<Vec<_>, _>>()?;
+            // random sleep because insurance is slow
+            let sleep_duration = rng.random_range(self.min_response_time_secs..=self.max_response_time_secs);
+            let remittance = create_remittance(&self.name, service_lines, claim, &mut rng);
+            remittance.check_balance()?;
+            Ok::<_, InsuranceError>((remittance, sleep_duration))
+        })?;
+        self.clock.sleep(Duration::from_secs(sleep_duration));
+
+        self.claim_history.record(&self.name, plan_year, claim, &remittance);
         Ok(remittance)
     }
+
+    fn reverse_claim(&self, claim: &PayerClaim) -> Result<Remittance, InsuranceError> {
+        self.check_availability(claim)?;
+        self.claim_history.reverse(&self.name, claim, &self.accumulators)
+    }
+
+    fn replace_claim(&self, claim: &PayerClaim) -> Result<(Remittance, Remittance), InsuranceError> {
+        let reversal = self.reverse_claim(claim)?;
+        // the replacement meets the deductible as if the original never happened, so it's
+        // adjudicated after the reversal and the reversal is taken back if it fails
+        let remittance = self.submit_claim(claim).inspect_err(|_| self.claim_history.restore(&self.name, claim, &self.accumulators))?;
+        Ok((reversal, remittance))
+    }
+
+    fn check_eligibility(&self, member_id: &str, date_of_service: NaiveDate) -> Result<EligibilityResponse, InsuranceError> {
+        let terms = self.rules.plan_terms(member_id);
+        Ok(eligibility_response(&self.name, &self.roster, &self.accumulators, member_id, date_of_service, |plan_year| self.benefit_limits(member_id, plan_year), terms))
+    }
+}
+
+/// What a payer's plan charges, for eligibility responses.
+pub struct PlanTerms<'a> {
+    pub plan_name: &'a str,
+    pub office_visit_copay: Money,
+    pub coinsurance: f64,
+}
+
+// Reads the member's accumulators the same way adjudication would, so a member seen for the first
+// time reports their full deductible.
+fn eligibility_response(
+    payer_id: &str,
+    roster: &MemberRoster,
+    accumulators: &AccumulatorStore,
+    member_id: &str,
+    date_of_service: NaiveDate,
+    limits: impl FnOnce(i32) -> BenefitLimits,
+    terms: PlanTerms,
+) -> EligibilityResponse {
+    let (status, enrollment) = roster.coverage(payer_id, member_id, date_of_service);
+    let benefits = status.is_active().then(|| {
+        let plan_year = accumulators.plan_year_of(date_of_service);
+        let limits = || limits(plan_year);
+        let (deductible_remaining, out_of_pocket_remaining) = accumulators.transaction(payer_id, member_id, plan_year, limits, |benefits| {
+            (benefits.remaining_deductible(), benefits.remaining_out_of_pocket())
+        });
+        let plan_name = enrollment.map(|e| e.plan.as_str()).filter(|plan| !plan.is_empty()).unwrap_or(terms.plan_name);
+        BenefitSummary {
+            plan_name: plan_name.to_string(),
+            deductible_remaining,
+            out_of_pocket_remaining: (out_of_pocket_remaining != Money::MAX).then_some(out_of_pocket_remaining),
+            office_visit_copay: terms.office_visit_copay,
+            coinsurance: terms.coinsurance,
+        }
+    });
+    EligibilityResponse { payer_id: payer_id.to_string(), member_id: member_id.to_string(), date_of_service, status, benefits }
 }
 
-impl Insurance for Anthem {
-    fn submit_claim(&self, claim: &PayerClaim) -> Result<Remittance, String> {
-        let mut service_lines = Vec::new();
-
-        for line in &claim.service_lines {
-            let billed_amount = line.unit_charge_amount * line.units as f64;
-            
-            // Anthem denial rate: 5-8% (moderate for private insurer)
-            let denial_rate = 0.05 + (rand::random::<f64>() * 0.03); // 5-8%
-            let not_allowed_amount = billed_amount * denial_rate;
-            let allowed_amount = billed_amount - not_allowed_amount;
-            
-            // Anthem average individual deductible: ~$1,650-2,000 (applied to allowed amount)
-            let deductible_base = 1650.0 + (rand::random::<f64>() * 350.0); // $1,650-2,000
-            let deductible_amount = if allowed_amount > deductible_base { deductible_base } else { allowed_amount };
-            let remaining_after_deductible = allowed_amount - deductible_amount;
-            
-            // Anthem typical copay for routine services: $20-30
-            let copay_base = 20.0 + (rand::random::<f64>() * 10.0); // $20-30
-            let copay_amount = if remaining_after_deductible > copay_base { copay_base } else { 0.0 };
-            let remaining_after_copay = remaining_after_deductible - copay_amount;
-            
-            // Anthem Silver plan structure: 70% coverage, 30% coinsurance
-            // This is based on typical Anthem Silver plan coinsurance rates
-            let payer_paid_amount = remaining_after_copay * 0.7;
-            let coinsurance_amount = remaining_after_copay * 0.3;
-
-            let service_line = ServiceLine::new(
-                line, 
-                billed_amount, 
-                payer_paid_amount, 
-                coinsurance_amount, 
-                copay_amount, 
-                deductible_amount, 
-                not_allowed_amount
-            )?;
-            
-            service_lines.push(service_line);
+// A claim crossed over from earlier payers is only adjudicated up to what they left the patient
+// owing; the rest of the billed amount was settled by them. Returns the allowed amount to adjudicate
+// and the amount the earlier payers settled.
+fn coordinate(claim: &PayerClaim, line: &IntakeServiceLine, billed_amount: Money, allowed_amount: Money) -> (Money, Money) {
+    match claim.prior_line_payment(line) {
+        Some(prior) => {
+            let remaining = prior.patient_responsibility().clamp(Money::ZERO, billed_amount);
+            (allowed_amount.min(remaining), billed_amount - remaining)
         }
+        None => (allowed_amount, Money::ZERO),
+    }
+}
 
-        // random sleep because insurance is slow
-        let sleep_duration = rand::random_range(self.min_response_time_secs..=self.max_response_time_secs);
-        thread::sleep(Duration::from_secs(sleep_duration));
+// Prices a line that cleared every denial. Anything billed above the fee schedule is a contractual
+// write-off (CO-45); the member's copay, deductible and coinsurance on the rest are taken against
+// their accumulators, and the payer covers `coverage_rate` of what's left after the deductible.
+fn price_line(
+    benefits: &mut accumulators::MemberBenefits,
+    fee_schedule: &FeeSchedule,
+    claim: &PayerClaim,
+    line: &IntakeServiceLine,
+    billed_amount: Money,
+    copay: Money,
+    coverage_rate: f64,
+) -> Result<ServiceLine, InsuranceError> {
+    let allowed_amount = fee_schedule.allowed_amount(line, claim.place_of_service_code);
+    let (allowed_amount, prior_settled) = coordinate(claim, line, billed_amount, allowed_amount);
+    let not_allowed_amount = billed_amount - allowed_amount;
+    let share = benefits.adjudicate(allowed_amount, copay, coverage_rate);
+    let service_line = ServiceLine::new(line, billed_amount, share.payer_paid, share.coinsurance, share.copay, share.deductible, not_allowed_amount)?;
+    Ok(service_line.with_prior_payer_adjustment(prior_settled))
+}
 
-        let remittance = create_remittance(service_lines, claim);
-        Ok(remittance)
+// Denials every payer makes before its own rules see the line, as the CARC the line is denied with.
+fn screen_line(claim: &PayerClaim, line: &IntakeServiceLine, timely_filing_days: u32, coverage: &CoverageStatus) -> Option<&'static str> {
+    // lines billed after the payer's timely filing limit are denied outright (CO-29)
+    let days_to_file = (claim.received_date() - claim.line_date_of_service(line)).num_days();
+    if days_to_file > i64::from(timely_filing_days) {
+        return Some(CARC_TIMELY_FILING);
     }
+    // nothing is covered for a patient who isn't enrolled under the member id on the date of service
+    coverage.reason_code()
 }
 
-pub fn create_remittance(service_lines: Vec<ServiceLine>, claim: &PayerClaim) -> Remittance {
+pub fn create_remittance(payer_id: &str, service_lines: Vec<ServiceLine>, claim: &PayerClaim, rng: &mut impl Rng) -> Remittance {
     let claim_id = claim.claim_id.clone();
-    let payer_id = match claim.insurance.payer_id {
-        PayerId::Medicare => "Medicare".to_string(),
-        PayerId::UnitedHealthGroup => "UnitedHealthGroup".to_string(),
-        PayerId::Anthem => "Anthem".to_string(),
-    };
+    let payer_id = payer_id.to_string();
     let patient_id = format!("{}-{}", payer_id, claim.insurance.patient_member_id);
     let payee_npi = claim.organization.billing_npi.clone().unwrap_or("".to_string());
-    let remittance_id = format!("{}", Uuid::new_v4());
+    let remittance_id = format!("{}", uuid::Builder::from_random_bytes(rng.random()).into_uuid());
 
     Remittance {
-        remittance_id: remittance_id,
-        claim_id: claim_id,
-        payer_id: payer_id,
-        payee_npi: payee_npi,
-        patient_id: patient_id,
-        service_lines: service_lines,
+        remittance_id,
+        claim_id,
+        payer_id,
+        payee_npi,
+        patient_id,
+        service_lines,
         initial_claim_ts: claim.initial_claim_ts,
+        reversal: false,
+        payer_sequence: claim.prior_payments.len() as u8 + 1,
+        crossover_payer_id: None,
+        // the clearinghouse converts claims into the payer's settlement currency before sending them
+        currency: claim.service_lines.first().map_or_else(|| "USD".to_string(), |line| line.unit_charge_currency.clone()),
+        currency_conversion: None,
     }
 }
\ No newline at end of file
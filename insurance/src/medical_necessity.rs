@@ -0,0 +1,83 @@
+use crate::InsuranceError;
+use intake::Diagnosis;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Which diagnoses support a procedure. Procedures without a rule are covered for any diagnosis;
+/// a procedure with rules is covered when at least one of the line's diagnoses starts with one
+/// of its prefixes (codes are compared without the dot).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MedicalNecessityPolicy {
+    rules: HashMap<String, Vec<String>>,
+}
+
+impl MedicalNecessityPolicy {
+    pub fn new() -> MedicalNecessityPolicy {
+        MedicalNecessityPolicy::default()
+    }
+
+    pub fn insert(&mut self, procedure_code: &str, diagnosis_prefix: &str) {
+        let prefix = diagnosis_prefix.replace('.', "").to_ascii_uppercase();
+        self.rules.entry(procedure_code.to_string()).or_default().push(prefix);
+    }
+
+    /// Parses `procedure_code,diagnosis_prefix` rows. The header row is required; blank lines
+    /// and lines starting with `#` are skipped.
+    pub fn from_csv(contents: &str) -> Result<MedicalNecessityPolicy, InsuranceError> {
+        let mut policy = MedicalNecessityPolicy::new();
+        let mut rows = contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        match rows.next() {
+            Some((_, "procedure_code,diagnosis_prefix")) => {}
+            Some((line_number, _)) => {
+                return Err(InsuranceError::policy(format!(
+                    "Line {}: expected header procedure_code,diagnosis_prefix",
+                    line_number
+                )));
+            }
+            None => return Err(InsuranceError::policy("Medical necessity policy is empty")),
+        }
+
+        for (line_number, row) in rows {
+            match row.split(',').map(|f| f.trim()).collect::<Vec<_>>()[..] {
+                [procedure_code, diagnosis_prefix] if !procedure_code.is_empty() && !diagnosis_prefix.is_empty() => {
+                    policy.insert(procedure_code, diagnosis_prefix);
+                }
+                _ => {
+                    return Err(InsuranceError::policy(format!(
+                        "Line {}: expected a procedure_code and a diagnosis_prefix",
+                        line_number
+                    )));
+                }
+            }
+        }
+
+        Ok(policy)
+    }
+
+    pub fn load(path: &Path) -> Result<MedicalNecessityPolicy, InsuranceError> {
+        let with_path = |message: String| InsuranceError::Policy { path: Some(path.display().to_string()), message };
+        let contents = std::fs::read_to_string(path).map_err(|e| with_path(format!("failed to read: {}", e)))?;
+        MedicalNecessityPolicy::from_csv(&contents).map_err(|e| with_path(e.to_string()))
+    }
+
+    /// Whether any of `diagnoses` supports `procedure_code`.
+    pub fn supports<'a>(&self, procedure_code: &str, diagnoses: impl IntoIterator<Item = &'a Diagnosis>) -> bool {
+        let Some(prefixes) = self.rules.get(procedure_code) else {
+            return true;
+        };
+        diagnoses.into_iter().any(|diagnosis| {
+            let code = diagnosis.normalized_code();
+            prefixes.iter().any(|prefix| code.starts_with(prefix.as_str()))
+        })
+    }
+
+    /// The bundled policy shared by all payers.
+    pub fn standard() -> MedicalNecessityPolicy {
+        MedicalNecessityPolicy::from_csv(include_str!("../policies/medical_necessity.csv")).unwrap()
+    }
+}
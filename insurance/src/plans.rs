@@ -0,0 +1,215 @@
+use crate::accumulators::BenefitLimits;
+use crate::InsuranceError;
+use intake::Money;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Broad kinds of service a plan can set its own copay for, derived from the procedure code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceCategory {
+    /// Preventive medicine visits (CPT 99381-99429)
+    Preventive,
+    /// Office, hospital and other evaluation and management visits (CPT 99202-99499)
+    EvaluationAndManagement,
+    /// CPT 00100-01999
+    Anesthesia,
+    /// CPT 10004-69990
+    Surgery,
+    /// CPT 70010-79999
+    Radiology,
+    /// CPT 80047-89398
+    Laboratory,
+    /// CPT 90281-99199, such as injections, ECGs and therapy
+    Medicine,
+    /// HCPCS level II codes and anything unrecognized
+    Other,
+}
+
+impl ServiceCategory {
+    pub fn of(procedure_code: &str) -> ServiceCategory {
+        let code: u32 = match procedure_code.trim().parse() {
+            Ok(code) if procedure_code.trim().len() == 5 => code,
+            _ => return ServiceCategory::Other,
+        };
+        match code {
+            99381..=99429 => ServiceCategory::Preventive,
+            99202..=99499 => ServiceCategory::EvaluationAndManagement,
+            100..=1999 => ServiceCategory::Anesthesia,
+            10004..=69990 => ServiceCategory::Surgery,
+            70010..=79999 => ServiceCategory::Radiology,
+            80047..=89398 => ServiceCategory::Laboratory,
+            90281..=99199 => ServiceCategory::Medicine,
+            _ => ServiceCategory::Other,
+        }
+    }
+}
+
+/// When a plan denies a line outright, before any cost sharing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DenialRules {
+    /// Deny lines whose diagnoses don't support the procedure (CO-11)
+    pub medical_necessity: bool,
+    /// Procedure codes the plan doesn't cover at all (CO-96)
+    pub non_covered: Vec<String>,
+    /// Chance (0-1) that any other line is denied with `reason_code`
+    pub rate: f64,
+    pub reason_code: String,
+}
+
+impl Default for DenialRules {
+    fn default() -> Self {
+        DenialRules { medical_necessity: true, non_covered: Vec::new(), rate: 0.0, reason_code: "197".to_string() }
+    }
+}
+
+/// One benefit plan: what the member pays and what the plan won't cover.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenefitPlan {
+    pub plan_id: String,
+    pub deductible: Money,
+    #[serde(default)]
+    pub family_deductible: Option<Money>,
+    #[serde(default)]
+    pub out_of_pocket_max: Option<Money>,
+    #[serde(default)]
+    pub family_out_of_pocket_max: Option<Money>,
+    /// Member's share (0-1) of what's left after the deductible and copay
+    pub coinsurance: f64,
+    /// Copay per service category; categories that aren't listed have none
+    #[serde(default)]
+    pub copays: HashMap<ServiceCategory, Money>,
+    #[serde(default)]
+    pub denials: DenialRules,
+}
+
+impl BenefitPlan {
+    pub fn limits(&self) -> BenefitLimits {
+        BenefitLimits {
+            deductible: self.deductible,
+            out_of_pocket_max: self.out_of_pocket_max,
+            family_deductible: self.family_deductible,
+            family_out_of_pocket_max: self.family_out_of_pocket_max,
+        }
+    }
+
+    pub fn copay(&self, procedure_code: &str) -> Money {
+        self.copays.get(&ServiceCategory::of(procedure_code)).copied().unwrap_or_default()
+    }
+
+    pub fn covers(&self, procedure_code: &str) -> bool {
+        !self.denials.non_covered.iter().any(|code| code == procedure_code)
+    }
+
+    fn validate(&self) -> Result<(), InsuranceError> {
+        let invalid = |field: &str, message: String| InsuranceError::Plan { field: format!("{}.{}", self.plan_id, field), message };
+        let amounts = [
+            ("deductible", Some(self.deductible)),
+            ("family_deductible", self.family_deductible),
+            ("out_of_pocket_max", self.out_of_pocket_max),
+            ("family_out_of_pocket_max", self.family_out_of_pocket_max),
+        ];
+        for (field, amount) in amounts {
+            if amount.is_some_and(|amount| amount < Money::ZERO) {
+                return Err(invalid(field, format!("plan {} {} must not be negative", self.plan_id, field)));
+            }
+        }
+        for (field, rate) in [("coinsurance", self.coinsurance), ("denials.rate", self.denials.rate)] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(invalid(field, format!("plan {} {} must be between 0 and 1, got {}", self.plan_id, field, rate)));
+            }
+        }
+        if let Some((category, _)) = self.copays.iter().find(|(_, copay)| **copay < Money::ZERO) {
+            return Err(invalid("copays", format!("plan {} copay for {:?} must not be negative", self.plan_id, category)));
+        }
+        Ok(())
+    }
+}
+
+/// A payer's benefit plans and which plan each member is enrolled in. Members without an
+/// enrollment get `default_plan`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanCatalog {
+    pub default_plan: String,
+    pub plans: Vec<BenefitPlan>,
+    /// member id -> plan id
+    #[serde(default)]
+    pub members: HashMap<String, String>,
+    /// Days after a line's date of service claims are still accepted
+    #[serde(default = "default_timely_filing_days")]
+    pub timely_filing_days: u32,
+}
+
+// the most common commercial limit
+fn default_timely_filing_days() -> u32 {
+    180
+}
+
+impl PlanCatalog {
+    /// A catalog where every member gets `plan`.
+    pub fn single(plan: BenefitPlan) -> PlanCatalog {
+        PlanCatalog {
+            default_plan: plan.plan_id.clone(),
+            plans: vec![plan],
+            members: HashMap::new(),
+            timely_filing_days: default_timely_filing_days(),
+        }
+    }
+
+    pub fn from_json(contents: &str) -> Result<PlanCatalog, InsuranceError> {
+        let catalog: PlanCatalog = serde_json::from_str(contents)
+            .map_err(|e| InsuranceError::Plan { field: "plans".to_string(), message: format!("Invalid plan JSON: {}", e) })?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    pub fn load(path: &Path) -> Result<PlanCatalog, InsuranceError> {
+        let with_path = |field: String, message: String| InsuranceError::Plan { field, message: format!("Plans {}: {}", path.display(), message) };
+        let contents = std::fs::read_to_string(path).map_err(|e| with_path("plans".to_string(), format!("failed to read: {}", e)))?;
+        PlanCatalog::from_json(&contents).map_err(|e| match e {
+            InsuranceError::Plan { field, message } => with_path(field, message),
+            e => e,
+        })
+    }
+
+    pub fn plan(&self, plan_id: &str) -> Option<&BenefitPlan> {
+        self.plans.iter().find(|plan| plan.plan_id == plan_id)
+    }
+
+    /// The plan `member_id` is enrolled in.
+    pub fn plan_for(&self, member_id: &str) -> &BenefitPlan {
+        let plan_id = self.members.get(member_id).unwrap_or(&self.default_plan);
+        self.plan(plan_id).expect("validated catalogs only refer to their own plans")
+    }
+
+    pub fn enroll(&mut self, member_id: &str, plan_id: &str) -> Result<(), InsuranceError> {
+        if self.plan(plan_id).is_none() {
+            return Err(unknown_plan(&format!("members.{}", member_id), plan_id));
+        }
+        self.members.insert(member_id.to_string(), plan_id.to_string());
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), InsuranceError> {
+        let mut plan_ids = HashSet::new();
+        for plan in &self.plans {
+            if !plan_ids.insert(plan.plan_id.as_str()) {
+                return Err(InsuranceError::Plan { field: "plans".to_string(), message: format!("plan {} is defined twice", plan.plan_id) });
+            }
+            plan.validate()?;
+        }
+        if self.plan(&self.default_plan).is_none() {
+            return Err(unknown_plan("default_plan", &self.default_plan));
+        }
+        if let Some((member_id, plan_id)) = self.members.iter().find(|(_, plan_id)| self.plan(plan_id).is_none()) {
+            return Err(unknown_plan(&format!("members.{}", member_id), plan_id));
+        }
+        Ok(())
+    }
+}
+
+fn unknown_plan(field: &str, plan_id: &str) -> InsuranceError {
+    InsuranceError::Plan { field: field.to_string(), message: format!("{} refers to unknown plan {}", field, plan_id) }
+}
@@ -0,0 +1,27 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+/// The random number generator used to adjudicate one claim (or price one member's plan).
+///
+/// With a seed, the stream is derived from the seed and `key` alone, so a claim gets the same
+/// draws no matter which worker processes it or in what order. Without one, it is seeded from
+/// the operating system.
+///
+/// ChaCha12 is named rather than taken as `StdRng`, whose algorithm may change between rand
+/// releases, so a seed keeps producing the same remittances after an upgrade.
+pub fn adjudication_rng(seed: Option<u64>, key: &[&str]) -> ChaCha12Rng {
+    match seed {
+        Some(seed) => {
+            // FNV-1a rather than std's hasher, whose output is not guaranteed across Rust releases
+            let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+            for part in key {
+                for byte in part.bytes().chain([0xff]) {
+                    hash ^= byte as u64;
+                    hash = hash.wrapping_mul(0x0100_0000_01b3);
+                }
+            }
+            ChaCha12Rng::seed_from_u64(hash)
+        }
+        None => ChaCha12Rng::from_os_rng(),
+    }
+}
@@ -1,5 +1,7 @@
+use crate::InsuranceError;
+use chrono::NaiveDate;
 use serde::{Serialize, Deserialize};
-use intake::ServiceLine as IntakeServiceLine;
+use intake::{Money, ServiceLine as IntakeServiceLine};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Remittance {
@@ -10,54 +12,294 @@ pub struct Remittance {
     pub patient_id: String,
     pub service_lines: Vec<ServiceLine>,
     pub initial_claim_ts: i64,
+    /// Takes back an earlier payment (CLP02 22): carries the original's remittance id and its
+    /// amounts negated
+    #[serde(default)]
+    pub reversal: bool,
+    /// Where the payer stands in the patient's coordination of benefits: 1 for the primary, 2 for
+    /// the secondary and so on
+    #[serde(default = "primary_payer")]
+    pub payer_sequence: u8,
+    /// The next payer the claim was crossed over to for the patient's share (NM1*TT); that
+    /// payer's remittance says what the patient still owes
+    #[serde(default)]
+    pub crossover_payer_id: Option<String>,
+    /// ISO 4217 code of the currency every amount is in: the payer's settlement currency
+    #[serde(default = "settlement_currency")]
+    pub currency: String,
+    /// How the claim was converted when it was billed in another currency. Reversals carry none:
+    /// they take back the original's amounts as they were paid
+    #[serde(default)]
+    pub currency_conversion: Option<CurrencyConversion>,
+}
+
+fn primary_payer() -> u8 {
+    1
+}
+
+fn settlement_currency() -> String {
+    "USD".to_string()
+}
+
+/// The exchange rate a claim billed in a foreign currency was converted into the payer's
+/// settlement currency with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurrencyConversion {
+    pub from_currency: String,
+    pub to_currency: String,
+    /// Units of `to_currency` per unit of `from_currency`
+    pub rate: f64,
+    /// The day the rate took effect
+    pub effective_date: NaiveDate,
+}
+
+impl CurrencyConversion {
+    /// `amount` in `to_currency`, rounded to the nearest cent.
+    pub fn convert(&self, amount: Money) -> Money {
+        amount.times(self.rate)
+    }
+}
+
+impl Remittance {
+    /// Checks that every line balances to the cent: the paid, patient and not-allowed amounts add
+    /// up to the billed amount, and the adjustments explain everything that wasn't paid.
+    pub fn check_balance(&self) -> Result<(), InsuranceError> {
+        for line in &self.service_lines {
+            let accounted = line.payer_paid_amount
+                + line.coinsurance_amount
+                + line.copay_amount
+                + line.deductible_amount
+                + line.not_allowed_amount;
+            let adjusted: Money = line.adjustments.iter().map(|a| a.amount).sum();
+            let message = if accounted != line.billed_amount {
+                format!("{} billed but {} paid, owed by the patient or not allowed", line.billed_amount, accounted)
+            } else if adjusted != line.billed_amount - line.payer_paid_amount {
+                format!("{} unpaid but adjustments total {}", line.billed_amount - line.payer_paid_amount, adjusted)
+            } else {
+                continue;
+            };
+            return Err(InsuranceError::Unbalanced {
+                service_line_id: line.service_line_id.clone(),
+                message,
+            });
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceLine {
     pub service_line_id: String,
     pub procedure_code: String,
-    pub billed_amount: f64,
-    pub payer_paid_amount: f64,
-    pub coinsurance_amount: f64,
-    pub copay_amount: f64,
-    pub deductible_amount: f64,
-    pub not_allowed_amount: f64,
+    pub billed_amount: Money,
+    pub payer_paid_amount: Money,
+    pub coinsurance_amount: Money,
+    pub copay_amount: Money,
+    pub deductible_amount: Money,
+    pub not_allowed_amount: Money,
+    #[serde(default)]
+    pub adjustments: Vec<Adjustment>,
     pub remark_codes: Option<Vec<String>>,
 }
 
+/// X12 claim adjustment group code (CAS01)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GroupCode {
+    #[serde(rename = "CO")]
+    ContractualObligation,
+    #[serde(rename = "PR")]
+    PatientResponsibility,
+    #[serde(rename = "OA")]
+    OtherAdjustment,
+    #[serde(rename = "PI")]
+    PayerInitiated,
+}
+
+impl GroupCode {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GroupCode::ContractualObligation => "CO",
+            GroupCode::PatientResponsibility => "PR",
+            GroupCode::OtherAdjustment => "OA",
+            GroupCode::PayerInitiated => "PI",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<GroupCode> {
+        match code {
+            "CO" => Some(GroupCode::ContractualObligation),
+            "PR" => Some(GroupCode::PatientResponsibility),
+            "OA" => Some(GroupCode::OtherAdjustment),
+            "PI" => Some(GroupCode::PayerInitiated),
+            _ => None,
+        }
+    }
+}
+
+/// One explained portion of the unpaid amount, e.g. PR-1 (deductible) or CO-45 (contractual).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Adjustment {
+    pub group_code: GroupCode,
+    /// Claim adjustment reason code (CARC)
+    pub reason_code: String,
+    pub amount: Money,
+}
+
+impl Adjustment {
+    pub fn new(group_code: GroupCode, reason_code: &str, amount: Money) -> Adjustment {
+        Adjustment { group_code, reason_code: reason_code.to_string(), amount }
+    }
+}
+
+// CARCs for the standard adjustment buckets
+pub const CARC_DEDUCTIBLE: &str = "1";
+pub const CARC_COINSURANCE: &str = "2";
+pub const CARC_COPAY: &str = "3";
+pub const CARC_CONTRACTUAL: &str = "45";
+/// The procedure code is inconsistent with the diagnosis
+pub const CARC_DIAGNOSIS_INCONSISTENT: &str = "11";
+/// The service isn't covered by the member's plan
+pub const CARC_NON_COVERED: &str = "96";
+/// The part of the billed amount earlier payers already paid or adjusted
+pub const CARC_PRIOR_PAYER: &str = "23";
+/// The line was billed after the payer's timely filing limit
+pub const CARC_TIMELY_FILING: &str = "29";
+
 impl ServiceLine {
     pub fn new(
         line: &IntakeServiceLine, 
-        billed_amount: f64, 
-        payer_paid_amount: f64, 
-        coinsurance_amount: f64, 
-        copay_amount: f64, 
-        deductible_amount: f64, 
-        not_allowed_amount: f64
-    ) -> Result<ServiceLine, String> {
+        billed_amount: Money, 
+        payer_paid_amount: Money, 
+        coinsurance_amount: Money, 
+        copay_amount: Money, 
+        deductible_amount: Money, 
+        not_allowed_amount: Money
+    ) -> Result<ServiceLine, InsuranceError> {
         if line.do_not_bill.unwrap_or(false) {
             return Ok(ServiceLine {
                 service_line_id: line.service_line_id.clone(),
                 procedure_code: line.procedure_code.clone(),
-                billed_amount: 0.0,
-                payer_paid_amount: 0.0,
-                coinsurance_amount: 0.0,
-                copay_amount: 0.0,
-                deductible_amount: 0.0,
-                not_allowed_amount: 0.0,
+                billed_amount: Money::ZERO,
+                payer_paid_amount: Money::ZERO,
+                coinsurance_amount: Money::ZERO,
+                copay_amount: Money::ZERO,
+                deductible_amount: Money::ZERO,
+                not_allowed_amount: Money::ZERO,
+                adjustments: Vec::new(),
                 remark_codes: None,
             });
         }
+
+        let buckets = [
+            ("billed_amount", billed_amount),
+            ("payer_paid_amount", payer_paid_amount),
+            ("coinsurance_amount", coinsurance_amount),
+            ("copay_amount", copay_amount),
+            ("deductible_amount", deductible_amount),
+            ("not_allowed_amount", not_allowed_amount),
+        ];
+        if let Some((field, amount)) = buckets.iter().find(|(_, amount)| *amount < Money::ZERO) {
+            return Err(InsuranceError::InvalidServiceLine {
+                service_line_id: line.service_line_id.clone(),
+                field: field.to_string(),
+                message: format!("must be a non-negative amount, got {}", amount),
+            });
+        }
+        let accounted = payer_paid_amount + coinsurance_amount + copay_amount + deductible_amount + not_allowed_amount;
+        if accounted != billed_amount {
+            return Err(InsuranceError::InvalidServiceLine {
+                service_line_id: line.service_line_id.clone(),
+                field: "billed_amount".to_string(),
+                message: format!("{} billed but {} paid, owed by the patient or not allowed", billed_amount, accounted),
+            });
+        }
+
+        // each non-zero bucket is explained by its standard group code and CARC
+        let adjustments = [
+            (GroupCode::PatientResponsibility, CARC_DEDUCTIBLE, deductible_amount),
+            (GroupCode::PatientResponsibility, CARC_COINSURANCE, coinsurance_amount),
+            (GroupCode::PatientResponsibility, CARC_COPAY, copay_amount),
+            (GroupCode::ContractualObligation, CARC_CONTRACTUAL, not_allowed_amount),
+        ]
+        .into_iter()
+        .filter(|(_, _, amount)| !amount.is_zero())
+        .map(|(group, reason, amount)| Adjustment::new(group, reason, amount))
+        .collect();
+
         Ok(ServiceLine {
             service_line_id: line.service_line_id.clone(),
             procedure_code: line.procedure_code.clone(),
-            billed_amount: billed_amount,
-            payer_paid_amount: payer_paid_amount,
-            coinsurance_amount: coinsurance_amount,
-            copay_amount: copay_amount,
-            deductible_amount: deductible_amount,
-            not_allowed_amount: not_allowed_amount,
+            billed_amount,
+            payer_paid_amount,
+            coinsurance_amount,
+            copay_amount,
+            deductible_amount,
+            not_allowed_amount,
+            adjustments,
             remark_codes: None,
         })
     }
+
+    /// A line the payer won't pay: the whole billed amount is written off as a contractual
+    /// obligation under `reason_code`, and nothing is the patient's responsibility.
+    pub fn denied(line: &IntakeServiceLine, billed_amount: Money, reason_code: &str) -> ServiceLine {
+        ServiceLine {
+            service_line_id: line.service_line_id.clone(),
+            procedure_code: line.procedure_code.clone(),
+            billed_amount,
+            payer_paid_amount: Money::ZERO,
+            coinsurance_amount: Money::ZERO,
+            copay_amount: Money::ZERO,
+            deductible_amount: Money::ZERO,
+            not_allowed_amount: billed_amount,
+            adjustments: vec![Adjustment::new(GroupCode::ContractualObligation, reason_code, billed_amount)],
+            remark_codes: None,
+        }
+    }
+
+    /// The same line with every amount and adjustment negated, as it appears on a reversal.
+    pub fn reversed(&self) -> ServiceLine {
+        ServiceLine {
+            billed_amount: -self.billed_amount,
+            payer_paid_amount: -self.payer_paid_amount,
+            coinsurance_amount: -self.coinsurance_amount,
+            copay_amount: -self.copay_amount,
+            deductible_amount: -self.deductible_amount,
+            not_allowed_amount: -self.not_allowed_amount,
+            adjustments: self
+                .adjustments
+                .iter()
+                .map(|a| Adjustment::new(a.group_code, &a.reason_code, -a.amount))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Moves `amount` of the line's contractual write-off to OA-23: on a crossover claim, the part
+    /// of the billed amount the earlier payers settled isn't this payer's write-off.
+    pub fn with_prior_payer_adjustment(mut self, amount: Money) -> ServiceLine {
+        if amount <= Money::ZERO {
+            return self;
+        }
+        if let Some(index) = self
+            .adjustments
+            .iter()
+            .position(|a| a.group_code == GroupCode::ContractualObligation && a.reason_code == CARC_CONTRACTUAL)
+        {
+            let contractual = self.adjustments[index].amount - amount;
+            if contractual.is_zero() {
+                self.adjustments.remove(index);
+            } else {
+                self.adjustments[index].amount = contractual;
+            }
+        }
+        self.adjustments.push(Adjustment::new(GroupCode::OtherAdjustment, CARC_PRIOR_PAYER, amount));
+        self
+    }
+
+    /// Attaches remittance advice remark codes (RARC); an empty list leaves the line without remarks.
+    pub fn with_remark_codes(mut self, remark_codes: Vec<String>) -> ServiceLine {
+        self.remark_codes = (!remark_codes.is_empty()).then_some(remark_codes);
+        self
+    }
 }
\ No newline at end of file
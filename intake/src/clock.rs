@@ -0,0 +1,125 @@
+use crate::IntakeError;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where the pipeline gets the current time and how it waits.
+///
+/// Claim receipt timestamps, simulated payer latency and AR aging all go through the same clock
+/// so that they stay consistent with each other when time is sped up or stopped.
+pub trait Clock: Send + Sync {
+    fn now_millis(&self) -> i64;
+    fn sleep(&self, duration: Duration);
+}
+
+/// Wall-clock time and real sleeps.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Zero-delay time: sleeps return immediately but move the clock forward by what they would have
+/// waited, so simulated payer latency still ages claims. `advance` moves it without sleeping.
+pub struct ManualClock {
+    now_millis: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(start_millis: i64) -> ManualClock {
+        ManualClock { now_millis: AtomicI64::new(start_millis) }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now_millis.fetch_add(duration.as_millis() as i64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> i64 {
+        self.now_millis.load(Ordering::SeqCst)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Simulated time running `speedup` times faster than real time, starting from `origin_millis`.
+/// A 20 second payer response takes 20 / `speedup` real seconds but still ages a claim by 20
+/// simulated seconds.
+pub struct AcceleratedClock {
+    origin_millis: i64,
+    started: Instant,
+    speedup: f64,
+}
+
+impl AcceleratedClock {
+    pub fn new(speedup: f64) -> Result<AcceleratedClock, IntakeError> {
+        AcceleratedClock::starting_at(chrono::Utc::now().timestamp_millis(), speedup)
+    }
+
+    pub fn starting_at(origin_millis: i64, speedup: f64) -> Result<AcceleratedClock, IntakeError> {
+        if !(speedup.is_finite() && speedup > 0.0) {
+            return Err(IntakeError::config("clock", format!("Invalid clock speedup: {}", speedup)));
+        }
+        Ok(AcceleratedClock { origin_millis, started: Instant::now(), speedup })
+    }
+}
+
+impl Clock for AcceleratedClock {
+    fn now_millis(&self) -> i64 {
+        self.origin_millis + (self.started.elapsed().as_secs_f64() * self.speedup * 1000.0) as i64
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration.div_f64(self.speedup));
+    }
+}
+
+/// The `--clock` setting: `realtime`, `instant` or an accelerated rate such as `60x`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClockMode {
+    RealTime,
+    Instant,
+    Accelerated(f64),
+}
+
+impl ClockMode {
+    pub fn clock(&self) -> Result<Arc<dyn Clock>, IntakeError> {
+        Ok(match self {
+            ClockMode::RealTime => Arc::new(SystemClock),
+            ClockMode::Instant => Arc::new(ManualClock::new(chrono::Utc::now().timestamp_millis())),
+            ClockMode::Accelerated(speedup) => Arc::new(AcceleratedClock::new(*speedup)?),
+        })
+    }
+}
+
+impl FromStr for ClockMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "realtime" => Ok(ClockMode::RealTime),
+            "instant" => Ok(ClockMode::Instant),
+            other => {
+                let speedup: f64 = other
+                    .strip_suffix('x')
+                    .and_then(|rate| rate.parse().ok())
+                    .ok_or_else(|| format!("Unknown clock: {} (expected realtime, instant or e.g. 60x)", s))?;
+                if !(speedup.is_finite() && speedup > 0.0) {
+                    return Err(format!("Invalid clock speedup: {}", speedup));
+                }
+                Ok(ClockMode::Accelerated(speedup))
+            }
+        }
+    }
+}
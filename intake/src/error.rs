@@ -0,0 +1,48 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntakeError {
+    /// Invalid command-line arguments or settings
+    Config { field: String, message: String },
+    /// The input file couldn't be opened or read
+    Io { path: String, message: String },
+    /// Reading failed partway through the file; `offset` is where the line it stopped at starts
+    Read { path: String, line_number: usize, offset: u64, message: String },
+    /// A claim couldn't be parsed. `claim_id` and `field` are filled in when the parser got far
+    /// enough to know them.
+    Parse { claim_id: Option<String>, field: Option<String>, message: String },
+}
+
+impl IntakeError {
+    pub fn config(field: &str, message: impl Into<String>) -> IntakeError {
+        IntakeError::Config { field: field.to_string(), message: message.into() }
+    }
+
+    /// Only I/O failures can succeed on a second try; bad arguments and malformed claims won't.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, IntakeError::Io { .. } | IntakeError::Read { .. })
+    }
+
+    pub fn claim_id(&self) -> Option<&str> {
+        match self {
+            IntakeError::Parse { claim_id, .. } => claim_id.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IntakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntakeError::Config { message, .. } => write!(f, "{}", message),
+            IntakeError::Io { path, message } => write!(f, "Failed to open file {}: {}", path, message),
+            IntakeError::Read { path, line_number, offset, message } => {
+                write!(f, "Failed to read {} at line {} (byte {}): {}", path, line_number, offset, message)
+            }
+            IntakeError::Parse { claim_id: Some(claim_id), message, .. } => write!(f, "Claim {}: {}", claim_id, message),
+            IntakeError::Parse { claim_id: None, message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for IntakeError {}
@@ -1,12 +1,56 @@
 pub mod types;
+pub mod x12;
+pub mod clock;
+pub mod error;
+pub mod place_of_service;
+pub mod money;
+pub mod stream;
 
 // Re-export all types for easier access from other crates
 pub use types::*;
+pub use x12::parse_837p;
+pub use clock::{AcceleratedClock, Clock, ClockMode, ManualClock, SystemClock};
+pub use error::IntakeError;
+pub use money::Money;
+pub use stream::{Line, LinePosition, LineReader, ResumePoint, MAX_LINE_BYTES};
+pub use place_of_service::{place_of_service, PlaceOfService, PLACES_OF_SERVICE};
 
 use std::time::Instant;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use serde_json;
+use std::io::BufReader;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    /// One JSON `PayerClaim` per line
+    Json,
+    /// ANSI X12 837P interchange
+    X12,
+}
+
+impl InputFormat {
+    pub fn from_path(file_path: &str) -> InputFormat {
+        let extension = std::path::Path::new(file_path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("837" | "x12" | "edi") => InputFormat::X12,
+            _ => InputFormat::Json,
+        }
+    }
+}
+
+impl std::str::FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(InputFormat::Json),
+            "x12" | "837" | "837p" => Ok(InputFormat::X12),
+            other => Err(format!("Unknown input format: {}", other)),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Config {
@@ -14,36 +58,144 @@ pub struct Config {
     pub rate_per_second: u32,
     pub refill_rate: u32,
     pub num_threads: u32,
+    pub input_format: InputFormat,
+    pub seed: Option<u64>,
+    pub clock: ClockMode,
+    /// Chance (0-1) that a payer is unavailable when a claim is submitted
+    pub outage_rate: f64,
+    /// Month and day the plans' deductibles and out-of-pocket maximums reset on; January 1 when unset
+    pub plan_year_start: Option<(u32, u32)>,
+    /// NPPES-style registry extract used to check NPI entity types
+    pub npi_registry: Option<String>,
+    /// Modifier table replacing the bundled one
+    pub modifier_table: Option<String>,
+    /// Procedure code list; when set, codes missing from it are rejected
+    pub procedure_codes: Option<String>,
+    /// File of previously adjudicated claims, used to catch resubmissions across runs
+    pub duplicate_store: Option<String>,
+    /// Payer registry adding payers and aliases to the built-in simulators
+    pub payers: Option<String>,
+    /// Member roster the payers check coverage against
+    pub roster: Option<String>,
+    /// Exchange rates for converting claims into their payer's settlement currency
+    pub exchange_rates: Option<String>,
+    /// Byte offset to start reading JSON lines from, as logged by a run that stopped partway
+    pub resume_offset: Option<u64>,
 }
 
 impl Config {
-    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Config, String> {
+    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Config, IntakeError> {
         args.next();
 
+        // flags may appear anywhere; everything else is positional
+        let mut input_format = None;
+        let mut seed = None;
+        let mut clock = ClockMode::RealTime;
+        let mut outage_rate = 0.0;
+        let mut plan_year_start = None;
+        let mut npi_registry = None;
+        let mut modifier_table = None;
+        let mut procedure_codes = None;
+        let mut duplicate_store = None;
+        let mut payers = None;
+        let mut roster = None;
+        let mut exchange_rates = None;
+        let mut resume_offset = None;
+        let mut positional = Vec::new();
+        for arg in args {
+            if let Some(value) = arg.strip_prefix("--format=") {
+                input_format = Some(value.parse().map_err(|e: String| IntakeError::config("format", e))?);
+            } else if let Some(value) = arg.strip_prefix("--clock=") {
+                clock = value.parse().map_err(|e: String| IntakeError::config("clock", e))?;
+            } else if let Some(value) = arg.strip_prefix("--outage-rate=") {
+                outage_rate = value
+                    .parse()
+                    .ok()
+                    .filter(|rate| (0.0..=1.0).contains(rate))
+                    .ok_or_else(|| IntakeError::config("outage_rate", format!("Invalid outage rate: {} (expected 0-1)", value)))?;
+            } else if let Some(value) = arg.strip_prefix("--plan-year-start=") {
+                // any year works for checking the day exists; 2001 wasn't a leap year, so Feb 29 doesn't
+                let month_day = value
+                    .split_once('-')
+                    .and_then(|(month, day)| Some((month.parse().ok()?, day.parse().ok()?)))
+                    .filter(|&(month, day)| chrono::NaiveDate::from_ymd_opt(2001, month, day).is_some());
+                plan_year_start = Some(month_day.ok_or_else(|| {
+                    IntakeError::config("plan_year_start", format!("Invalid plan year start: {} (expected MM-DD)", value))
+                })?);
+            } else if let Some(value) = arg.strip_prefix("--npi-registry=") {
+                npi_registry = Some(value.to_string());
+            } else if let Some(value) = arg.strip_prefix("--modifier-table=") {
+                modifier_table = Some(value.to_string());
+            } else if let Some(value) = arg.strip_prefix("--procedure-codes=") {
+                procedure_codes = Some(value.to_string());
+            } else if let Some(value) = arg.strip_prefix("--duplicate-store=") {
+                duplicate_store = Some(value.to_string());
+            } else if let Some(value) = arg.strip_prefix("--payers=") {
+                payers = Some(value.to_string());
+            } else if let Some(value) = arg.strip_prefix("--roster=") {
+                roster = Some(value.to_string());
+            } else if let Some(value) = arg.strip_prefix("--exchange-rates=") {
+                exchange_rates = Some(value.to_string());
+            } else if let Some(value) = arg.strip_prefix("--resume-from=") {
+                resume_offset = Some(value.parse().map_err(|e| IntakeError::config("resume_from", format!("Invalid resume offset: {}", e)))?);
+            } else if let Some(value) = arg.strip_prefix("--seed=") {
+                seed = Some(value.parse().map_err(|e| IntakeError::config("seed", format!("Invalid seed: {}", e)))?);
+            } else if arg.starts_with("--") {
+                return Err(IntakeError::config(&arg, format!("Unknown flag: {}", arg)));
+            } else {
+                positional.push(arg);
+            }
+        }
+        let mut args = positional.into_iter();
+
         let file_path = match args.next() {
             Some(arg) => arg,
-            None => return Err("Didn't get a file path".to_string()),
+            None => return Err(IntakeError::config("file_path", "Didn't get a file path")),
         };
 
         let refill_rate = match args.next() {
-            Some(arg) => arg.parse().map_err(|e| format!("Invalid refill rate: {}", e))?,
-            None => return Err("Didn't get a refill rate".to_string()),
+            Some(arg) => arg.parse().map_err(|e| IntakeError::config("refill_rate", format!("Invalid refill rate: {}", e)))?,
+            None => return Err(IntakeError::config("refill_rate", "Didn't get a refill rate")),
         };
 
         let rate_per_second = match args.next() {
-            Some(arg) => arg.parse().map_err(|e| format!("Invalid rate per second: {}", e))?,
-            None => return Err("Didn't get a rate per second".to_string()),
+            Some(arg) => arg.parse().map_err(|e| IntakeError::config("rate_per_second", format!("Invalid rate per second: {}", e)))?,
+            None => return Err(IntakeError::config("rate_per_second", "Didn't get a rate per second")),
         };
 
         let num_threads = match args.next() {
-            Some(arg) => arg.parse().map_err(|e| format!("Invalid number of threads: {}", e))?,
+            Some(arg) => arg.parse().map_err(|e| IntakeError::config("num_threads", format!("Invalid number of threads: {}", e)))?,
             None => {
                 eprintln!("No number of threads provided, using default of 1");
                 1
             },
         };
 
-        Ok(Config { file_path, rate_per_second, refill_rate, num_threads })
+        let input_format = input_format.unwrap_or_else(|| InputFormat::from_path(&file_path));
+        // an 837P interchange is parsed as a whole, so there's no line to pick up from
+        if resume_offset.is_some() && input_format == InputFormat::X12 {
+            return Err(IntakeError::config("resume_from", "Can only resume reading JSON lines input"));
+        }
+
+        Ok(Config {
+            file_path,
+            rate_per_second,
+            refill_rate,
+            num_threads,
+            input_format,
+            seed,
+            clock,
+            outage_rate,
+            plan_year_start,
+            npi_registry,
+            modifier_table,
+            procedure_codes,
+            duplicate_store,
+            payers,
+            roster,
+            exchange_rates,
+            resume_offset,
+        })
     }
 }
 
@@ -89,18 +241,43 @@ impl TokenBucket {
     }
 }
 
-pub fn read_file(config: &Config) -> Result<impl Iterator<Item = String>, String> {
-    let file = File::open(&config.file_path).map_err(|e| format!("Failed to open file: {}", e))?;
-    let reader = BufReader::new(file);
-    Ok(reader
-        .lines()
-        .map(|line| line.unwrap_or_else(|_| "".to_string()))
-    )
+/// Opens the file for reading line by line, from `resume_offset` when one is set.
+pub fn read_file(config: &Config) -> Result<LineReader<BufReader<File>>, IntakeError> {
+    let file = File::open(&config.file_path).map_err(|e| io_error(config, e))?;
+    let reader = LineReader::new(BufReader::new(file), &config.file_path);
+    match config.resume_offset {
+        Some(offset) => reader.resume_from(offset),
+        None => Ok(reader),
+    }
 }
 
-pub fn parse_line(line: &str) -> Result<PayerClaim, String> {
-    let mut claim: PayerClaim = serde_json::from_str(line).map_err(|e| format!("Failed to parse line: {}", e))?;
+pub fn parse_line(line: &str) -> Result<PayerClaim, IntakeError> {
+    let mut claim: PayerClaim = serde_json::from_str(line).map_err(|e| json_error(line, e))?;
     claim.initial_claim_ts = chrono::Utc::now().timestamp_millis();
     Ok(claim)
 }
 
+pub fn read_x12_file(config: &Config) -> Result<Vec<PayerClaim>, IntakeError> {
+    let contents = std::fs::read_to_string(&config.file_path).map_err(|e| io_error(config, e))?;
+    parse_837p(&contents)
+}
+
+fn io_error(config: &Config, e: std::io::Error) -> IntakeError {
+    IntakeError::Io { path: config.file_path.clone(), message: e.to_string() }
+}
+
+// serde only reports a line/column, so pull out what the submitter needs to find the problem:
+// the claim (if the line is at least valid JSON) and the field serde complained about
+fn json_error(line: &str, e: serde_json::Error) -> IntakeError {
+    let claim_id = serde_json::from_str::<serde_json::Value>(line)
+        .ok()
+        .and_then(|value| value.get("claim_id")?.as_str().map(str::to_string));
+    let message = e.to_string();
+    let field = ["missing field `", "unknown field `", "in field `"]
+        .iter()
+        .find_map(|prefix| message.split_once(prefix))
+        .and_then(|(_, rest)| rest.split_once('`'))
+        .map(|(field, _)| field.to_string());
+
+    IntakeError::Parse { claim_id, field, message: format!("Failed to parse line: {}", message) }
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// An amount of money, held in whole cents so sums are exact.
+///
+/// Adding, subtracting and multiplying by a unit count never round. Taking a share of an amount
+/// (`times`) rounds to the nearest cent, halves away from zero; take one share and subtract it
+/// from the whole to get the other, so the two always add back up to the cent. Amounts are read
+/// and written as decimal numbers (`123.45`); input with a fraction of a cent is rejected rather
+/// than rounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+// rates are applied as parts per million, which covers any rate written with six decimal places
+const RATE_SCALE: i128 = 1_000_000;
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+    /// More than any real amount; stands in for a limit a plan doesn't have.
+    pub const MAX: Money = Money(i64::MAX);
+
+    pub const fn from_cents(cents: i64) -> Money {
+        Money(cents)
+    }
+
+    /// Converts a dollar amount, rounding to the nearest cent.
+    pub fn from_dollars(dollars: f64) -> Money {
+        Money((dollars * 100.0).round() as i64)
+    }
+
+    pub fn cents(self) -> i64 {
+        self.0
+    }
+
+    /// For statistics and display only; amounts should be added up as `Money`.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// A per-unit amount times `units`, or `None` if the total is too large to represent.
+    pub fn checked_mul(self, units: i32) -> Option<Money> {
+        self.0.checked_mul(i64::from(units)).map(Money)
+    }
+
+    /// `rate` of this amount (0.8 for 80%), rounded to the nearest cent, halves away from zero.
+    pub fn times(self, rate: f64) -> Money {
+        let rate = (rate * RATE_SCALE as f64).round() as i128;
+        let product = i128::from(self.0) * rate;
+        let half = RATE_SCALE / 2;
+        let rounded = if product >= 0 { (product + half) / RATE_SCALE } else { (product - half) / RATE_SCALE };
+        Money(rounded as i64)
+    }
+
+    /// One of `parts` equal shares of the amount (an average), rounded to the nearest cent,
+    /// halves away from zero; dividing by zero gives zero.
+    pub fn divided_by(self, parts: usize) -> Money {
+        let parts = parts as i128;
+        if parts == 0 {
+            return Money::ZERO;
+        }
+        let amount = i128::from(self.0);
+        let rounded = if amount >= 0 { (2 * amount + parts) / (2 * parts) } else { (2 * amount - parts) / (2 * parts) };
+        Money(rounded as i64)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+/// A per-unit charge times a number of units.
+impl Mul<i32> for Money {
+    type Output = Money;
+
+    fn mul(self, units: i32) -> Money {
+        Money(self.0 * i64::from(units))
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+/// Parses a decimal amount such as `123.45`, `-5.5` or `100`, exactly.
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Money, String> {
+        let invalid = || format!("invalid amount {}", s);
+        let (negative, digits) = match s.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.trim()),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if (whole.is_empty() && fraction.is_empty())
+            || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if fraction.len() > 2 {
+            return Err(format!("amount {} has a fraction of a cent", s));
+        }
+        let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid())? };
+        let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+        let cents = whole.checked_mul(100).and_then(|c| c.checked_add(fraction)).ok_or_else(invalid)?;
+        Ok(Money(if negative { -cents } else { cents }))
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        let dollars = f64::deserialize(deserializer)?;
+        let cents = dollars * 100.0;
+        // JSON numbers are binary floats, so 0.1 arrives as 0.1000000000000000055...; anything
+        // further than that from a whole cent was written with more than two decimal places
+        if !cents.is_finite() || (cents - cents.round()).abs() > 1e-6 {
+            return Err(serde::de::Error::custom(format!("amount {} has a fraction of a cent", dollars)));
+        }
+        Ok(Money(cents.round() as i64))
+    }
+}
@@ -0,0 +1,75 @@
+/// A CMS place-of-service code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaceOfService {
+    pub code: i32,
+    pub name: &'static str,
+    /// Whether the physician fee schedule pays the facility rate here (the facility bills its own
+    /// overhead separately), as opposed to the higher non-facility rate
+    pub facility: bool,
+}
+
+const fn pos(code: i32, name: &'static str, facility: bool) -> PlaceOfService {
+    PlaceOfService { code, name, facility }
+}
+
+/// Every assigned code in the CMS place-of-service code set. Numbers missing from the list
+/// (28-30, 35-40, 43-48, 59, 63-64, 67-70, 73-80, 82-98) are unassigned.
+pub const PLACES_OF_SERVICE: &[PlaceOfService] = &[
+    pos(1, "Pharmacy", false),
+    pos(2, "Telehealth Provided Other than in Patient's Home", true),
+    pos(3, "School", false),
+    pos(4, "Homeless Shelter", false),
+    pos(5, "Indian Health Service Free-standing Facility", false),
+    pos(6, "Indian Health Service Provider-based Facility", false),
+    pos(7, "Tribal 638 Free-standing Facility", false),
+    pos(8, "Tribal 638 Provider-based Facility", false),
+    pos(9, "Prison/Correctional Facility", false),
+    pos(10, "Telehealth Provided in Patient's Home", false),
+    pos(11, "Office", false),
+    pos(12, "Home", false),
+    pos(13, "Assisted Living Facility", false),
+    pos(14, "Group Home", false),
+    pos(15, "Mobile Unit", false),
+    pos(16, "Temporary Lodging", false),
+    pos(17, "Walk-in Retail Health Clinic", false),
+    pos(18, "Place of Employment-Worksite", false),
+    pos(19, "Off Campus-Outpatient Hospital", true),
+    pos(20, "Urgent Care Facility", false),
+    pos(21, "Inpatient Hospital", true),
+    pos(22, "On Campus-Outpatient Hospital", true),
+    pos(23, "Emergency Room-Hospital", true),
+    pos(24, "Ambulatory Surgical Center", true),
+    pos(25, "Birthing Center", false),
+    pos(26, "Military Treatment Facility", true),
+    pos(27, "Outreach Site/Street", false),
+    pos(31, "Skilled Nursing Facility", true),
+    pos(32, "Nursing Facility", false),
+    pos(33, "Custodial Care Facility", false),
+    pos(34, "Hospice", true),
+    pos(41, "Ambulance-Land", true),
+    pos(42, "Ambulance-Air or Water", true),
+    pos(49, "Independent Clinic", false),
+    pos(50, "Federally Qualified Health Center", false),
+    pos(51, "Inpatient Psychiatric Facility", true),
+    pos(52, "Psychiatric Facility-Partial Hospitalization", true),
+    pos(53, "Community Mental Health Center", true),
+    pos(54, "Intermediate Care Facility/Individuals with Intellectual Disabilities", false),
+    pos(55, "Residential Substance Abuse Treatment Facility", false),
+    pos(56, "Psychiatric Residential Treatment Center", true),
+    pos(57, "Non-residential Substance Abuse Treatment Facility", false),
+    pos(58, "Non-residential Opioid Treatment Facility", false),
+    pos(60, "Mass Immunization Center", false),
+    pos(61, "Comprehensive Inpatient Rehabilitation Facility", true),
+    pos(62, "Comprehensive Outpatient Rehabilitation Facility", false),
+    pos(65, "End-Stage Renal Disease Treatment Facility", false),
+    pos(66, "Programs of All-Inclusive Care for the Elderly (PACE) Center", false),
+    pos(71, "Public Health Clinic", false),
+    pos(72, "Rural Health Clinic", false),
+    pos(81, "Independent Laboratory", false),
+    pos(99, "Other Place of Service", false),
+];
+
+/// Looks up an assigned place-of-service code.
+pub fn place_of_service(code: i32) -> Option<&'static PlaceOfService> {
+    PLACES_OF_SERVICE.iter().find(|place| place.code == code)
+}
@@ -0,0 +1,191 @@
+use crate::IntakeError;
+use std::collections::BTreeSet;
+use std::io::{BufRead, Read};
+
+/// Longest line the reader holds in memory. A claim is a few kilobytes of JSON, so a longer line
+/// is reported as a parse error and skipped rather than read in whole.
+pub const MAX_LINE_BYTES: usize = 1 << 20;
+
+/// Where a line sits in the input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinePosition {
+    /// Counted from 1 at the start of the file, including when reading resumed partway through
+    pub line_number: usize,
+    /// Byte offset the line starts at
+    pub offset: u64,
+    /// Byte offset the next line starts at
+    pub next_offset: u64,
+}
+
+/// One line of the input file, without its line ending.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub position: LinePosition,
+    pub text: String,
+}
+
+/// Reads a file one line at a time, so only the current line is held in memory however large the
+/// file is.
+///
+/// A line that isn't UTF-8 or is longer than `MAX_LINE_BYTES` comes back as a parse error and
+/// reading carries on with the next one. A failed read comes back as `IntakeError::Read`, naming
+/// the line it stopped at, and ends the iteration.
+pub struct LineReader<R> {
+    reader: R,
+    path: String,
+    line_number: usize,
+    offset: u64,
+    buffer: Vec<u8>,
+    failed: bool,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R, path: &str) -> LineReader<R> {
+        LineReader { reader, path: path.to_string(), line_number: 0, offset: 0, buffer: Vec::new(), failed: false }
+    }
+
+    /// Skips ahead to `offset`, which has to be where a line starts. The lines skipped are counted,
+    /// not kept, so line numbers still match the file.
+    pub fn resume_from(mut self, offset: u64) -> Result<LineReader<R>, IntakeError> {
+        let mut last_byte = b'\n';
+        while self.offset < offset {
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(e) => return Err(self.read_error(e.to_string())),
+            };
+            if chunk.is_empty() {
+                return Err(IntakeError::config("resume_from", format!("Resume offset {} is past the end of {}", offset, self.path)));
+            }
+            let length = chunk.len().min(usize::try_from(offset - self.offset).unwrap_or(usize::MAX));
+            let chunk = &chunk[..length];
+            self.line_number += chunk.iter().filter(|&&byte| byte == b'\n').count();
+            last_byte = chunk[length - 1];
+            self.reader.consume(length);
+            self.offset += length as u64;
+        }
+        if last_byte != b'\n' {
+            return Err(IntakeError::config(
+                "resume_from",
+                format!("Resume offset {} is partway through line {} of {}", offset, self.line_number + 1, self.path),
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Number of the last line read, or of the last line skipped when resuming.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Byte offset the next line starts at.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn read_error(&self, message: String) -> IntakeError {
+        IntakeError::Read { path: self.path.clone(), line_number: self.line_number + 1, offset: self.offset, message }
+    }
+
+    // drops the rest of an overlong line a buffer at a time, returning how many bytes it had left
+    fn skip_line(&mut self) -> std::io::Result<u64> {
+        let mut skipped = 0;
+        loop {
+            let chunk = self.reader.fill_buf()?;
+            if chunk.is_empty() {
+                return Ok(skipped);
+            }
+            let (length, done) = match chunk.iter().position(|&byte| byte == b'\n') {
+                Some(index) => (index + 1, true),
+                None => (chunk.len(), false),
+            };
+            self.reader.consume(length);
+            skipped += length as u64;
+            if done {
+                return Ok(skipped);
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = Result<Line, IntakeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        self.buffer.clear();
+        let limit = MAX_LINE_BYTES as u64 + 1;
+        let mut length = match self.reader.by_ref().take(limit).read_until(b'\n', &mut self.buffer) {
+            Ok(0) => return None,
+            Ok(length) => length as u64,
+            Err(e) => {
+                self.failed = true;
+                return Some(Err(self.read_error(e.to_string())));
+            }
+        };
+        let overlong = self.buffer.len() > MAX_LINE_BYTES && self.buffer.last() != Some(&b'\n');
+        if overlong {
+            match self.skip_line() {
+                Ok(skipped) => length += skipped,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(self.read_error(e.to_string())));
+                }
+            }
+        }
+
+        self.line_number += 1;
+        let position = LinePosition { line_number: self.line_number, offset: self.offset, next_offset: self.offset + length };
+        self.offset = position.next_offset;
+
+        let parse_error = |message: String| IntakeError::Parse { claim_id: None, field: None, message };
+        if overlong {
+            return Some(Err(parse_error(format!("Line {} is longer than {} bytes", position.line_number, MAX_LINE_BYTES))));
+        }
+        if self.buffer.ends_with(b"\n") {
+            self.buffer.pop();
+            if self.buffer.ends_with(b"\r") {
+                self.buffer.pop();
+            }
+        }
+        match String::from_utf8(std::mem::take(&mut self.buffer)) {
+            Ok(text) => Some(Ok(Line { position, text })),
+            Err(e) => Some(Err(parse_error(format!("Line {} is not valid UTF-8: {}", position.line_number, e.utf8_error())))),
+        }
+    }
+}
+
+/// Tracks how far through the file a run has got, as the offset a rerun can resume from without
+/// skipping a claim: the start of the earliest line whose claim is still being processed, or the
+/// end of the last line read when none is.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResumePoint {
+    in_flight: BTreeSet<u64>,
+    read_to: u64,
+}
+
+impl ResumePoint {
+    pub fn new(offset: u64) -> ResumePoint {
+        ResumePoint { in_flight: BTreeSet::new(), read_to: offset }
+    }
+
+    /// The line's claim is being processed; it holds the resume point back until `finished`.
+    pub fn started(&mut self, position: &LinePosition) {
+        self.in_flight.insert(position.offset);
+        self.read_to = self.read_to.max(position.next_offset);
+    }
+
+    /// The line needs no more work, e.g. because it didn't parse.
+    pub fn skipped(&mut self, position: &LinePosition) {
+        self.read_to = self.read_to.max(position.next_offset);
+    }
+
+    pub fn finished(&mut self, offset: u64) {
+        self.in_flight.remove(&offset);
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.in_flight.first().copied().unwrap_or(self.read_to)
+    }
+}
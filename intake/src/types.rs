@@ -1,4 +1,6 @@
-use serde::{Serialize, Deserialize};
+use crate::Money;
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PayerClaim {
@@ -8,23 +10,275 @@ pub struct PayerClaim {
     pub patient: Patient,
     pub organization: Organization,
     pub rendering_provider: RenderingProvider,
+    /// ICD-10-CM codes for the claim; service lines point into this list
+    #[serde(default)]
+    pub diagnoses: Vec<Diagnosis>,
     pub service_lines: Vec<ServiceLine>,
+    /// Whether this is a new claim or corrects one the payer already adjudicated
+    #[serde(default)]
+    pub frequency_code: ClaimFrequency,
+    /// The payer's claim control number for the claim being replaced or voided (CLP07 on its
+    /// remittance); required for frequency codes 7 and 8
+    #[serde(default)]
+    pub payer_claim_control_number: Option<String>,
+    /// Coverages that pay after `insurance`, in order (secondary, then tertiary)
+    #[serde(default)]
+    pub other_insurance: Vec<Insurance>,
+    /// What earlier payers paid and adjusted, in the order they paid; set on a claim crossed over
+    /// to a secondary payer (the 837P 2320 and 2430 loops)
+    #[serde(default)]
+    pub prior_payments: Vec<PriorPayment>,
     #[serde(skip_deserializing, default)]
     pub initial_claim_ts: i64,
 }
 
+impl PayerClaim {
+    /// The diagnoses a service line points to, in pointer order. Out-of-range pointers are
+    /// skipped; validation reports them.
+    pub fn line_diagnoses<'a>(&'a self, line: &'a ServiceLine) -> impl Iterator<Item = &'a Diagnosis> + 'a {
+        line.diagnosis_pointers
+            .iter()
+            .filter_map(|&pointer| (pointer as usize).checked_sub(1).and_then(|i| self.diagnoses.get(i)))
+    }
+
+    /// The day the clearinghouse received the claim.
+    pub fn received_date(&self) -> NaiveDate {
+        chrono::DateTime::from_timestamp_millis(self.initial_claim_ts)
+            .map(|dt| dt.date_naive())
+            .unwrap_or_default()
+    }
+
+    /// The first day of service on the claim. Claims whose lines don't carry dates of service
+    /// count as received on the day they were provided.
+    pub fn date_of_service(&self) -> NaiveDate {
+        self.service_lines
+            .iter()
+            .filter_map(|line| line.service_date_from)
+            .min()
+            .unwrap_or_else(|| self.received_date())
+    }
+
+    /// The patient's age in whole years on the claim's date of service.
+    pub fn patient_age(&self) -> u32 {
+        self.patient.age_on(self.date_of_service())
+    }
+
+    /// The first day of service on a line, or the claim's received date when it has none.
+    pub fn line_date_of_service(&self, line: &ServiceLine) -> NaiveDate {
+        line.service_date_from.unwrap_or_else(|| self.received_date())
+    }
+
+    /// Every coverage on the claim in the order the payers pay: `insurance` first.
+    pub fn coverages(&self) -> impl Iterator<Item = &Insurance> {
+        std::iter::once(&self.insurance).chain(&self.other_insurance)
+    }
+
+    /// How the most recent earlier payer adjudicated a service line, on a crossover claim.
+    pub fn prior_line_payment(&self, line: &ServiceLine) -> Option<&PriorLinePayment> {
+        self.prior_payments
+            .last()?
+            .service_lines
+            .iter()
+            .find(|prior| prior.service_line_id == line.service_line_id)
+    }
+}
+
+/// An earlier payer's adjudication of the claim, carried on the claim sent to the next payer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PriorPayment {
+    pub payer_id: PayerId,
+    /// The earlier payer's claim control number (its remittance id)
+    pub payer_claim_control_number: String,
+    pub service_lines: Vec<PriorLinePayment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PriorLinePayment {
+    pub service_line_id: String,
+    pub paid_amount: Money,
+    /// The earlier payer's CAS adjustments on the line
+    #[serde(default)]
+    pub adjustments: Vec<PriorAdjustment>,
+}
+
+impl PriorLinePayment {
+    /// What the earlier payer left the patient owing: its PR adjustments.
+    pub fn patient_responsibility(&self) -> Money {
+        self.adjustments.iter().filter(|a| a.group_code == "PR").map(|a| a.amount).sum()
+    }
+}
+
+/// One CAS adjustment from an earlier payer, e.g. `PR` `2` for coinsurance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PriorAdjustment {
+    pub group_code: String,
+    pub reason_code: String,
+    pub amount: Money,
+}
+
+/// X12 claim frequency type code (CLM05-3)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClaimFrequency {
+    #[default]
+    #[serde(rename = "1")]
+    Original,
+    /// Replaces an adjudicated claim: the payer reverses it and adjudicates this one instead
+    #[serde(rename = "7")]
+    Replacement,
+    /// Voids (cancels) an adjudicated claim: the payer reverses it
+    #[serde(rename = "8")]
+    Void,
+}
+
+impl ClaimFrequency {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ClaimFrequency::Original => "1",
+            ClaimFrequency::Replacement => "7",
+            ClaimFrequency::Void => "8",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<ClaimFrequency> {
+        match code {
+            "1" => Some(ClaimFrequency::Original),
+            "7" => Some(ClaimFrequency::Replacement),
+            "8" => Some(ClaimFrequency::Void),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Insurance {
     pub payer_id: PayerId,
+    /// The id on the subscriber's card, which dependents share
     pub patient_member_id: String,
+    /// Group or policy number (SBR03)
+    #[serde(default)]
+    pub group_number: Option<String>,
+    /// The patient's relationship to the subscriber (SBR02, or PAT01 for dependents)
+    #[serde(default)]
+    pub relationship: Relationship,
+    /// The policyholder, when the patient is their dependent
+    #[serde(default)]
+    pub subscriber: Option<Subscriber>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// X12 individual relationship code: who the patient is to the subscriber
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Relationship {
+    /// The patient is the subscriber
+    #[default]
+    #[serde(rename = "18")]
+    SelfInsured,
+    #[serde(rename = "01")]
+    Spouse,
+    #[serde(rename = "19")]
+    Child,
+    #[serde(rename = "20")]
+    Employee,
+    #[serde(rename = "21")]
+    Unknown,
+    #[serde(rename = "39")]
+    OrganDonor,
+    #[serde(rename = "40")]
+    CadaverDonor,
+    #[serde(rename = "53")]
+    LifePartner,
+    #[serde(rename = "G8")]
+    Other,
+}
+
+impl Relationship {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Relationship::SelfInsured => "18",
+            Relationship::Spouse => "01",
+            Relationship::Child => "19",
+            Relationship::Employee => "20",
+            Relationship::Unknown => "21",
+            Relationship::OrganDonor => "39",
+            Relationship::CadaverDonor => "40",
+            Relationship::LifePartner => "53",
+            Relationship::Other => "G8",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Relationship> {
+        match code {
+            "18" => Some(Relationship::SelfInsured),
+            "01" => Some(Relationship::Spouse),
+            "19" => Some(Relationship::Child),
+            "20" => Some(Relationship::Employee),
+            "21" => Some(Relationship::Unknown),
+            "39" => Some(Relationship::OrganDonor),
+            "40" => Some(Relationship::CadaverDonor),
+            "53" => Some(Relationship::LifePartner),
+            "G8" => Some(Relationship::Other),
+            _ => None,
+        }
+    }
+}
+
+/// The person who holds the policy a dependent patient is covered under.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Subscriber {
+    pub first_name: String,
+    pub last_name: String,
+    #[serde(default, deserialize_with = "optional_dob")]
+    pub dob: Option<NaiveDate>,
+    pub gender: Option<Gender>,
+}
+
+/// The payer a claim is billed to. The three simulated payers have their own variants; any
+/// other id is carried as written and resolved by the clearinghouse's payer registry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PayerId {
     Medicare,
     UnitedHealthGroup,
     Anthem,
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl PayerId {
+    /// Parses a payer id as written in claim files, recognizing the built-in payers.
+    pub fn new(id: &str) -> PayerId {
+        match id {
+            "medicare" => PayerId::Medicare,
+            "united_health_group" => PayerId::UnitedHealthGroup,
+            "anthem" => PayerId::Anthem,
+            other => PayerId::Other(other.to_string()),
+        }
+    }
+
+    /// The id as written in claim files, e.g. `united_health_group`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            PayerId::Medicare => "medicare",
+            PayerId::UnitedHealthGroup => "united_health_group",
+            PayerId::Anthem => "anthem",
+            PayerId::Other(id) => id,
+        }
+    }
+
+    /// The name the payer goes by on remittances, e.g. `UnitedHealthGroup`.
+    pub fn name(&self) -> &str {
+        match self {
+            PayerId::Medicare => "Medicare",
+            PayerId::UnitedHealthGroup => "UnitedHealthGroup",
+            PayerId::Anthem => "Anthem",
+            PayerId::Other(id) => id,
+        }
+    }
+}
+
+impl std::fmt::Display for PayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,12 +286,37 @@ pub struct Patient {
     pub first_name: String,
     pub last_name: String,
     pub gender: Gender,
-    pub dob: String,
+    /// Date of birth (`YYYY-MM-DD`)
+    #[serde(deserialize_with = "dob")]
+    pub dob: NaiveDate,
     pub email: Option<String>,
     pub address: Option<Address>,
+    /// Why the patient has Medicare; patients under 65 qualify through a disability or
+    /// end-stage renal disease
+    #[serde(default)]
+    pub medicare_entitlement: Option<MedicareEntitlement>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Patient {
+    /// The patient's age in whole years on `date`; zero before they were born.
+    pub fn age_on(&self, date: NaiveDate) -> u32 {
+        date.years_since(self.dob).unwrap_or(0)
+    }
+}
+
+/// The basis of a patient's Medicare entitlement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MedicareEntitlement {
+    /// 65 or older
+    Age,
+    /// Entitled through Social Security disability benefits
+    Disability,
+    /// End-stage renal disease
+    Esrd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Gender {
     #[serde(rename = "m")]
     Male,
@@ -77,6 +356,22 @@ pub struct RenderingProvider {
     pub npi: String, // for 10-digit validation
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Diagnosis {
+    /// ICD-10-CM code, with or without the dot (`E11.9` or `E119`)
+    pub code: String,
+    /// The principal diagnosis for the claim
+    #[serde(default)]
+    pub primary: bool,
+}
+
+impl Diagnosis {
+    /// The code without its dot, upper-cased, as it appears in X12 and code tables.
+    pub fn normalized_code(&self) -> String {
+        self.code.trim().replace('.', "").to_ascii_uppercase()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceLine {
     pub service_line_id: String,
@@ -85,6 +380,48 @@ pub struct ServiceLine {
     pub units: i32,
     pub details: String,
     pub unit_charge_currency: String,
-    pub unit_charge_amount: f64, // for >0 validation
+    pub unit_charge_amount: Money, // for >0 validation
     pub do_not_bill: Option<bool>,
+    /// 1-based positions in the claim's `diagnoses` (up to four on a professional claim)
+    #[serde(default)]
+    pub diagnosis_pointers: Vec<u32>,
+    /// First day of service (`YYYY-MM-DD`; DTP*472 in 837P input)
+    #[serde(default, deserialize_with = "service_date_from")]
+    pub service_date_from: Option<NaiveDate>,
+    /// Last day of service for a line spanning several days; the first day when absent
+    #[serde(default, deserialize_with = "service_date_to")]
+    pub service_date_to: Option<NaiveDate>,
+}
+
+// chrono's own errors ("input is out of range") don't say which field or value was wrong, so
+// dates are read as strings and the error names both
+fn parse_date<E: serde::de::Error>(value: &str, field: &str) -> Result<NaiveDate, E> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| E::custom(format!("invalid date `{}` in field `{}` (expected YYYY-MM-DD)", value, field)))
+}
+
+fn date<'de, D: Deserializer<'de>>(deserializer: D, field: &str) -> Result<NaiveDate, D::Error> {
+    parse_date(&String::deserialize(deserializer)?, field)
+}
+
+fn optional_date<'de, D: Deserializer<'de>>(deserializer: D, field: &str) -> Result<Option<NaiveDate>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_date(&value, field))
+        .transpose()
+}
+
+fn dob<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    date(deserializer, "dob")
+}
+
+fn optional_dob<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+    optional_date(deserializer, "dob")
+}
+
+fn service_date_from<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+    optional_date(deserializer, "service_date_from")
+}
+
+fn service_date_to<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+    optional_date(deserializer, "service_date_to")
 }
\ No newline at end of file
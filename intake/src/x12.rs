@@ -0,0 +1,901 @@
+use crate::{IntakeError, Money};
+use crate::types::*;
+use chrono::NaiveDate;
+
+/// Separators declared by the ISA header of an interchange.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Delimiters {
+    pub element: char,
+    pub component: char,
+    pub repetition: char,
+    pub segment: char,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Delimiters { element: '*', component: ':', repetition: '^', segment: '~' }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// 1-based position of the segment within the interchange
+    pub position: usize,
+    pub id: String,
+    pub elements: Vec<String>,
+    pub component_separator: char,
+}
+
+impl Segment {
+    /// Returns the element at the given 1-based X12 reference (e.g. `element(1)` for CLM01),
+    /// or an empty string if the element was omitted.
+    pub fn element(&self, index: usize) -> &str {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.elements.get(i))
+            .map(|e| e.as_str())
+            .unwrap_or("")
+    }
+
+    pub fn components(&self, index: usize) -> Vec<&str> {
+        self.element(index).split(self.component_separator).collect()
+    }
+
+    pub fn error(&self, message: impl std::fmt::Display) -> String {
+        format!("Segment {} ({}): {}", self.position, self.id, message)
+    }
+}
+
+// ISA is fixed width, so the separators always sit at the same offsets
+const ISA_LENGTH: usize = 106;
+
+pub fn tokenize(input: &str) -> Result<(Delimiters, Vec<Segment>), String> {
+    let input = input.trim_start();
+    let header = input.as_bytes();
+    if !input.starts_with("ISA") || header.len() < ISA_LENGTH || !header[..ISA_LENGTH].is_ascii() {
+        return Err("Segment 1: interchange must start with a 106 character ISA header".to_string());
+    }
+
+    let delimiters = Delimiters {
+        element: header[3] as char,
+        repetition: header[82] as char,
+        component: header[104] as char,
+        segment: header[105] as char,
+    };
+
+    let segments = input
+        .split(delimiters.segment)
+        .map(|raw| raw.trim())
+        .filter(|raw| !raw.is_empty())
+        .enumerate()
+        .map(|(i, raw)| {
+            let mut parts = raw.split(delimiters.element).map(|e| e.to_string());
+            Segment {
+                position: i + 1,
+                id: parts.next().unwrap_or_default(),
+                elements: parts.collect(),
+                component_separator: delimiters.component,
+            }
+        })
+        .collect();
+
+    Ok((delimiters, segments))
+}
+
+struct Envelope {
+    control_number: String,
+    position: usize,
+}
+
+pub enum EnvelopeEvent {
+    /// ISA, GS, GE or IEA
+    Envelope,
+    TransactionStart,
+    TransactionEnd,
+    /// A segment inside an open ST/SE transaction set
+    Body,
+}
+
+/// Tracks ISA/GS/ST nesting and checks each trailer against its header.
+pub struct Envelopes {
+    functional_id: &'static str,
+    transaction_set: &'static str,
+    interchange: Option<Envelope>,
+    group: Option<Envelope>,
+    transaction: Option<Envelope>,
+    transaction_segments: usize,
+}
+
+impl Envelopes {
+    pub fn new(functional_id: &'static str, transaction_set: &'static str) -> Self {
+        Envelopes { functional_id, transaction_set, interchange: None, group: None, transaction: None, transaction_segments: 0 }
+    }
+
+    pub fn accept(&mut self, seg: &Segment) -> Result<EnvelopeEvent, String> {
+        if self.transaction.is_some() {
+            self.transaction_segments += 1;
+        }
+
+        match seg.id.as_str() {
+            "ISA" => {
+                if self.interchange.is_some() {
+                    return Err(seg.error("nested ISA without a closing IEA"));
+                }
+                self.interchange = Some(Envelope { control_number: seg.element(13).trim().to_string(), position: seg.position });
+            }
+            "GS" => {
+                if self.interchange.is_none() || self.group.is_some() {
+                    return Err(seg.error("GS must appear once inside an open ISA"));
+                }
+                if seg.element(1) != self.functional_id {
+                    return Err(seg.error(format!("expected functional group {}, found {}", self.functional_id, seg.element(1))));
+                }
+                self.group = Some(Envelope { control_number: seg.element(6).to_string(), position: seg.position });
+            }
+            "ST" => {
+                if self.group.is_none() || self.transaction.is_some() {
+                    return Err(seg.error("ST must appear once inside an open GS"));
+                }
+                if seg.element(1) != self.transaction_set {
+                    return Err(seg.error(format!("expected transaction set {}, found {}", self.transaction_set, seg.element(1))));
+                }
+                self.transaction = Some(Envelope { control_number: seg.element(2).to_string(), position: seg.position });
+                self.transaction_segments = 1;
+                return Ok(EnvelopeEvent::TransactionStart);
+            }
+            "SE" => {
+                let transaction = self.transaction.take().ok_or_else(|| seg.error("SE without a matching ST"))?;
+                if seg.element(2) != transaction.control_number {
+                    return Err(seg.error(format!(
+                        "control number {} does not match ST at segment {} ({})",
+                        seg.element(2), transaction.position, transaction.control_number
+                    )));
+                }
+                if seg.element(1).parse::<usize>().ok() != Some(self.transaction_segments) {
+                    return Err(seg.error(format!(
+                        "segment count {} does not match the {} segments in the transaction",
+                        seg.element(1), self.transaction_segments
+                    )));
+                }
+                return Ok(EnvelopeEvent::TransactionEnd);
+            }
+            "GE" => {
+                let group = self.group.take().ok_or_else(|| seg.error("GE without a matching GS"))?;
+                if self.transaction.is_some() {
+                    return Err(seg.error("GE before the open transaction was closed with SE"));
+                }
+                if seg.element(2) != group.control_number {
+                    return Err(seg.error(format!("control number {} does not match GS at segment {}", seg.element(2), group.position)));
+                }
+            }
+            "IEA" => {
+                let interchange = self.interchange.take().ok_or_else(|| seg.error("IEA without a matching ISA"))?;
+                if self.group.is_some() {
+                    return Err(seg.error("IEA before the open functional group was closed with GE"));
+                }
+                if seg.element(2).trim() != interchange.control_number {
+                    return Err(seg.error(format!("control number {} does not match ISA at segment {}", seg.element(2), interchange.position)));
+                }
+            }
+            _ if self.transaction.is_none() => {
+                return Err(seg.error("segment appears outside of an ST/SE transaction set"));
+            }
+            _ => return Ok(EnvelopeEvent::Body),
+        }
+
+        Ok(EnvelopeEvent::Envelope)
+    }
+
+    /// Fails if any envelope is still open once the input has run out.
+    pub fn finish(&self, last: Option<&Segment>) -> Result<(), String> {
+        let position = last.map(|s| s.position).unwrap_or(0);
+        let unclosed = [("ST", &self.transaction), ("GS", &self.group), ("ISA", &self.interchange)];
+        if let Some((id, envelope)) = unclosed.iter().find_map(|(id, e)| e.as_ref().map(|e| (id, e))) {
+            return Err(format!(
+                "Segment {}: interchange ended before {} at segment {} was closed",
+                position, id, envelope.position
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub struct InterchangeHeader {
+    pub sender_id: String,
+    pub receiver_id: String,
+    pub control_number: u32,
+    pub created: chrono::NaiveDateTime,
+}
+
+/// Builds a single-group interchange, filling in every envelope trailer and count.
+pub struct InterchangeWriter {
+    delimiters: Delimiters,
+    control_number: u32,
+    version: String,
+    segments: Vec<String>,
+    transaction_count: u32,
+    transaction_start: Option<usize>,
+}
+
+impl InterchangeWriter {
+    pub fn new(header: &InterchangeHeader, functional_id: &str, version: &str) -> Self {
+        let mut writer = InterchangeWriter {
+            delimiters: Delimiters::default(),
+            control_number: header.control_number,
+            version: version.to_string(),
+            segments: Vec::new(),
+            transaction_count: 0,
+            transaction_start: None,
+        };
+        let isa = format!(
+            "ISA*00*{:10}*00*{:10}*ZZ*{:15.15}*ZZ*{:15.15}*{}*{}*{}*00501*{:09}*0*P*{}",
+            "", "",
+            header.sender_id, header.receiver_id,
+            header.created.format("%y%m%d"), header.created.format("%H%M"),
+            writer.delimiters.repetition, header.control_number, writer.delimiters.component
+        );
+        writer.segments.push(isa);
+        let date = header.created.format("%Y%m%d").to_string();
+        let time = header.created.format("%H%M").to_string();
+        let control = header.control_number.to_string();
+        writer.segment("GS", &[functional_id, &header.sender_id, &header.receiver_id, &date, &time, &control, "X", version]);
+        writer
+    }
+
+    pub fn begin_transaction(&mut self, transaction_set: &str) {
+        self.transaction_count += 1;
+        self.transaction_start = Some(self.segments.len());
+        let control = format!("{:04}", self.transaction_count);
+        let version = self.version.clone();
+        self.segment("ST", &[transaction_set, &control, &version]);
+    }
+
+    /// Appends a segment, dropping trailing empty elements as X12 requires.
+    pub fn segment(&mut self, id: &str, elements: &[&str]) {
+        let used = elements.iter().rposition(|e| !e.is_empty()).map(|i| i + 1).unwrap_or(0);
+        let mut segment = id.to_string();
+        for element in &elements[..used] {
+            segment.push(self.delimiters.element);
+            segment.push_str(element);
+        }
+        self.segments.push(segment);
+    }
+
+    pub fn end_transaction(&mut self) {
+        if let Some(start) = self.transaction_start.take() {
+            let count = (self.segments.len() - start + 1).to_string();
+            let control = format!("{:04}", self.transaction_count);
+            self.segment("SE", &[&count, &control]);
+        }
+    }
+
+    pub fn finish(mut self) -> String {
+        self.end_transaction();
+        let control = self.control_number.to_string();
+        let transactions = self.transaction_count.to_string();
+        self.segment("GE", &[&transactions, &control]);
+        self.segment("IEA", &["1", &format!("{:09}", self.control_number)]);
+
+        let terminator = format!("{}\n", self.delimiters.segment);
+        let mut output = self.segments.join(&terminator);
+        output.push_str(&terminator);
+        output
+    }
+}
+
+/// Formats an amount as an X12 decimal (R) element: two places at most, no trailing zeros.
+pub fn format_amount(amount: Money) -> String {
+    let formatted = amount.to_string();
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Parses an ANSI X12 837P (005010X222A1) interchange into claims.
+///
+/// Every CLM segment becomes one `PayerClaim`, inheriting the billing provider (2000A),
+/// subscriber (2000B) and patient (2000C) loops that precede it.
+pub fn parse_837p(input: &str) -> Result<Vec<PayerClaim>, IntakeError> {
+    parse_segments(input).map_err(|message| IntakeError::Parse {
+        claim_id: None,
+        field: None,
+        message: format!("Failed to parse 837P: {}", message),
+    })
+}
+
+fn parse_segments(input: &str) -> Result<Vec<PayerClaim>, String> {
+    let (_, segments) = tokenize(input)?;
+    let mut parser = Parser837 {
+        envelopes: Envelopes::new("HC", "837"),
+        entity: None,
+        billing_provider: None,
+        subscriber: None,
+        subscriber_is_patient: false,
+        group_number: None,
+        patient_relationship: None,
+        member_id: None,
+        payer_id: None,
+        patient: None,
+        claim: None,
+        claims: Vec::new(),
+    };
+
+    for segment in &segments {
+        parser.segment(segment)?;
+    }
+
+    parser.finish(segments.last())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Entity {
+    BillingProvider,
+    Subscriber,
+    Payer,
+    Patient,
+    RenderingProvider,
+    /// 2330A: the subscriber of another coverage
+    OtherSubscriber,
+    /// 2330B: the payer of another coverage
+    OtherPayer,
+    Other,
+}
+
+#[derive(Default, Clone)]
+struct PartialPatient {
+    first_name: Option<String>,
+    last_name: Option<String>,
+    gender: Option<Gender>,
+    dob: Option<NaiveDate>,
+    address: Option<Address>,
+}
+
+/// A 2320 other subscriber loop: a coverage that pays after the claim's payer.
+struct PartialCoverage {
+    position: usize,
+    relationship: Relationship,
+    group_number: Option<String>,
+    member_id: Option<String>,
+    payer_id: Option<PayerId>,
+    subscriber: PartialPatient,
+}
+
+struct ClaimBuilder {
+    claim_id: String,
+    position: usize,
+    place_of_service_code: i32,
+    frequency_code: ClaimFrequency,
+    payer_claim_control_number: Option<String>,
+    rendering_provider: Option<RenderingProvider>,
+    diagnoses: Vec<Diagnosis>,
+    other_coverage: Vec<PartialCoverage>,
+    /// The 2300 date of service, for lines without their own
+    service_dates: Option<(NaiveDate, NaiveDate)>,
+    service_lines: Vec<ServiceLine>,
+}
+
+struct Parser837 {
+    envelopes: Envelopes,
+    entity: Option<Entity>,
+    billing_provider: Option<Organization>,
+    subscriber: Option<PartialPatient>,
+    subscriber_is_patient: bool,
+    group_number: Option<String>,
+    /// PAT01 from the 2000C patient loop
+    patient_relationship: Option<Relationship>,
+    member_id: Option<String>,
+    payer_id: Option<PayerId>,
+    patient: Option<PartialPatient>,
+    claim: Option<ClaimBuilder>,
+    claims: Vec<PayerClaim>,
+}
+
+impl Parser837 {
+    fn segment(&mut self, seg: &Segment) -> Result<(), String> {
+        match self.envelopes.accept(seg)? {
+            EnvelopeEvent::TransactionStart => {
+                self.reset_hierarchy();
+                return Ok(());
+            }
+            EnvelopeEvent::TransactionEnd => return self.finish_claim(),
+            EnvelopeEvent::Envelope => return Ok(()),
+            EnvelopeEvent::Body => {}
+        }
+
+        match seg.id.as_str() {
+            "HL" => self.hierarchical_level(seg)?,
+            // within a claim, SBR starts a 2320 loop for another coverage
+            "SBR" if self.claim.is_some() => self.other_coverage(seg)?,
+            "SBR" => {
+                self.subscriber_is_patient = seg.element(2) == "18";
+                self.group_number = non_empty(seg.element(3));
+            }
+            "PAT" if self.patient.is_some() => {
+                let relationship = Relationship::from_code(seg.element(1))
+                    .ok_or_else(|| seg.error(format!("unsupported patient relationship code {}", seg.element(1))))?;
+                self.patient_relationship = Some(relationship);
+            }
+            "NM1" => self.name(seg)?,
+            "N3" => {
+                let street = [seg.element(1), seg.element(2)]
+                    .iter()
+                    .filter(|s| !s.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if let Some(address) = self.address_mut() {
+                    address.street = Some(street);
+                }
+            }
+            "N4" => {
+                let zip = match seg.element(3) {
+                    z if !z.is_ascii() => return Err(seg.error(format!("invalid postal code {}", z))),
+                    z if z.len() == 9 => format!("{}-{}", &z[..5], &z[5..]),
+                    z => z.to_string(),
+                };
+                let country = non_empty(seg.element(4));
+                if let Some(address) = self.address_mut() {
+                    address.city = non_empty(seg.element(1));
+                    address.state = non_empty(seg.element(2));
+                    address.zip = non_empty(&zip);
+                    address.country = country;
+                }
+            }
+            "REF" => self.reference(seg)?,
+            "PER" if self.entity == Some(Entity::BillingProvider) => {
+                let (first_name, last_name) = match seg.element(2).rsplit_once(' ') {
+                    Some((first, last)) => (Some(first.to_string()), Some(last.to_string())),
+                    None => (None, non_empty(seg.element(2))),
+                };
+                let phone_number = (seg.element(3) == "TE").then(|| seg.element(4).to_string());
+                if let Some(org) = self.billing_provider.as_mut() {
+                    org.contact = Some(Contact { first_name, last_name, phone_number });
+                }
+            }
+            "DMG" => self.demographics(seg)?,
+            "CLM" => self.start_claim(seg)?,
+            "HI" => self.health_care_diagnoses(seg)?,
+            "DTP" => {
+                let dates = match seg.element(2) {
+                    "D8" => parse_d8(seg.element(3)).map(|date| Some((date, date))),
+                    "RD8" => seg
+                        .element(3)
+                        .split_once('-')
+                        .and_then(|(from, to)| Some(Some((parse_d8(from)?, parse_d8(to)?)))),
+                    _ => Some(None),
+                };
+                let dates = dates.ok_or_else(|| seg.error(format!("invalid {} date {}", seg.element(2), seg.element(3))))?;
+                // 472 is the date of service: on a line in 2400, or for every line in 2300
+                if let (Some(dates), "472", Some(claim)) = (dates, seg.element(1), self.claim.as_mut()) {
+                    match claim.service_lines.last_mut() {
+                        Some(line) => (line.service_date_from, line.service_date_to) = (Some(dates.0), Some(dates.1)),
+                        None => claim.service_dates = Some(dates),
+                    }
+                }
+            }
+            "LX" => {
+                if self.claim.is_none() {
+                    return Err(seg.error("LX outside of a CLM loop"));
+                }
+                self.entity = None;
+            }
+            "SV1" => self.professional_service(seg)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn reset_hierarchy(&mut self) {
+        self.entity = None;
+        self.billing_provider = None;
+        self.subscriber = None;
+        self.subscriber_is_patient = false;
+        self.group_number = None;
+        self.patient_relationship = None;
+        self.member_id = None;
+        self.payer_id = None;
+        self.patient = None;
+    }
+
+    fn hierarchical_level(&mut self, seg: &Segment) -> Result<(), String> {
+        self.finish_claim()?;
+        self.entity = None;
+        match seg.element(3) {
+            "20" => self.reset_hierarchy(),
+            "22" => {
+                self.subscriber = Some(PartialPatient::default());
+                self.subscriber_is_patient = false;
+                self.group_number = None;
+                self.patient_relationship = None;
+                self.member_id = None;
+                self.payer_id = None;
+                self.patient = None;
+            }
+            "23" => {
+                self.patient = Some(PartialPatient::default());
+                self.patient_relationship = None;
+            }
+            other => return Err(seg.error(format!("unsupported hierarchical level code {}", other))),
+        }
+        Ok(())
+    }
+
+    fn name(&mut self, seg: &Segment) -> Result<(), String> {
+        let last_name = seg.element(3).to_string();
+        let first_name = seg.element(4).to_string();
+        let id = non_empty(seg.element(9));
+
+        self.entity = Some(match seg.element(1) {
+            "85" => {
+                self.billing_provider = Some(Organization {
+                    name: last_name,
+                    billing_npi: id.filter(|_| seg.element(8) == "XX"),
+                    ein: None,
+                    contact: None,
+                    address: None,
+                });
+                Entity::BillingProvider
+            }
+            "IL" if self.in_other_coverage() => {
+                let coverage = self.current_coverage();
+                coverage.subscriber.first_name = Some(first_name);
+                coverage.subscriber.last_name = Some(last_name);
+                coverage.member_id = id;
+                Entity::OtherSubscriber
+            }
+            "PR" if self.in_other_coverage() => {
+                let payer_id = payer_id_from(&last_name, id.as_deref().unwrap_or(""))
+                    .ok_or_else(|| seg.error("other payer has neither a name nor an id"))?;
+                self.current_coverage().payer_id = Some(payer_id);
+                Entity::OtherPayer
+            }
+            "IL" => {
+                let subscriber = self.subscriber.as_mut().ok_or_else(|| seg.error("NM1*IL outside of a 2000B subscriber loop"))?;
+                subscriber.first_name = Some(first_name);
+                subscriber.last_name = Some(last_name);
+                self.member_id = id;
+                Entity::Subscriber
+            }
+            "PR" => {
+                let payer_id = payer_id_from(&last_name, id.as_deref().unwrap_or(""))
+                    .ok_or_else(|| seg.error("payer has neither a name nor an id"))?;
+                self.payer_id = Some(payer_id);
+                Entity::Payer
+            }
+            "QC" => {
+                let patient = self.patient.as_mut().ok_or_else(|| seg.error("NM1*QC outside of a 2000C patient loop"))?;
+                patient.first_name = Some(first_name);
+                patient.last_name = Some(last_name);
+                Entity::Patient
+            }
+            "82" => {
+                let provider = RenderingProvider {
+                    first_name,
+                    last_name,
+                    npi: id.filter(|_| seg.element(8) == "XX").unwrap_or_default(),
+                };
+                // 2310B applies to the whole claim; a 2420A line-level provider only fills the gap
+                if let Some(claim) = self.claim.as_mut()
+                    && (claim.service_lines.is_empty() || claim.rendering_provider.is_none())
+                {
+                    claim.rendering_provider = Some(provider);
+                }
+                Entity::RenderingProvider
+            }
+            _ => Entity::Other,
+        });
+
+        Ok(())
+    }
+
+    fn other_coverage(&mut self, seg: &Segment) -> Result<(), String> {
+        let relationship = Relationship::from_code(seg.element(2))
+            .ok_or_else(|| seg.error(format!("unsupported relationship code {}", seg.element(2))))?;
+        let claim = self.claim.as_mut().ok_or_else(|| seg.error("2320 SBR outside of a CLM loop"))?;
+        if !claim.service_lines.is_empty() {
+            return Err(seg.error("2320 other subscriber loops must come before the service lines"));
+        }
+        self.entity = None;
+        claim.other_coverage.push(PartialCoverage {
+            position: seg.position,
+            relationship,
+            group_number: non_empty(seg.element(3)),
+            member_id: None,
+            payer_id: None,
+            subscriber: PartialPatient::default(),
+        });
+        Ok(())
+    }
+
+    fn in_other_coverage(&self) -> bool {
+        self.claim.as_ref().is_some_and(|c| !c.other_coverage.is_empty() && c.service_lines.is_empty())
+    }
+
+    fn current_coverage(&mut self) -> &mut PartialCoverage {
+        self.claim
+            .as_mut()
+            .and_then(|c| c.other_coverage.last_mut())
+            .expect("only called within a 2320 loop")
+    }
+
+    fn address_mut(&mut self) -> Option<&mut Address> {
+        let empty = || Address { street: None, city: None, state: None, zip: None, country: None };
+        match self.entity? {
+            Entity::BillingProvider => Some(self.billing_provider.as_mut()?.address.get_or_insert_with(empty)),
+            Entity::Subscriber => Some(self.subscriber.as_mut()?.address.get_or_insert_with(empty)),
+            Entity::Patient => Some(self.patient.as_mut()?.address.get_or_insert_with(empty)),
+            _ => None,
+        }
+    }
+
+    fn reference(&mut self, seg: &Segment) -> Result<(), String> {
+        match (seg.element(1), self.entity) {
+            ("EI", Some(Entity::BillingProvider)) => {
+                let ein = seg.element(2).replace('-', "");
+                if !ein.is_ascii() {
+                    return Err(seg.error(format!("invalid employer identification number {}", seg.element(2))));
+                }
+                if let Some(org) = self.billing_provider.as_mut() {
+                    org.ein = Some(if ein.len() == 9 { format!("{}-{}", &ein[..2], &ein[2..]) } else { ein });
+                }
+            }
+            // 2300 payer claim control number, naming the claim a replacement or void corrects; a
+            // 2330B one is the other payer's and isn't kept
+            ("F8", entity) if !matches!(entity, Some(Entity::OtherSubscriber | Entity::OtherPayer)) => {
+                if let Some(claim) = self.claim.as_mut().filter(|c| c.service_lines.is_empty()) {
+                    claim.payer_claim_control_number = non_empty(seg.element(2));
+                }
+            }
+            ("6R", _) => {
+                if let Some(line) = self.claim.as_mut().and_then(|c| c.service_lines.last_mut()) {
+                    line.service_line_id = seg.element(2).to_string();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn demographics(&mut self, seg: &Segment) -> Result<(), String> {
+        let target = match self.entity {
+            Some(Entity::Subscriber) => self.subscriber.as_mut(),
+            Some(Entity::Patient) => self.patient.as_mut(),
+            _ => None,
+        };
+        let Some(target) = target else { return Ok(()) };
+
+        if seg.element(1) != "D8" {
+            return Err(seg.error(format!("unsupported date format qualifier {}", seg.element(1))));
+        }
+        let dob = parse_d8(seg.element(2)).ok_or_else(|| seg.error(format!("invalid date of birth {}", seg.element(2))))?;
+        target.dob = Some(dob);
+        target.gender = Some(match seg.element(3) {
+            "M" => Gender::Male,
+            "F" => Gender::Female,
+            other => return Err(seg.error(format!("unsupported gender code {}", other))),
+        });
+        Ok(())
+    }
+
+    fn start_claim(&mut self, seg: &Segment) -> Result<(), String> {
+        self.finish_claim()?;
+        let facility = seg.components(5);
+        let place_of_service_code = facility[0]
+            .parse()
+            .map_err(|_| seg.error(format!("invalid place of service {}", facility[0])))?;
+        let frequency_code = match facility.get(2).copied().unwrap_or("") {
+            "" => ClaimFrequency::Original,
+            code => ClaimFrequency::from_code(code).ok_or_else(|| seg.error(format!("unsupported claim frequency code {}", code)))?,
+        };
+
+        self.entity = None;
+        self.claim = Some(ClaimBuilder {
+            claim_id: seg.element(1).to_string(),
+            position: seg.position,
+            place_of_service_code,
+            frequency_code,
+            payer_claim_control_number: None,
+            rendering_provider: None,
+            diagnoses: Vec::new(),
+            other_coverage: Vec::new(),
+            service_dates: None,
+            service_lines: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn health_care_diagnoses(&mut self, seg: &Segment) -> Result<(), String> {
+        let claim = self.claim.as_mut().ok_or_else(|| seg.error("HI outside of a CLM loop"))?;
+        for i in 1..=seg.elements.len() {
+            let composite = seg.components(i);
+            if composite.len() < 2 {
+                return Err(seg.error(format!("HI{:02} must be a qualifier:code composite", i)));
+            }
+            // ABK/BK is the principal diagnosis, ABF/BF the others; other qualifiers (e.g.
+            // anesthesia or condition codes) aren't diagnoses
+            let primary = match composite[0] {
+                "ABK" | "BK" => true,
+                "ABF" | "BF" => false,
+                _ => continue,
+            };
+            claim.diagnoses.push(Diagnosis { code: composite[1].to_string(), primary });
+        }
+        Ok(())
+    }
+
+    fn professional_service(&mut self, seg: &Segment) -> Result<(), String> {
+        let claim = self.claim.as_mut().ok_or_else(|| seg.error("SV1 outside of a CLM loop"))?;
+        let procedure = seg.components(1);
+        if procedure.len() < 2 || !matches!(procedure[0], "HC" | "WK") {
+            return Err(seg.error("SV101 must be an HC:procedure composite"));
+        }
+
+        let charge: Money = seg
+            .element(2)
+            .parse()
+            .map_err(|e| seg.error(format!("invalid line charge {}: {}", seg.element(2), e)))?;
+        let units = match seg.element(4) {
+            "" => 1.0,
+            u => u.parse::<f64>().map_err(|_| seg.error(format!("invalid unit count {}", u)))?,
+        };
+        if units.fract() != 0.0 || units < 1.0 {
+            return Err(seg.error(format!("unit count {} must be a whole number of at least 1", seg.element(4))));
+        }
+        let units = units as i32;
+        // claims carry a per-unit charge, so the line charge has to split evenly across the units
+        let unit_charge = Money::from_cents(charge.cents() / i64::from(units));
+        if unit_charge * units != charge {
+            return Err(seg.error(format!("line charge {} doesn't divide evenly into {} units", charge, units)));
+        }
+
+        let modifiers: Vec<String> = procedure[2..procedure.len().min(6)]
+            .iter()
+            .filter(|m| !m.is_empty())
+            .map(|m| m.to_string())
+            .collect();
+        let diagnosis_pointers = seg
+            .components(7)
+            .into_iter()
+            .filter(|p| !p.is_empty())
+            .map(|p| p.parse().map_err(|_| seg.error(format!("invalid diagnosis pointer {}", p))))
+            .collect::<Result<Vec<u32>, String>>()?;
+        let details = procedure
+            .get(6)
+            .filter(|d| !d.is_empty())
+            .map(|d| d.to_string())
+            .unwrap_or_else(|| format!("Procedure {}", procedure[1]));
+
+        claim.service_lines.push(ServiceLine {
+            service_line_id: format!("{}-{}", claim.claim_id, claim.service_lines.len() + 1),
+            procedure_code: procedure[1].to_string(),
+            modifiers: (!modifiers.is_empty()).then_some(modifiers),
+            units,
+            details,
+            unit_charge_currency: "USD".to_string(),
+            unit_charge_amount: unit_charge,
+            do_not_bill: None,
+            diagnosis_pointers,
+            service_date_from: None,
+            service_date_to: None,
+        });
+        Ok(())
+    }
+
+    fn finish_claim(&mut self) -> Result<(), String> {
+        let Some(mut claim) = self.claim.take() else { return Ok(()) };
+        let err = |message: &str| format!("Segment {} (CLM): claim {} {}", claim.position, claim.claim_id, message);
+
+        let organization = self.billing_provider.clone().ok_or_else(|| err("has no 2010AA billing provider"))?;
+        let payer_id = self.payer_id.clone().ok_or_else(|| err("has no 2010BB payer"))?;
+        let patient_member_id = self.member_id.clone().ok_or_else(|| err("has no 2010BA subscriber member id"))?;
+        let rendering_provider = claim.rendering_provider.ok_or_else(|| err("has no 2310B rendering provider"))?;
+        let dependent = !self.subscriber_is_patient && self.patient.is_some();
+        let source = if dependent { self.patient.clone() } else { self.subscriber.clone() };
+        let source = source.ok_or_else(|| err("has no subscriber or patient loop"))?;
+        let relationship = match self.patient_relationship {
+            _ if !dependent => Relationship::SelfInsured,
+            Some(relationship) => relationship,
+            None => return Err(err("has a 2000C patient loop without a PAT relationship")),
+        };
+        let subscriber = self.subscriber.clone().filter(|_| dependent).map(|subscriber| Subscriber {
+            first_name: subscriber.first_name.unwrap_or_default(),
+            last_name: subscriber.last_name.unwrap_or_default(),
+            dob: subscriber.dob,
+            gender: subscriber.gender,
+        });
+
+        if let Some((from, to)) = claim.service_dates {
+            for line in claim.service_lines.iter_mut().filter(|line| line.service_date_from.is_none()) {
+                (line.service_date_from, line.service_date_to) = (Some(from), Some(to));
+            }
+        }
+
+        let other_insurance = claim
+            .other_coverage
+            .into_iter()
+            .map(|coverage| {
+                let err = |message: &str| format!("Segment {} (SBR): claim {} {}", coverage.position, claim.claim_id, message);
+                let subscriber = coverage.subscriber;
+                Ok(Insurance {
+                    payer_id: coverage.payer_id.ok_or_else(|| err("has a 2320 other coverage without a 2330B payer"))?,
+                    patient_member_id: coverage.member_id.ok_or_else(|| err("has a 2320 other coverage without a 2330A member id"))?,
+                    group_number: coverage.group_number,
+                    relationship: coverage.relationship,
+                    subscriber: (coverage.relationship != Relationship::SelfInsured).then(|| Subscriber {
+                        first_name: subscriber.first_name.unwrap_or_default(),
+                        last_name: subscriber.last_name.unwrap_or_default(),
+                        dob: subscriber.dob,
+                        gender: subscriber.gender,
+                    }),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let patient = Patient {
+            first_name: source.first_name.ok_or_else(|| err("is missing the patient name"))?,
+            last_name: source.last_name.unwrap_or_default(),
+            gender: source.gender.ok_or_else(|| err("is missing patient DMG gender"))?,
+            dob: source.dob.ok_or_else(|| err("is missing patient DMG date of birth"))?,
+            email: None,
+            address: source.address,
+            medicare_entitlement: None,
+        };
+
+        self.claims.push(PayerClaim {
+            claim_id: claim.claim_id,
+            place_of_service_code: claim.place_of_service_code,
+            insurance: Insurance {
+                payer_id,
+                patient_member_id,
+                group_number: self.group_number.clone(),
+                relationship,
+                subscriber,
+            },
+            patient,
+            organization,
+            rendering_provider,
+            diagnoses: claim.diagnoses,
+            service_lines: claim.service_lines,
+            frequency_code: claim.frequency_code,
+            payer_claim_control_number: claim.payer_claim_control_number,
+            other_insurance,
+            prior_payments: Vec::new(),
+            initial_claim_ts: chrono::Utc::now().timestamp_millis(),
+        });
+        Ok(())
+    }
+
+    fn finish(mut self, last: Option<&Segment>) -> Result<Vec<PayerClaim>, String> {
+        self.finish_claim()?;
+        self.envelopes.finish(last)?;
+        Ok(self.claims)
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn parse_d8(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y%m%d").ok()
+}
+
+fn payer_id_from(name: &str, id: &str) -> Option<PayerId> {
+    // the payer's own id is left for the clearinghouse's payer registry to resolve, aliases and all;
+    // the built-in ids are recognized whatever their case, as the registry would
+    if !id.is_empty() {
+        return Some(match PayerId::new(&id.to_ascii_lowercase()) {
+            PayerId::Other(_) => PayerId::Other(id.to_string()),
+            builtin => builtin,
+        });
+    }
+    let upper = name.to_ascii_uppercase();
+    if upper.contains("MEDICARE") {
+        Some(PayerId::Medicare)
+    } else if upper.contains("UNITED") || upper.contains("UHC") {
+        Some(PayerId::UnitedHealthGroup)
+    } else if upper.contains("ANTHEM") {
+        Some(PayerId::Anthem)
+    } else {
+        non_empty(name).map(PayerId::Other)
+    }
+}
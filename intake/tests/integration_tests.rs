@@ -13,6 +13,19 @@ fn create_test_config(file_path: &str) -> Config {
         rate_per_second: 10,
         refill_rate: 5,
         num_threads: 2,
+        input_format: InputFormat::from_path(file_path),
+        seed: None,
+        clock: ClockMode::RealTime,
+        outage_rate: 0.0,
+        plan_year_start: None,
+        npi_registry: None,
+        modifier_table: None,
+        procedure_codes: None,
+        duplicate_store: None,
+        payers: None,
+        roster: None,
+        exchange_rates: None,
+        resume_offset: None,
     }
 }
 
@@ -22,7 +35,7 @@ fn test_read_file_valid_single_claim() {
     let result = read_file(&config);
     
     assert!(result.is_ok());
-    let lines: Vec<String> = result.unwrap().collect();
+    let lines: Vec<String> = result.unwrap().map(|line| line.unwrap().text).collect();
     assert_eq!(lines.len(), 1);
     assert!(lines[0].contains("CLAIM123"));
 }
@@ -33,7 +46,7 @@ fn test_read_file_multiple_claims() {
     let result = read_file(&config);
     
     assert!(result.is_ok());
-    let lines: Vec<String> = result.unwrap().collect();
+    let lines: Vec<String> = result.unwrap().map(|line| line.unwrap().text).collect();
     assert_eq!(lines.len(), 2);
     assert!(lines[0].contains("MULTI001"));
     assert!(lines[1].contains("MULTI002"));
@@ -45,7 +58,7 @@ fn test_read_file_empty_file() {
     let result = read_file(&config);
     
     assert!(result.is_ok());
-    let lines: Vec<String> = result.unwrap().collect();
+    let lines: Vec<String> = result.unwrap().map(|line| line.unwrap().text).collect();
     assert_eq!(lines.len(), 0);
 }
 
@@ -56,7 +69,7 @@ fn test_read_file_nonexistent() {
     
     assert!(result.is_err());
     match result {
-        Err(error_msg) => assert!(error_msg.contains("Failed to open file")),
+        Err(error_msg) => assert!(error_msg.to_string().contains("Failed to open file")),
         Ok(_) => panic!("Expected error, but got Ok"),
     }
 }
@@ -96,7 +109,7 @@ fn test_parse_line_invalid_json() {
     
     let result = parse_line(invalid_json);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("Failed to parse line"));
+    assert!(result.unwrap_err().to_string().contains("Failed to parse line"));
 }
 
 #[test]
@@ -105,29 +118,35 @@ fn test_parse_line_missing_required_field() {
     
     let result = parse_line(&json_line);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("Failed to parse line"));
+    assert!(result.unwrap_err().to_string().contains("Failed to parse line"));
 }
 
 #[test]
 fn test_parse_line_empty_string() {
     let result = parse_line("");
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("Failed to parse line"));
+    assert!(result.unwrap_err().to_string().contains("Failed to parse line"));
 }
 
 #[test]
 fn test_parse_line_whitespace_only() {
     let result = parse_line("   \n  \t  ");
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("Failed to parse line"));
+    assert!(result.unwrap_err().to_string().contains("Failed to parse line"));
 }
 
 #[test]
-fn test_parse_line_invalid_payer_id() {
-    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"invalid_payer","patient_member_id":"MED123"},"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1980-01-15"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567890"},"service_lines":[{"service_line_id":"SL001","procedure_code":"99213","units":1,"details":"Test visit","unit_charge_currency":"USD","unit_charge_amount":100.00}]}"#;
+fn test_parse_line_other_payer_id() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"aetna","patient_member_id":"MED123"},"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1980-01-15"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567890"},"service_lines":[{"service_line_id":"SL001","procedure_code":"99213","units":1,"details":"Test visit","unit_charge_currency":"USD","unit_charge_amount":100.00}]}"#;
     
-    let result = parse_line(json_line);
-    assert!(result.is_err());
+    // payers other than the simulators are carried through for the clearinghouse's registry
+    let claim = parse_line(json_line).unwrap();
+    assert_eq!(claim.insurance.payer_id, PayerId::Other("aetna".to_string()));
+    assert_eq!(claim.insurance.payer_id.name(), "aetna");
+    assert_eq!(PayerId::new("united_health_group"), PayerId::UnitedHealthGroup);
+    assert_eq!(PayerId::UnitedHealthGroup.to_string(), "united_health_group");
+
+    assert!(parse_line(&json_line.replace(r#""payer_id":"aetna""#, r#""payer_id":42"#)).is_err());
 }
 
 #[test]
@@ -172,11 +191,721 @@ fn test_integration_read_and_parse() {
     let lines = read_file(&config).unwrap();
     
     for line in lines {
-        let claim_result = parse_line(&line);
+        let claim_result = parse_line(&line.unwrap().text);
         assert!(claim_result.is_ok());
         
         let claim = claim_result.unwrap();
         assert_eq!(claim.claim_id, "CLAIM123");
         assert!(claim.initial_claim_ts > 0);
     }
-}
\ No newline at end of file
+}
+// Helper function to build a one-claim 837P interchange around the given claim segments
+fn build_837p(claim_segments: &[&str]) -> String {
+    let mut segments = vec![
+        "ST*837*0001*005010X222A1",
+        "HL*1**20*1",
+        "NM1*85*2*TEST CLINIC*****XX*1234567890",
+        "HL*2*1*22*0",
+        "SBR*P*18*******MB",
+        "NM1*IL*1*DOE*JOHN****MI*MED123",
+        "DMG*D8*19800115*M",
+        "NM1*PR*2*MEDICARE*****PI*MEDICARE",
+    ];
+    segments.extend_from_slice(claim_segments);
+    let se = format!("SE*{}*0001", segments.len() + 1);
+    segments.push(&se);
+
+    format!(
+        "ISA*00*          *00*          *ZZ*SUBMITTER01    *ZZ*RECEIVER01     *250805*1200*^*00501*000000001*0*T*:~GS*HC*SUB*RCV*20250805*1200*1*X*005010X222A1~{}~GE*1*1~IEA*1*000000001~",
+        segments.join("~")
+    )
+}
+
+#[test]
+fn test_input_format_from_path() {
+    assert_eq!(InputFormat::from_path("claims.837"), InputFormat::X12);
+    assert_eq!(InputFormat::from_path("claims.X12"), InputFormat::X12);
+    assert_eq!(InputFormat::from_path("claims.edi"), InputFormat::X12);
+    assert_eq!(InputFormat::from_path("test.txt"), InputFormat::Json);
+}
+
+#[test]
+fn test_config_build_format_flag_overrides_extension() {
+    let args = ["app", "claims.txt", "5", "10", "--format=x12"].iter().map(|s| s.to_string());
+    let config = Config::build(args).unwrap();
+    assert_eq!(config.input_format, InputFormat::X12);
+    assert_eq!(config.refill_rate, 5);
+    assert_eq!(config.rate_per_second, 10);
+
+    let args = ["app", "claims.837", "5", "10"].iter().map(|s| s.to_string());
+    assert_eq!(Config::build(args).unwrap().input_format, InputFormat::X12);
+
+    let args = ["app", "claims.837", "5", "10", "--format=csv"].iter().map(|s| s.to_string());
+    assert!(Config::build(args).is_err());
+}
+
+#[test]
+fn test_config_build_seed_flag() {
+    let args = ["app", "--seed=42", "claims.txt", "5", "10", "4"].iter().map(|s| s.to_string());
+    let config = Config::build(args).unwrap();
+    assert_eq!(config.seed, Some(42));
+    assert_eq!(config.num_threads, 4);
+
+    let args = ["app", "claims.txt", "5", "10"].iter().map(|s| s.to_string());
+    assert_eq!(Config::build(args).unwrap().seed, None);
+
+    let args = ["app", "claims.txt", "5", "10", "--seed=abc"].iter().map(|s| s.to_string());
+    assert!(matches!(Config::build(args), Err(IntakeError::Config { field, message }) if field == "seed" && message.starts_with("Invalid seed")));
+}
+
+#[test]
+fn test_config_build_clock_flag() {
+    let build = |flag: &str| Config::build(["app", "claims.txt", "5", "10", flag].iter().map(|s| s.to_string()));
+    assert_eq!(build("--clock=instant").unwrap().clock, ClockMode::Instant);
+    assert_eq!(build("--clock=realtime").unwrap().clock, ClockMode::RealTime);
+    assert_eq!(build("--clock=60x").unwrap().clock, ClockMode::Accelerated(60.0));
+    assert!(build("--clock=0x").is_err());
+    assert!(build("--clock=fast").is_err());
+    assert_eq!(Config::build(["app", "claims.txt", "5", "10"].iter().map(|s| s.to_string())).unwrap().clock, ClockMode::RealTime);
+}
+
+#[test]
+fn test_manual_clock_moves_by_sleeps_without_waiting() {
+    let clock = ManualClock::new(1_000);
+    let started = std::time::Instant::now();
+    clock.sleep(std::time::Duration::from_secs(30));
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    assert_eq!(clock.now_millis(), 31_000);
+    clock.advance(std::time::Duration::from_secs(90));
+    assert_eq!(clock.now_millis(), 121_000);
+}
+
+#[test]
+fn test_accelerated_clock_scales_sleeps() {
+    let clock = AcceleratedClock::starting_at(0, 1000.0).unwrap();
+    let started = std::time::Instant::now();
+    clock.sleep(std::time::Duration::from_secs(20));
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    assert!(clock.now_millis() >= 20_000);
+    assert!(AcceleratedClock::starting_at(0, -1.0).is_err());
+}
+
+#[test]
+fn test_read_x12_file_valid_batch() {
+    let config = create_test_config(&get_fixture_path("valid_837p.x12"));
+    let claims = read_x12_file(&config).unwrap();
+    assert_eq!(claims.len(), 2);
+
+    let first = &claims[0];
+    assert_eq!(first.claim_id, "X12CLM001");
+    assert_eq!(first.place_of_service_code, 11);
+    assert!(matches!(first.insurance.payer_id, PayerId::Medicare));
+    assert_eq!(first.insurance.patient_member_id, "MED123456789");
+    assert_eq!(first.patient.first_name, "JOHN");
+    assert_eq!(first.patient.dob.to_string(), "1985-03-15");
+    assert!(matches!(first.patient.gender, Gender::Male));
+    assert_eq!(first.organization.name, "CITY MEDICAL CENTER");
+    assert_eq!(first.organization.billing_npi.as_deref(), Some("1234567890"));
+    assert_eq!(first.organization.ein.as_deref(), Some("12-3456789"));
+    assert_eq!(first.organization.address.as_ref().unwrap().zip.as_deref(), Some("10002-1234"));
+    assert_eq!(first.organization.contact.as_ref().unwrap().phone_number.as_deref(), Some("555-0123"));
+    assert_eq!(first.rendering_provider.npi, "9876543210");
+    assert!(first.initial_claim_ts > 0);
+
+    assert_eq!(first.service_lines.len(), 2);
+    assert_eq!(first.service_lines[0].service_line_id, "SL001");
+    assert_eq!(first.service_lines[0].modifiers, Some(vec!["25".to_string()]));
+    assert_eq!(first.service_lines[1].service_line_id, "X12CLM001-2");
+    assert_eq!(first.service_lines[1].details, "ELECTROCARDIOGRAM");
+    assert_eq!(first.service_lines[1].units, 2);
+    assert_eq!(first.service_lines[1].unit_charge_amount, Money::from_dollars(75.0));
+    assert_eq!(first.diagnoses, vec![Diagnosis { code: "I10".to_string(), primary: true }]);
+    assert_eq!(first.service_lines[1].diagnosis_pointers, vec![1]);
+}
+
+#[test]
+fn test_read_x12_file_dependent_patient() {
+    let config = create_test_config(&get_fixture_path("valid_837p.x12"));
+    let claims = read_x12_file(&config).unwrap();
+
+    // 2000C patient loop overrides the subscriber as the patient
+    let second = &claims[1];
+    // billed to UnitedHealthcare's payer id, which the clearinghouse's payer registry resolves
+    assert_eq!(second.insurance.payer_id, PayerId::Other("87726".to_string()));
+    assert_eq!(second.insurance.patient_member_id, "UHG987654321");
+    assert_eq!(second.patient.first_name, "MARY");
+    assert!(matches!(second.patient.gender, Gender::Female));
+    assert_eq!(second.patient.dob.to_string(), "2010-07-22");
+    assert_eq!(second.organization.name, "CITY MEDICAL CENTER");
+
+    // the 2010BA subscriber is kept as the policyholder
+    assert_eq!(second.insurance.relationship, Relationship::Child);
+    assert_eq!(second.insurance.group_number.as_deref(), Some("GRP001"));
+    let subscriber = second.insurance.subscriber.as_ref().unwrap();
+    assert_eq!((subscriber.first_name.as_str(), subscriber.last_name.as_str()), ("ROBERT", "DAVIS"));
+    assert_eq!(subscriber.dob.map(|dob| dob.to_string()).as_deref(), Some("1978-07-22"));
+    assert_eq!(subscriber.gender, Some(Gender::Male));
+
+    let first = &claims[0];
+    assert_eq!(first.insurance.relationship, Relationship::SelfInsured);
+    assert!(first.insurance.subscriber.is_none());
+    assert!(first.insurance.group_number.is_none());
+}
+
+#[test]
+fn test_parse_837p_patient_relationship_codes() {
+    let dependent = |pat: &str| {
+        let input = build_837p(&[
+            "HL*3*2*23*0",
+            pat,
+            "NM1*QC*1*DOE*JANE",
+            "DMG*D8*20120301*F",
+            "CLM*DEP001*100***11:B:1",
+            "HI*ABK:J069",
+            "NM1*82*1*SMITH*JANE****XX*9876543210",
+            "LX*1",
+            "SV1*HC:99213*100*UN*1***1",
+        ]);
+        // SBR02 is left empty when the patient isn't the subscriber
+        parse_837p(&input.replace("SBR*P*18*", "SBR*P**"))
+    };
+    let claims = dependent("PAT*01").unwrap();
+    assert_eq!(claims[0].insurance.relationship, Relationship::Spouse);
+    assert_eq!(claims[0].patient.first_name, "JANE");
+    assert_eq!(claims[0].insurance.subscriber.as_ref().unwrap().first_name, "JOHN");
+    assert_eq!(Relationship::from_code("G8"), Some(Relationship::Other));
+    assert_eq!(Relationship::LifePartner.code(), "53");
+
+    let err = dependent("PAT*99").unwrap_err();
+    assert!(err.to_string().contains("unsupported patient relationship code 99"), "{}", err);
+}
+
+#[test]
+fn test_parse_line_subscriber_details() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"anthem","patient_member_id":"ANT123","group_number":"GRP-77","relationship":"19","subscriber":{"first_name":"Robert","last_name":"Doe","dob":"1975-02-01","gender":"m"}},"patient":{"first_name":"Jane","last_name":"Doe","gender":"f","dob":"2012-03-01"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567893"},"diagnoses":[{"code":"J06.9","primary":true}],"service_lines":[{"service_line_id":"SL001","procedure_code":"99213","units":1,"details":"Test visit","unit_charge_currency":"USD","unit_charge_amount":100.00,"diagnosis_pointers":[1]}]}"#;
+    let claim = parse_line(json_line).unwrap();
+    assert_eq!(claim.insurance.relationship, Relationship::Child);
+    assert_eq!(claim.insurance.group_number.as_deref(), Some("GRP-77"));
+    assert_eq!(claim.insurance.subscriber.unwrap().last_name, "Doe");
+
+    // claims written before subscriber details existed are the patient's own coverage
+    let claim = parse_line(&json_line.replace(r#","group_number":"GRP-77","relationship":"19","subscriber":{"first_name":"Robert","last_name":"Doe","dob":"1975-02-01","gender":"m"}"#, "")).unwrap();
+    assert_eq!(claim.insurance.relationship, Relationship::SelfInsured);
+    assert!(claim.insurance.subscriber.is_none());
+}
+
+#[test]
+fn test_parse_837p_other_coverage() {
+    let input = build_837p(&[
+        "CLM*COB001*100***11:B:1",
+        "HI*ABK:J069",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "SBR*S*18*MEDIGAP-G******CI",
+        "NM1*IL*1*DOE*JOHN****MI*ANT123",
+        "NM1*PR*2*ANTHEM*****PI*ANTHEM",
+        "REF*F8*OTHER-ICN",
+        "SBR*T*01*******CI",
+        "NM1*IL*1*DOE*MARY****MI*UHC999",
+        "NM1*PR*2*UNITEDHEALTHCARE*****PI*87726",
+        "LX*1",
+        "SV1*HC:99213*100*UN*1***1",
+    ]);
+    let claims = parse_837p(&input).unwrap();
+    let claim = &claims[0];
+    assert_eq!(claim.insurance.payer_id, PayerId::Medicare);
+    assert_eq!(claim.insurance.patient_member_id, "MED123");
+    assert_eq!(claim.insurance.relationship, Relationship::SelfInsured);
+    // the other payer's claim control number isn't this claim's
+    assert!(claim.payer_claim_control_number.is_none());
+
+    let payers: Vec<&PayerId> = claim.coverages().map(|c| &c.payer_id).collect();
+    assert_eq!(payers, vec![&PayerId::Medicare, &PayerId::Anthem, &PayerId::Other("87726".to_string())]);
+    let secondary = &claim.other_insurance[0];
+    assert_eq!(secondary.patient_member_id, "ANT123");
+    assert_eq!(secondary.group_number.as_deref(), Some("MEDIGAP-G"));
+    assert!(secondary.subscriber.is_none());
+    let tertiary = &claim.other_insurance[1];
+    assert_eq!(tertiary.relationship, Relationship::Spouse);
+    assert_eq!(tertiary.subscriber.as_ref().unwrap().first_name, "MARY");
+    assert!(claim.prior_payments.is_empty());
+
+    let err = parse_837p(&input.replace("MI*UHC999", "")).unwrap_err();
+    assert!(err.to_string().contains("claim COB001 has a 2320 other coverage without a 2330A member id"), "{}", err);
+}
+
+#[test]
+fn test_parse_line_coordination_of_benefits() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"medicare","patient_member_id":"MED123"},"other_insurance":[{"payer_id":"anthem","patient_member_id":"ANT123"}],"prior_payments":[{"payer_id":"medicare","payer_claim_control_number":"R1","service_lines":[{"service_line_id":"SL001","paid_amount":80.0,"adjustments":[{"group_code":"PR","reason_code":"2","amount":20.0},{"group_code":"CO","reason_code":"45","amount":50.0}]}]}],"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1950-01-15"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567893"},"service_lines":[{"service_line_id":"SL001","procedure_code":"99213","units":1,"details":"Test visit","unit_charge_currency":"USD","unit_charge_amount":150.00}]}"#;
+    let claim = parse_line(json_line).unwrap();
+    assert_eq!(claim.other_insurance[0].payer_id, PayerId::Anthem);
+    let prior = claim.prior_line_payment(&claim.service_lines[0]).unwrap();
+    assert_eq!(prior.paid_amount, Money::from_dollars(80.0));
+    assert_eq!(prior.patient_responsibility(), Money::from_dollars(20.0));
+
+    let claim = parse_line(&json_line.replace("SL001\",\"paid", "SL002\",\"paid")).unwrap();
+    assert!(claim.prior_line_payment(&claim.service_lines[0]).is_none());
+}
+
+#[test]
+fn test_parse_837p_dates_of_service() {
+    let input = build_837p(&[
+        "CLM*DOS001*300***11:B:1",
+        "DTP*472*D8*20250801",
+        "HI*ABK:J069",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "LX*1",
+        "SV1*HC:99213*100*UN*1***1",
+        "LX*2",
+        "SV1*HC:97110*200*UN*4***1",
+        "DTP*472*RD8*20250804-20250807",
+    ]);
+    let claim = &parse_837p(&input).unwrap()[0];
+    let date = |s: &str| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    // the claim-level date fills in for lines without their own
+    assert_eq!(claim.service_lines[0].service_date_from, Some(date("2025-08-01")));
+    assert_eq!(claim.service_lines[0].service_date_to, Some(date("2025-08-01")));
+    assert_eq!(claim.service_lines[1].service_date_from, Some(date("2025-08-04")));
+    assert_eq!(claim.service_lines[1].service_date_to, Some(date("2025-08-07")));
+    assert_eq!(claim.date_of_service(), date("2025-08-01"));
+    assert_eq!(claim.line_date_of_service(&claim.service_lines[1]), date("2025-08-04"));
+
+    let err = parse_837p(&input.replace("RD8*20250804-20250807", "RD8*20250804-2025080")).unwrap_err();
+    assert!(err.to_string().contains("invalid RD8 date 20250804-2025080"), "{}", err);
+}
+
+#[test]
+fn test_parse_line_service_dates() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"medicare","patient_member_id":"MED123"},"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1950-01-15"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567893"},"service_lines":[{"service_line_id":"SL001","procedure_code":"99213","units":1,"details":"Test visit","unit_charge_currency":"USD","unit_charge_amount":150.00,"service_date_from":"2025-07-30"}]}"#;
+    let claim = parse_line(json_line).unwrap();
+    assert_eq!(claim.service_lines[0].service_date_from, chrono::NaiveDate::from_ymd_opt(2025, 7, 30));
+    assert!(claim.service_lines[0].service_date_to.is_none());
+
+    // lines without dates fall back to the day the claim was received
+    let claim = parse_line(&json_line.replace(r#","service_date_from":"2025-07-30""#, "")).unwrap();
+    assert_eq!(claim.date_of_service(), claim.received_date());
+
+    let err = parse_line(&json_line.replace("2025-07-30", "2025-07-32")).unwrap_err();
+    assert!(matches!(&err, IntakeError::Parse { field: Some(field), .. } if field == "service_date_from"), "{:?}", err);
+    assert!(err.to_string().contains("invalid date `2025-07-32` in field `service_date_from`"), "{}", err);
+}
+
+#[test]
+fn test_parse_837p_reports_segment_position() {
+    let input = build_837p(&[
+        "CLM*C1*100***11:B:1",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "LX*1",
+        "SV1*HC:99213*abc*UN*1",
+    ]);
+    let err = parse_837p(&input).unwrap_err().to_string();
+    assert!(err.contains("Segment 14 (SV1)"), "{}", err);
+    assert!(err.contains("invalid line charge abc"));
+}
+
+#[test]
+fn test_parse_837p_missing_rendering_provider() {
+    let input = build_837p(&["CLM*C1*100***11:B:1", "LX*1", "SV1*HC:99213*100*UN*1"]);
+    let err = parse_837p(&input).unwrap_err().to_string();
+    assert!(err.contains("Segment 11 (CLM)"), "{}", err);
+    assert!(err.contains("2310B rendering provider"));
+}
+
+#[test]
+fn test_parse_837p_segment_count_mismatch() {
+    let input = build_837p(&[
+        "CLM*C1*100***11:B:1",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "LX*1",
+        "SV1*HC:99213*100*UN*1",
+    ])
+    .replace("SE*13*0001", "SE*99*0001");
+    let err = parse_837p(&input).unwrap_err().to_string();
+    assert!(err.contains("(SE): segment count 99"), "{}", err);
+}
+
+#[test]
+fn test_parse_837p_rejects_non_ascii_zip_and_ein() {
+    let input = build_837p(&[
+        "CLM*C1*100***11:B:1",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "LX*1",
+        "SV1*HC:99213*100*UN*1",
+    ])
+    .replace("SE*13*0001", "SE*14*0001");
+    let billing_provider = "NM1*85*2*TEST CLINIC*****XX*1234567890";
+    let with_segment = |segment: &str| input.replace(billing_provider, &format!("{}~{}", billing_provider, segment));
+
+    // nine bytes, but not nine characters
+    let err = parse_837p(&with_segment("N4*NEW YORK*NY*1234É678")).unwrap_err().to_string();
+    assert!(err.contains("Segment 6 (N4): invalid postal code 1234É678"), "{}", err);
+    let err = parse_837p(&with_segment("REF*EI*1É-345678")).unwrap_err().to_string();
+    assert!(err.contains("Segment 6 (REF): invalid employer identification number 1É-345678"), "{}", err);
+
+    let claims = parse_837p(&with_segment("REF*EI*123456789")).unwrap();
+    assert_eq!(claims[0].organization.ein.as_deref(), Some("12-3456789"));
+}
+
+#[test]
+fn test_parse_837p_rejects_non_x12_input() {
+    let err = parse_837p(r#"{"claim_id":"TEST001"}"#).unwrap_err().to_string();
+    assert!(err.contains("ISA header"));
+}
+
+#[test]
+fn test_parse_837p_invalid_date_of_birth() {
+    let input = build_837p(&["CLM*C1*100***11:B:1"]).replace("DMG*D8*19800115*M", "DMG*D8*19801345*M");
+    let err = parse_837p(&input).unwrap_err().to_string();
+    assert!(err.contains("Segment 9 (DMG): invalid date of birth 19801345"), "{}", err);
+}
+
+#[test]
+fn test_parse_line_error_carries_claim_id_and_field() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11}"#;
+    let err = parse_line(json_line).unwrap_err();
+
+    assert!(matches!(&err, IntakeError::Parse { claim_id: Some(id), field: Some(field), .. } if id == "TEST001" && field == "insurance"), "{:?}", err);
+    assert_eq!(err.claim_id(), Some("TEST001"));
+    assert!(err.to_string().starts_with("Claim TEST001: Failed to parse line"));
+    assert!(!err.is_retryable());
+}
+
+#[test]
+fn test_parse_line_invalid_date_of_birth() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"medicare","patient_member_id":"MED123"},"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1985-13-45"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567893"},"service_lines":[]}"#;
+    let err = parse_line(json_line).unwrap_err();
+    assert!(matches!(&err, IntakeError::Parse { claim_id: Some(id), field: Some(field), .. } if id == "TEST001" && field == "dob"), "{:?}", err);
+    assert!(err.to_string().contains("invalid date `1985-13-45` in field `dob` (expected YYYY-MM-DD)"), "{}", err);
+
+    assert!(parse_line(&json_line.replace("1985-13-45", "")).is_err());
+    assert!(parse_line(&json_line.replace("1985-13-45", "01/15/1985")).is_err());
+    let claim = parse_line(&json_line.replace("1985-13-45", "1985-12-31")).unwrap();
+    assert_eq!(claim.patient.dob, chrono::NaiveDate::from_ymd_opt(1985, 12, 31).unwrap());
+    assert_eq!(claim.patient.medicare_entitlement, None);
+}
+
+#[test]
+fn test_patient_age() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"medicare","patient_member_id":"MED123"},"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1957-03-01","medicare_entitlement":"esrd"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567893"},"service_lines":[{"service_line_id":"SL001","procedure_code":"99213","units":1,"details":"Test visit","unit_charge_currency":"USD","unit_charge_amount":150.00,"service_date_from":"2022-02-28"}]}"#;
+    let date = |s: &str| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let claim = parse_line(json_line).unwrap();
+    assert_eq!(claim.patient.medicare_entitlement, Some(MedicareEntitlement::Esrd));
+    assert_eq!(claim.patient_age(), 64);
+    assert_eq!(claim.patient.age_on(date("2022-03-01")), 65);
+    assert_eq!(claim.patient.age_on(date("1957-03-01")), 0);
+    assert_eq!(claim.patient.age_on(date("1950-01-01")), 0);
+
+    // born on February 29th: a year older on March 1st in common years
+    let leap = parse_line(&json_line.replace("1957-03-01", "1960-02-29")).unwrap();
+    assert_eq!(leap.patient.age_on(date("2021-02-28")), 60);
+    assert_eq!(leap.patient.age_on(date("2021-03-01")), 61);
+}
+
+#[test]
+fn test_read_file_error_is_retryable() {
+    let err = read_file(&create_test_config("nonexistent_file.json")).err().unwrap();
+    assert!(matches!(err, IntakeError::Io { ref path, .. } if path == "nonexistent_file.json"));
+    assert!(err.is_retryable());
+}
+
+#[test]
+fn test_config_build_outage_rate_flag() {
+    let build = |flag: &str| Config::build(["app", "claims.txt", "5", "10", flag].iter().map(|s| s.to_string()));
+    assert_eq!(build("--outage-rate=0.25").unwrap().outage_rate, 0.25);
+    assert_eq!(build("--seed=1").unwrap().outage_rate, 0.0);
+    assert!(matches!(build("--outage-rate=1.5"), Err(IntakeError::Config { field, .. }) if field == "outage_rate"));
+    assert!(matches!(build("--outage-rate=often"), Err(IntakeError::Config { field, .. }) if field == "outage_rate"));
+}
+
+#[test]
+fn test_config_build_plan_year_start_flag() {
+    let build = |flag: &str| Config::build(["app", "claims.txt", "5", "10", flag].iter().map(|s| s.to_string()));
+    assert_eq!(build("--plan-year-start=07-01").unwrap().plan_year_start, Some((7, 1)));
+    assert_eq!(build("--seed=1").unwrap().plan_year_start, None);
+    for invalid in ["--plan-year-start=02-29", "--plan-year-start=13-01", "--plan-year-start=July"] {
+        assert!(matches!(build(invalid), Err(IntakeError::Config { field, .. }) if field == "plan_year_start"), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_config_build_npi_registry_flag() {
+    let args = ["app", "claims.txt", "5", "10", "--npi-registry=npi_registry.csv"].iter().map(|s| s.to_string());
+    assert_eq!(Config::build(args).unwrap().npi_registry.as_deref(), Some("npi_registry.csv"));
+    let args = ["app", "claims.txt", "5", "10"].iter().map(|s| s.to_string());
+    assert_eq!(Config::build(args).unwrap().npi_registry, None);
+}
+
+#[test]
+fn test_config_build_reference_file_flags() {
+    let args = ["app", "claims.txt", "5", "10", "--modifier-table=mods.csv", "--procedure-codes=cpt.csv", "--duplicate-store=seen.jsonl", "--payers=payers.csv", "--roster=roster.csv", "--exchange-rates=rates.csv"]
+        .iter()
+        .map(|s| s.to_string());
+    let config = Config::build(args).unwrap();
+    assert_eq!(config.modifier_table.as_deref(), Some("mods.csv"));
+    assert_eq!(config.procedure_codes.as_deref(), Some("cpt.csv"));
+    assert_eq!(config.duplicate_store.as_deref(), Some("seen.jsonl"));
+    assert_eq!(config.payers.as_deref(), Some("payers.csv"));
+    assert_eq!(config.roster.as_deref(), Some("roster.csv"));
+    assert_eq!(config.exchange_rates.as_deref(), Some("rates.csv"));
+}
+
+#[test]
+fn test_parse_line_diagnoses_and_pointers() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"medicare","patient_member_id":"MED123"},"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1980-01-15"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567893"},"diagnoses":[{"code":"E11.9","primary":true},{"code":"I10"}],"service_lines":[{"service_line_id":"SL001","procedure_code":"99213","units":1,"details":"Test visit","unit_charge_currency":"USD","unit_charge_amount":100.00,"diagnosis_pointers":[2,1,7]}]}"#;
+    let claim = parse_line(json_line).unwrap();
+
+    assert_eq!(claim.diagnoses.len(), 2);
+    assert!(claim.diagnoses[0].primary);
+    assert!(!claim.diagnoses[1].primary);
+    assert_eq!(claim.diagnoses[0].normalized_code(), "E119");
+
+    // the out-of-range pointer is left for validation to report
+    let codes: Vec<&str> = claim.line_diagnoses(&claim.service_lines[0]).map(|d| d.code.as_str()).collect();
+    assert_eq!(codes, vec!["I10", "E11.9"]);
+}
+
+#[test]
+fn test_parse_837p_diagnoses_and_pointers() {
+    let input = build_837p(&[
+        "CLM*C1*100***11:B:1",
+        "HI*ABK:E119*ABF:I10*BG:01",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "LX*1",
+        "SV1*HC:99213*100*UN*1***2:1",
+    ]);
+    let claim = &parse_837p(&input).unwrap()[0];
+
+    assert_eq!(claim.diagnoses, vec![
+        Diagnosis { code: "E119".to_string(), primary: true },
+        Diagnosis { code: "I10".to_string(), primary: false },
+    ]);
+    assert_eq!(claim.service_lines[0].diagnosis_pointers, vec![2, 1]);
+
+    let bad_pointer = input.replace("***2:1", "***A");
+    let err = parse_837p(&bad_pointer).unwrap_err().to_string();
+    assert!(err.contains("invalid diagnosis pointer A"), "{}", err);
+}
+
+#[test]
+fn test_place_of_service_table() {
+    let office = place_of_service(11).unwrap();
+    assert_eq!(office.name, "Office");
+    assert!(!office.facility);
+    assert!(place_of_service(22).unwrap().facility);
+    assert!(place_of_service(23).unwrap().facility);
+    assert!(place_of_service(30).is_none());
+    assert!(place_of_service(0).is_none());
+
+    let codes: Vec<i32> = PLACES_OF_SERVICE.iter().map(|place| place.code).collect();
+    assert!(codes.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_parse_line_frequency_code() {
+    let json_line = r#"{"claim_id":"TEST001","place_of_service_code":11,"insurance":{"payer_id":"medicare","patient_member_id":"MED123"},"patient":{"first_name":"John","last_name":"Doe","gender":"m","dob":"1980-01-15"},"organization":{"name":"Test Clinic"},"rendering_provider":{"first_name":"Dr. Test","last_name":"Provider","npi":"1234567893"},"frequency_code":"7","payer_claim_control_number":"ICN001","service_lines":[]}"#;
+    let claim = parse_line(json_line).unwrap();
+    assert_eq!(claim.frequency_code, ClaimFrequency::Replacement);
+    assert_eq!(claim.payer_claim_control_number.as_deref(), Some("ICN001"));
+
+    let original = parse_line(&json_line.replace(r#""frequency_code":"7","payer_claim_control_number":"ICN001","#, "")).unwrap();
+    assert_eq!(original.frequency_code, ClaimFrequency::Original);
+    assert_eq!(original.payer_claim_control_number, None);
+
+    assert!(parse_line(&json_line.replace(r#""frequency_code":"7""#, r#""frequency_code":"5""#)).is_err());
+}
+
+#[test]
+fn test_parse_837p_frequency_code_and_control_number() {
+    let input = build_837p(&[
+        "CLM*C1*100***11:B:8",
+        "REF*F8*ICN001",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "LX*1",
+        "SV1*HC:99213*100*UN*1",
+    ]);
+    let claim = &parse_837p(&input).unwrap()[0];
+    assert_eq!(claim.frequency_code, ClaimFrequency::Void);
+    assert_eq!(claim.payer_claim_control_number.as_deref(), Some("ICN001"));
+
+    let err = parse_837p(&input.replace("11:B:8", "11:B:5")).unwrap_err().to_string();
+    assert!(err.contains("unsupported claim frequency code 5"), "{}", err);
+}
+
+#[test]
+fn test_parse_837p_other_payer() {
+    let input = build_837p(&[
+        "CLM*C1*100***11:B:1",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "LX*1",
+        "SV1*HC:99213*100*UN*1",
+    ]);
+    let input = input.replace("NM1*PR*2*MEDICARE*****PI*MEDICARE", "NM1*PR*2*AETNA*****PI*60054");
+    assert_eq!(parse_837p(&input).unwrap()[0].insurance.payer_id, PayerId::Other("60054".to_string()));
+
+    // the id wins over a payer name that looks like a built-in payer's
+    let medigap = input.replace("NM1*PR*2*AETNA*****PI*60054", "NM1*PR*2*AARP MEDICARE SUPPLEMENT*****PI*36273");
+    assert_eq!(parse_837p(&medigap).unwrap()[0].insurance.payer_id, PayerId::Other("36273".to_string()));
+    let builtin = input.replace("NM1*PR*2*AETNA*****PI*60054", "NM1*PR*2*ANTHEM BLUE CROSS*****PI*Anthem");
+    assert_eq!(parse_837p(&builtin).unwrap()[0].insurance.payer_id, PayerId::Anthem);
+    // only a payer without an id is recognized by name
+    let idless = input.replace("NM1*PR*2*AETNA*****PI*60054", "NM1*PR*2*UHC OF CALIFORNIA");
+    assert_eq!(parse_837p(&idless).unwrap()[0].insurance.payer_id, PayerId::UnitedHealthGroup);
+
+    let nameless = input.replace("NM1*PR*2*AETNA*****PI*60054", "NM1*PR*2");
+    assert!(parse_837p(&nameless).unwrap_err().to_string().contains("neither a name nor an id"));
+}
+
+#[test]
+fn test_money_parses_and_formats_exact_cents() {
+    assert_eq!("123.45".parse::<Money>().unwrap(), Money::from_cents(12345));
+    assert_eq!("-5.5".parse::<Money>().unwrap(), Money::from_cents(-550));
+    assert_eq!("100".parse::<Money>().unwrap().to_string(), "100.00");
+    assert!("1.005".parse::<Money>().unwrap_err().contains("fraction of a cent"));
+    assert!("12a".parse::<Money>().is_err());
+    assert!(serde_json::from_str::<Money>("0.001").is_err());
+    assert_eq!(serde_json::from_str::<Money>("0.1").unwrap(), Money::from_cents(10));
+}
+
+#[test]
+fn test_money_shares_round_half_away_from_zero_and_add_back_up() {
+    let billed = Money::from_cents(3333);
+    let paid = billed.times(0.75);
+    assert_eq!(paid, Money::from_cents(2500)); // 24.9975 rounds up
+    assert_eq!(paid + (billed - paid), billed);
+    assert_eq!(Money::from_cents(5).times(0.5), Money::from_cents(3));
+    assert_eq!(Money::from_cents(-5).times(0.5), Money::from_cents(-3));
+    assert_eq!(Money::from_cents(100).divided_by(3), Money::from_cents(33));
+    assert_eq!(Money::from_cents(100).divided_by(0), Money::ZERO);
+    assert_eq!(Money::from_cents(1).checked_mul(i32::MAX), Some(Money::from_cents(i64::from(i32::MAX))));
+}
+
+#[test]
+fn test_parse_837p_rejects_line_charge_that_does_not_split_across_units() {
+    let input = build_837p(&[
+        "CLM*C1*100.01***11:B:1",
+        "NM1*82*1*SMITH*JANE****XX*9876543210",
+        "LX*1",
+        "SV1*HC:97110*100.01*UN*2",
+    ]);
+    let err = parse_837p(&input).unwrap_err().to_string();
+    assert!(err.contains("line charge 100.01 doesn't divide evenly into 2 units"), "{}", err);
+}
+
+// Reads one line, then fails the way a dropped network mount would
+struct FailingReader(bool);
+
+impl std::io::Read for FailingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.0 {
+            return Err(std::io::Error::other("connection reset"));
+        }
+        self.0 = true;
+        buf[..6].copy_from_slice(b"first\n");
+        Ok(6)
+    }
+}
+
+#[test]
+fn test_line_reader_reports_line_numbers_and_offsets() {
+    let input = "{\"a\":1}\r\n\n{\"b\":2}";
+    let lines: Vec<Line> = LineReader::new(input.as_bytes(), "claims.json").map(Result::unwrap).collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].text, "{\"a\":1}");
+    assert_eq!(lines[0].position, LinePosition { line_number: 1, offset: 0, next_offset: 9 });
+    assert_eq!(lines[1].text, "");
+    assert_eq!(lines[1].position, LinePosition { line_number: 2, offset: 9, next_offset: 10 });
+    // the last line needn't end with a newline
+    assert_eq!(lines[2].text, "{\"b\":2}");
+    assert_eq!(lines[2].position, LinePosition { line_number: 3, offset: 10, next_offset: 17 });
+}
+
+#[test]
+fn test_line_reader_surfaces_read_errors() {
+    let mut lines = LineReader::new(std::io::BufReader::new(FailingReader(false)), "claims.json");
+    assert_eq!(lines.next().unwrap().unwrap().text, "first");
+    let err = lines.next().unwrap().unwrap_err();
+    assert_eq!(err, IntakeError::Read {
+        path: "claims.json".to_string(),
+        line_number: 2,
+        offset: 6,
+        message: "connection reset".to_string(),
+    });
+    assert_eq!(err.to_string(), "Failed to read claims.json at line 2 (byte 6): connection reset");
+    assert!(err.is_retryable());
+    // nothing after a failed read can be trusted to line up
+    assert!(lines.next().is_none());
+}
+
+#[test]
+fn test_line_reader_skips_lines_it_cannot_hold() {
+    let long_line = "x".repeat(MAX_LINE_BYTES + 10);
+    let mut input = format!("one\n{}\n", long_line).into_bytes();
+    input.extend_from_slice(b"caf\xe9\nfour\n");
+    let mut lines = LineReader::new(input.as_slice(), "claims.json");
+
+    assert_eq!(lines.next().unwrap().unwrap().text, "one");
+    let err = lines.next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("Line 2 is longer than 1048576 bytes"), "{}", err);
+    assert!(!err.is_retryable());
+    let err = lines.next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("Line 3 is not valid UTF-8"), "{}", err);
+    let line = lines.next().unwrap().unwrap();
+    assert_eq!(line.text, "four");
+    assert_eq!(line.position.line_number, 4);
+    assert_eq!(line.position.offset, 4 + long_line.len() as u64 + 1 + 5);
+    assert!(lines.next().is_none());
+}
+
+#[test]
+fn test_line_reader_resumes_from_an_offset() {
+    let input = "first\nsecond\nthird\n";
+    let mut lines = LineReader::new(input.as_bytes(), "claims.json").resume_from(6).unwrap();
+    assert_eq!((lines.line_number(), lines.offset()), (1, 6));
+    let line = lines.next().unwrap().unwrap();
+    assert_eq!(line.text, "second");
+    assert_eq!(line.position, LinePosition { line_number: 2, offset: 6, next_offset: 13 });
+
+    let err = LineReader::new(input.as_bytes(), "claims.json").resume_from(8).err().unwrap();
+    assert_eq!(err.to_string(), "Resume offset 8 is partway through line 2 of claims.json");
+    let err = LineReader::new(input.as_bytes(), "claims.json").resume_from(100).err().unwrap();
+    assert_eq!(err.to_string(), "Resume offset 100 is past the end of claims.json");
+    // resuming at the very end reads nothing
+    assert!(LineReader::new(input.as_bytes(), "claims.json").resume_from(19).unwrap().next().is_none());
+}
+
+#[test]
+fn test_read_file_resumes_from_config_offset() {
+    let path = get_fixture_path("multiple_claims.json");
+    let first_line = fs::read_to_string(&path).unwrap().lines().next().unwrap().len() as u64 + 1;
+    let config = Config { resume_offset: Some(first_line), ..create_test_config(&path) };
+    let lines: Vec<Line> = read_file(&config).unwrap().map(Result::unwrap).collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].position.line_number, 2);
+    assert!(lines[0].text.contains("MULTI002"));
+}
+
+#[test]
+fn test_config_build_resume_flag() {
+    let build = |path: &str, flag: &str| Config::build(["app", path, "5", "10", flag].iter().map(|s| s.to_string()));
+    assert_eq!(build("claims.json", "--resume-from=1024").unwrap().resume_offset, Some(1024));
+    assert_eq!(build("claims.json", "--seed=1").unwrap().resume_offset, None);
+    assert!(matches!(build("claims.json", "--resume-from=-1"), Err(IntakeError::Config { field, .. }) if field == "resume_from"));
+    let err = build("claims.837", "--resume-from=1024").err().unwrap();
+    assert_eq!(err.to_string(), "Can only resume reading JSON lines input");
+}
+
+#[test]
+fn test_resume_point_waits_for_the_earliest_unfinished_line() {
+    let position = |offset, next_offset| LinePosition { line_number: 0, offset, next_offset };
+    let mut resume = ResumePoint::new(100);
+    assert_eq!(resume.offset(), 100);
+
+    resume.started(&position(100, 150));
+    resume.skipped(&position(150, 160));
+    resume.started(&position(160, 200));
+    assert_eq!(resume.offset(), 100);
+    // a later line finishing first doesn't move it
+    resume.finished(160);
+    assert_eq!(resume.offset(), 100);
+    resume.finished(100);
+    assert_eq!(resume.offset(), 200);
+}